@@ -5,23 +5,23 @@
 //! Foundry Local Web Server vision example (Responses API).
 //!
 //! Mirrors `samples/python/web-server-responses-vision`. Starts the local
-//! Foundry web service, posts a multimodal request to `/v1/responses` with a
-//! base64-encoded image, and streams the SSE response, printing each
-//! `response.output_text.delta` event.
+//! Foundry web service, sends a multimodal request to `/v1/responses` with a
+//! base64-encoded image through the SDK's `ResponsesClient`, and prints each
+//! `response.output_text.delta` event as it streams in.
 
 // <imports>
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
 use base64::Engine;
-use futures_util::StreamExt;
-use serde_json::{json, Value};
+use tokio_stream::StreamExt;
 
+use foundry_local_sdk::openai::{MessageRole, ResponseContentPart, ResponseItem, ResponseStreamEvent};
 use foundry_local_sdk::{FoundryLocalConfig, FoundryLocalManager};
 // </imports>
 
 const DEFAULT_MODEL_ALIAS: &str = "qwen3.5-0.8b";
-const DEFAULT_MAX_OUTPUT_TOKENS: u64 = 8192;
+const DEFAULT_MAX_OUTPUT_TOKENS: u32 = 8192;
 
 fn print_usage() {
     eprintln!("Usage: cargo run -p foundry-local-webserver-responses-vision -- <model_alias_or_id> [image_path]");
@@ -222,80 +222,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     manager.start_web_service().await?;
     println!("done.");
 
-    let urls = manager.urls()?;
-    let endpoint = urls
-        .first()
-        .expect("Web service did not return an endpoint");
-    let base_url = format!("{}/v1", endpoint.trim_end_matches('/'));
-    println!("Web service listening on: {base_url}");
+    let client = manager.create_responses_client(Some(model.id()))?;
+    println!("Web service listening on: {}", manager.urls()?.join(", "));
     // </server_setup>
 
     // <inference>
     println!("\nPreparing image: {}", image_path.display());
     let (image_b64, media_type) = encode_image(&image_path)?;
 
-    // The Foundry Local Responses API accepts an array of message items with input_text /
-    // input_image content parts. The input_image part uses Foundry-specific `image_data` and
-    // `media_type` fields (in place of OpenAI's `image_url`).
-    let vision_input = json!([
-        {
-            "type": "message",
-            "role": "user",
-            "content": [
-                { "type": "input_text", "text": "Describe this image." },
-                { "type": "input_image", "image_data": image_b64, "media_type": media_type }
-            ]
-        }
-    ]);
-
-    let body = json!({
-        "model": model.id(),
-        "input": vision_input,
-        "max_output_tokens": DEFAULT_MAX_OUTPUT_TOKENS,
-        "stream": true,
-    });
+    // The Foundry Local Responses API accepts message items with input_text / input_image
+    // content parts. The input_image part uses Foundry-specific `image_data` and `media_type`
+    // fields (in place of OpenAI's `image_url`).
+    let vision_input = vec![ResponseItem::message(
+        MessageRole::User,
+        vec![
+            ResponseContentPart::input_text("Describe this image."),
+            ResponseContentPart::input_image(image_b64, media_type),
+        ],
+    )];
 
     println!("\nStreaming vision response...");
-    // No request timeout: streamed vision responses can take a while to complete.
-    // (reqwest treats a zero Duration as "expire immediately" rather than "no timeout",
-    // so the timeout is simply left unset here.)
-    let client = reqwest::Client::builder().build()?;
-    let response = client
-        .post(format!("{base_url}/responses"))
-        .bearer_auth("notneeded")
-        .header("Accept", "text/event-stream")
-        .json(&body)
-        .send()
-        .await?
-        .error_for_status()?;
+    let mut stream = client
+        .request(vision_input)
+        .max_output_tokens(DEFAULT_MAX_OUTPUT_TOKENS)
+        .stream()
+        .await?;
 
     print!("[ASSISTANT]: ");
     io::stdout().flush().ok();
 
-    let mut stream = response.bytes_stream();
-    let mut buf = String::new();
-    'outer: while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        buf.push_str(&String::from_utf8_lossy(&chunk));
-        while let Some(nl) = buf.find('\n') {
-            let line = buf[..nl].trim_end().to_string();
-            buf.drain(..=nl);
-            let Some(data) = line.strip_prefix("data: ") else {
-                continue;
-            };
-            if data == "[DONE]" {
-                break 'outer;
-            }
-            if let Ok(event) = serde_json::from_str::<Value>(data) {
-                if event.get("type").and_then(|t| t.as_str())
-                    == Some("response.output_text.delta")
-                {
-                    if let Some(delta) = event.get("delta").and_then(|d| d.as_str()) {
-                        print!("{delta}");
-                        io::stdout().flush().ok();
-                    }
-                }
-            }
+    while let Some(event) = stream.next().await {
+        if let ResponseStreamEvent::OutputTextDelta { delta, .. } = event? {
+            print!("{delta}");
+            io::stdout().flush().ok();
         }
     }
     println!();
@@ -6,6 +6,7 @@ use std::io::{self, Write};
 use foundry_local_sdk::{
     ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
     ChatCompletionRequestUserMessage, FoundryLocalConfig, FoundryLocalError, FoundryLocalManager,
+    PrepareProgress,
 };
 use tokio_stream::StreamExt;
 
@@ -35,19 +36,15 @@ async fn main() -> Result<()> {
         .or_else(|| models.first().map(|m| m.alias().to_string()))
         .expect("No models available in the catalog");
 
-    let model = manager.catalog().get_model(&model_alias).await?;
-
-    if !model.is_cached().await? {
-        println!("Downloading model '{}'…", model.alias());
-        model
-            .download(Some(|progress: f64| {
-                println!("  {progress:.1}%");
-            }))
-            .await?;
-    }
-
-    println!("Loading model '{}'…", model.alias());
-    model.load().await?;
+    let model = manager
+        .prepare(&model_alias)
+        .progress(|progress| match progress {
+            PrepareProgress::Downloading { percent } => println!("  downloading {percent:.1}%"),
+            PrepareProgress::Loading => println!("Loading model…"),
+            _ => {}
+        })
+        .run()
+        .await?;
 
     // ── 4. Synchronous chat completion ───────────────────────────────────
     let client = model.create_chat_client().temperature(0.7).max_tokens(256);
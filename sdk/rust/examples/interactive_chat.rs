@@ -4,11 +4,7 @@
 
 use std::io::{self, Write};
 
-use foundry_local_sdk::{
-    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
-    ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage, FoundryLocalConfig,
-    FoundryLocalManager,
-};
+use foundry_local_sdk::{FoundryLocalConfig, FoundryLocalManager};
 use tokio_stream::StreamExt;
 
 #[tokio::main]
@@ -50,11 +46,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Ready! Type your messages below. Press Ctrl-D (or type 'quit') to exit.\n");
 
     // ── Chat loop ────────────────────────────────────────────────────────
-    let client = model.create_chat_client().temperature(0.7).max_tokens(512);
-
-    let mut messages: Vec<ChatCompletionRequestMessage> = vec![
-        ChatCompletionRequestSystemMessage::from("You are a helpful, concise assistant.").into(),
-    ];
+    // The session keeps the history and trims it to the model's context window.
+    let mut session = model
+        .create_chat_session()
+        .configure_client(|c| c.temperature(0.7).max_tokens(512))
+        .system_prompt("You are a helpful, concise assistant.");
 
     loop {
         print!("You: ");
@@ -73,34 +69,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             break;
         }
 
-        messages.push(ChatCompletionRequestUserMessage::from(input).into());
-
-        // Stream the response token by token
+        // Stream the response token by token; the session records the
+        // reply once the stream ends.
         print!("Assistant: ");
         io::stdout().flush()?;
 
-        let mut full_response = String::new();
-        let mut stream = client.complete_streaming_chat(&messages, None).await?;
+        let mut stream = session.send_streaming(input).await?;
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
             if let Some(choice) = chunk.choices.first() {
                 if let Some(ref content) = choice.delta.content {
                     print!("{content}");
                     io::stdout().flush().ok();
-                    full_response.push_str(content);
                 }
             }
         }
         println!("\n");
-
-        // Add assistant reply to history for multi-turn conversation
-        messages.push(
-            ChatCompletionRequestAssistantMessage {
-                content: Some(full_response.into()),
-                ..Default::default()
-            }
-            .into(),
-        );
     }
 
     // ── Cleanup ──────────────────────────────────────────────────────────
@@ -1,20 +1,38 @@
 //! Model catalog – discovers, caches, and looks up available models.
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use crate::detail::core_interop::CoreInterop;
+use serde_json::json;
+
+use crate::configuration::store_cache_location;
+use crate::detail::cache_lock::{self, CacheLocks};
+use crate::detail::cache_maintenance::{
+    self, CacheReport, CleanupSummary, KnownModels, MoveMethod,
+};
+use crate::detail::core_interop::{get_cache_directory, CoreInterop};
 use crate::detail::model::Model;
+use crate::detail::model_archive;
 use crate::detail::model_variant::ModelVariant;
-use crate::detail::ModelLoadManager;
+use crate::detail::preflight::LicenseStore;
+use crate::detail::{run_blocking, ModelLoadManager, ResidencyManager};
 use crate::error::{FoundryLocalError, Result};
 use crate::types::ModelInfo;
 
 /// How long the catalog cache remains valid before a refresh.
 const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60); // 6 hours
 
+/// Partial and orphaned entries modified more recently than this may belong
+/// to a download in progress and are left alone by [`Catalog::cleanup`].
+const CLEANUP_GRACE: Duration = Duration::from_secs(60 * 60); // 1 hour
+
+/// Provider reported by the native core for models found in the cache
+/// directory that are not catalog variants.
+const BYOM_PROVIDER: &str = "Local";
+
 /// Shared flag allowing `ModelVariant` to signal that the catalog cache is
 /// stale (e.g. after a download or removal).
 #[derive(Clone, Debug)]
@@ -47,6 +65,11 @@ struct CatalogState {
 pub struct Catalog {
     core: Arc<CoreInterop>,
     model_load_manager: Arc<ModelLoadManager>,
+    residency: Arc<ResidencyManager>,
+    license_store: Arc<LicenseStore>,
+    cache_locks: Arc<CacheLocks>,
+    /// Where [`Self::relocate`] records the new cache directory.
+    cache_location_file: PathBuf,
     name: String,
     state: Mutex<CatalogState>,
     /// Async gate ensuring only one refresh runs at a time.
@@ -58,6 +81,10 @@ impl Catalog {
     pub(crate) fn new(
         core: Arc<CoreInterop>,
         model_load_manager: Arc<ModelLoadManager>,
+        residency: Arc<ResidencyManager>,
+        license_store: Arc<LicenseStore>,
+        cache_locks: Arc<CacheLocks>,
+        cache_location_file: PathBuf,
     ) -> Result<Self> {
         let name = core
             .execute_command("get_catalog_name", None)
@@ -67,6 +94,10 @@ impl Catalog {
         let catalog = Self {
             core,
             model_load_manager,
+            residency,
+            license_store,
+            cache_locks,
+            cache_location_file,
             name,
             state: Mutex::new(CatalogState {
                 models_by_alias: HashMap::new(),
@@ -205,6 +236,143 @@ impl Catalog {
         self.resolve_model_ids(&loaded_ids).await
     }
 
+    /// Verify and install a model archive created by
+    /// [`Model::export`](crate::Model::export).
+    ///
+    /// Every file is checked against the size and SHA-256 digest recorded in
+    /// the archive before the variant is placed in the model cache, using the
+    /// same layout as on the exporting machine.  Returns the installed variant,
+    /// which reports itself as cached.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the archive is corrupt or tampered with, if the variant
+    /// already exists in the cache, or with
+    /// [`FoundryLocalError::CacheLocked`] if another operation holds the
+    /// variant's cache lock.
+    pub async fn import(&self, archive: impl AsRef<Path>) -> Result<Arc<Model>> {
+        let archive = archive.as_ref().to_path_buf();
+        let path = archive.clone();
+        let model_id = run_blocking(move || model_archive::read_archive_info(&path))
+            .await?
+            .id;
+        let _lock = self.cache_locks.acquire(&model_id, "import").await?;
+        let cache_dir = get_cache_directory(&self.core).await?;
+
+        let info =
+            run_blocking(move || model_archive::install_archive(&archive, &cache_dir)).await?;
+
+        self.invalidator.invalidate();
+        self.get_model_variant(&info.id).await
+    }
+
+    /// Classify everything in the model cache directory as a cached catalog
+    /// variant, a BYOM model, a partial download or an orphaned directory,
+    /// with the size of each entry.  Complete models that are neither in the
+    /// catalog nor BYOM models count as orphaned.
+    pub async fn cache_report(&self) -> Result<CacheReport> {
+        let cache_dir = get_cache_directory(&self.core).await?;
+        let known = self.known_models().await?;
+        run_blocking(move || cache_maintenance::scan(&cache_dir, &known)).await
+    }
+
+    /// Delete partial downloads and orphaned directories from the model
+    /// cache, returning what was removed and how much space was reclaimed.
+    ///
+    /// Cached catalog variants and BYOM models are never touched.  Entries
+    /// locked by another process are skipped, as are those modified within
+    /// the last hour unless their lock shows that the process downloading
+    /// them exited without releasing it.
+    pub async fn cleanup(&self) -> Result<CleanupSummary> {
+        let report = self.cache_report().await?;
+        let summary =
+            run_blocking(move || cache_maintenance::cleanup(report, CLEANUP_GRACE)).await?;
+        if !summary.removed.is_empty() {
+            self.invalidator.invalidate();
+        }
+        Ok(summary)
+    }
+
+    /// Move the model cache to `new_dir` and switch the native core to it.
+    ///
+    /// The cache is renamed when `new_dir` is on the same volume, and
+    /// otherwise copied, verified and only then removed from the old
+    /// location.  If the core rejects the new directory the move is undone.
+    /// The new directory is recorded in the app data directory and used on
+    /// later starts, unless
+    /// [`FoundryLocalConfig::model_cache_dir`](crate::FoundryLocalConfig::model_cache_dir)
+    /// is set.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any model is loaded, if a download, removal or other
+    /// operation holds a cache lock ([`FoundryLocalError::CacheLocked`]), if
+    /// `new_dir` is relative, not empty, or overlaps the current cache
+    /// directory, or if the move fails.  When undoing a failed move also
+    /// fails, the error says so and where the cache was left.
+    pub async fn relocate(&self, new_dir: impl AsRef<Path>) -> Result<()> {
+        let new_dir = new_dir.as_ref().to_path_buf();
+        if !new_dir.is_absolute() {
+            return Err(FoundryLocalError::Validation {
+                reason: format!(
+                    "model cache directory must be an absolute path, got '{}'",
+                    new_dir.display()
+                ),
+            });
+        }
+
+        let loaded = self.model_load_manager.list_loaded().await?;
+        if !loaded.is_empty() {
+            return Err(FoundryLocalError::ModelOperation {
+                reason: format!(
+                    "cannot relocate the model cache while models are loaded: {loaded:?}"
+                ),
+            });
+        }
+
+        let old_dir = get_cache_directory(&self.core).await?;
+        if old_dir == new_dir {
+            return Ok(());
+        }
+
+        let (from, to) = (old_dir.clone(), new_dir.clone());
+        let method = run_blocking(move || {
+            cache_lock::ensure_unlocked(&from)?;
+            cache_maintenance::move_cache(&from, &to)
+        })
+        .await?;
+
+        if let Err(e) = self.set_cache_directory(&new_dir).await {
+            let undone = self.undo_move(&old_dir, &new_dir, method).await;
+            return Err(with_rollback(e, undone, &old_dir, &new_dir));
+        }
+
+        let (file, dir) = (self.cache_location_file.clone(), new_dir.clone());
+        if let Err(e) = run_blocking(move || store_cache_location(&file, &dir)).await {
+            let undone = match self.set_cache_directory(&old_dir).await {
+                Ok(()) => self.undo_move(&old_dir, &new_dir, method).await,
+                Err(restore) => Err(restore),
+            };
+            return Err(with_rollback(e, undone, &old_dir, &new_dir));
+        }
+
+        self.invalidator.invalidate();
+        run_blocking(move || cache_maintenance::finish_move(&old_dir, method)).await
+    }
+
+    async fn set_cache_directory(&self, dir: &Path) -> Result<()> {
+        let params = json!({ "Params": { "Directory": dir.to_string_lossy() } });
+        self.core
+            .execute_command_async("set_cache_directory".into(), Some(params))
+            .await?;
+        Ok(())
+    }
+
+    async fn undo_move(&self, old_dir: &Path, new_dir: &Path, method: MoveMethod) -> Result<()> {
+        let (from, to) = (old_dir.to_path_buf(), new_dir.to_path_buf());
+        run_blocking(move || cache_maintenance::revert_move(&from, &to, method)).await
+    }
+
     /// Resolve a list of model ids against the in-memory catalog, self-healing
     /// once if any id is unknown (e.g. a manually-added BYOM model the SDK has
     /// not yet seen). Preserves the input order of `model_ids` (minus unknowns).
@@ -290,6 +458,9 @@ impl Catalog {
                 info,
                 Arc::clone(&self.core),
                 Arc::clone(&self.model_load_manager),
+                Arc::clone(&self.residency),
+                Arc::clone(&self.license_store),
+                Arc::clone(&self.cache_locks),
                 self.invalidator.clone(),
             );
             id_map.insert(id, Arc::new(Model::from_variant(variant.clone())));
@@ -346,6 +517,21 @@ impl Catalog {
         Ok(())
     }
 
+    /// Ids of the catalog variants and of the BYOM models.
+    async fn known_models(&self) -> Result<KnownModels> {
+        self.update_models().await?;
+        let s = self.lock_state()?;
+        let mut known = KnownModels::default();
+        for (id, model) in &s.variants_by_id {
+            if model.info().provider_type == BYOM_PROVIDER {
+                known.byom.insert(id.clone());
+            } else {
+                known.catalog.insert(id.clone());
+            }
+        }
+        Ok(known)
+    }
+
     fn lock_state(&self) -> Result<std::sync::MutexGuard<'_, CatalogState>> {
         self.state.lock().map_err(|_| FoundryLocalError::Internal {
             reason: "catalog state mutex poisoned".into(),
@@ -353,6 +539,26 @@ impl Catalog {
     }
 }
 
+/// `error`, extended with the failure to undo a relocation from `old_dir`
+/// to `new_dir` if `undone` is one.
+fn with_rollback(
+    error: FoundryLocalError,
+    undone: Result<()>,
+    old_dir: &Path,
+    new_dir: &Path,
+) -> FoundryLocalError {
+    match undone {
+        Ok(()) => error,
+        Err(rollback) => FoundryLocalError::ModelOperation {
+            reason: format!(
+                "{error}; undoing the relocation also failed ({rollback}), so the model cache may be split between '{}' and '{}'",
+                old_dir.display(),
+                new_dir.display()
+            ),
+        },
+    }
+}
+
 /// Fingerprint of an alias-grouped `Model`: the `(id, cached)` of every
 /// variant in catalog order. Two fingerprints are equal exactly when reusing
 /// the old `Arc<Model>` would surface the same `ModelInfo` data as a freshly
@@ -1,5 +1,10 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
 
 use crate::error::{FoundryLocalError, Result};
 
@@ -37,6 +42,190 @@ pub trait Logger: Send + Sync {
     fn log(&self, level: LogLevel, message: &str);
 }
 
+/// Credentials sent with every request to the service endpoint.
+#[derive(Clone)]
+enum HttpAuth {
+    Bearer(String),
+    ApiKey { header: String, value: String },
+}
+
+/// HTTP settings for requests to an external
+/// [`service_endpoint`](FoundryLocalConfig::service_endpoint).
+///
+/// ```ignore
+/// let http = HttpSettings::new()
+///     .request_timeout(Duration::from_secs(120))
+///     .bearer_token(std::env::var("FOUNDRY_TOKEN")?)
+///     .proxy("http://proxy.internal:3128");
+/// let config = FoundryLocalConfig::new("my_app")
+///     .service_endpoint("https://foundry.internal")
+///     .http_settings(http);
+/// ```
+#[derive(Clone, Default)]
+pub struct HttpSettings {
+    connect_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    auth: Option<HttpAuth>,
+    ca_certificate: Option<PathBuf>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl fmt::Debug for HttpSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let auth = self.auth.as_ref().map(|a| match a {
+            HttpAuth::Bearer(_) => "Bearer <redacted>".to_string(),
+            HttpAuth::ApiKey { header, .. } => format!("{header}: <redacted>"),
+        });
+        f.debug_struct("HttpSettings")
+            .field("connect_timeout", &self.connect_timeout)
+            .field("request_timeout", &self.request_timeout)
+            .field("auth", &auth)
+            .field("ca_certificate", &self.ca_certificate)
+            .field("proxy", &self.proxy)
+            .field("user_agent", &self.user_agent)
+            .finish()
+    }
+}
+
+impl HttpSettings {
+    /// Create settings with no timeouts, credentials or proxy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timeout for establishing a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for a whole request, from connecting until the response body
+    /// has been read.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Send `Authorization: Bearer <token>` with every request.
+    pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.auth = Some(HttpAuth::Bearer(token.into()));
+        self
+    }
+
+    /// Send an API key in the given header (e.g. `"api-key"`) with every
+    /// request.  Replaces a bearer token if one was set.
+    pub fn api_key(mut self, header: impl Into<String>, key: impl Into<String>) -> Self {
+        self.auth = Some(HttpAuth::ApiKey {
+            header: header.into(),
+            value: key.into(),
+        });
+        self
+    }
+
+    /// Trust the PEM-encoded CA certificate(s) at `path` in addition to the
+    /// system roots.
+    pub fn ca_certificate(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_certificate = Some(path.into());
+        self
+    }
+
+    /// Route requests through the given HTTP(S) proxy URL.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Override the `User-Agent` header.
+    ///
+    /// Defaults to `foundry-local-rust-sdk/<version>`.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Build the HTTP client used for the service endpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::InvalidConfiguration`] for an invalid
+    /// header, an unreadable or malformed CA certificate, or an invalid
+    /// proxy URL.
+    pub(crate) fn build_client(&self) -> Result<reqwest::Client> {
+        let invalid = |reason: String| FoundryLocalError::InvalidConfiguration { reason };
+
+        let mut headers = HeaderMap::new();
+        if let Some(auth) = &self.auth {
+            let (name, value) = match auth {
+                HttpAuth::Bearer(token) => (AUTHORIZATION, format!("Bearer {token}")),
+                HttpAuth::ApiKey { header, value } => (
+                    HeaderName::from_bytes(header.as_bytes())
+                        .map_err(|e| invalid(format!("invalid API key header '{header}': {e}")))?,
+                    value.clone(),
+                ),
+            };
+            let mut value = HeaderValue::from_str(&value)
+                .map_err(|e| invalid(format!("invalid credential header value: {e}")))?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+
+        let user_agent = self
+            .user_agent
+            .clone()
+            .unwrap_or_else(|| format!("foundry-local-rust-sdk/{}", env!("CARGO_PKG_VERSION")));
+        let mut builder = reqwest::Client::builder()
+            .default_headers(headers)
+            .user_agent(user_agent);
+
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(path) = &self.ca_certificate {
+            let pem = std::fs::read(path).map_err(|e| {
+                invalid(format!(
+                    "cannot read CA certificate '{}': {e}",
+                    path.display()
+                ))
+            })?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| {
+                invalid(format!("invalid CA certificate '{}': {e}", path.display()))
+            })?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(url) = &self.proxy {
+            let proxy = reqwest::Proxy::all(url)
+                .map_err(|e| invalid(format!("invalid proxy URL '{url}': {e}")))?;
+            builder = builder.proxy(proxy);
+        }
+
+        builder
+            .build()
+            .map_err(|e| invalid(format!("cannot build HTTP client: {e}")))
+    }
+}
+
+/// How downloads and cache removals react when another process holds the
+/// lock on the same model variant in a shared model cache directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheLockPolicy {
+    /// Wait for the lock to be released, giving up after `timeout` when set.
+    Wait { timeout: Option<Duration> },
+    /// Fail immediately with [`FoundryLocalError::CacheLocked`].
+    FailFast,
+}
+
+impl Default for CacheLockPolicy {
+    fn default() -> Self {
+        Self::Wait { timeout: None }
+    }
+}
+
 /// User-facing configuration for initializing the Foundry Local SDK.
 ///
 /// Construct with [`FoundryLocalConfig::new`] and customise via the builder
@@ -58,6 +247,8 @@ pub struct FoundryLocalConfig {
     service_endpoint: Option<String>,
     library_path: Option<String>,
     additional_settings: Option<HashMap<String, String>>,
+    http_settings: Option<HttpSettings>,
+    cache_lock_policy: Option<CacheLockPolicy>,
     logger: Option<Box<dyn Logger>>,
 }
 
@@ -73,6 +264,8 @@ impl fmt::Debug for FoundryLocalConfig {
             .field("service_endpoint", &self.service_endpoint)
             .field("library_path", &self.library_path)
             .field("additional_settings", &self.additional_settings)
+            .field("http_settings", &self.http_settings)
+            .field("cache_lock_policy", &self.cache_lock_policy)
             .field("logger", &self.logger.as_ref().map(|_| ".."))
             .finish()
     }
@@ -132,6 +325,22 @@ impl FoundryLocalConfig {
         self
     }
 
+    /// Set HTTP timeouts, credentials, TLS trust, proxy and user agent for
+    /// requests to the [`service_endpoint`](Self::service_endpoint).
+    pub fn http_settings(mut self, settings: HttpSettings) -> Self {
+        self.http_settings = Some(settings);
+        self
+    }
+
+    /// Choose whether downloads and cache removals wait for, or fail on, a
+    /// variant locked by another process sharing the model cache.
+    ///
+    /// Defaults to waiting without a timeout.
+    pub fn cache_lock_policy(mut self, policy: CacheLockPolicy) -> Self {
+        self.cache_lock_policy = Some(policy);
+        self
+    }
+
     /// Override the path to the native Foundry Local Core library.
     pub fn library_path(mut self, path: impl Into<String>) -> Self {
         self.library_path = Some(path.into());
@@ -160,6 +369,10 @@ impl FoundryLocalConfig {
 #[derive(Debug, Clone)]
 pub(crate) struct Configuration {
     pub params: HashMap<String, String>,
+    /// Settings for the service endpoint client; not sent to the core.
+    pub http: HttpSettings,
+    /// Behaviour on a contended model cache lock; not sent to the core.
+    pub cache_lock_policy: CacheLockPolicy,
 }
 
 impl Configuration {
@@ -203,10 +416,79 @@ impl Configuration {
             params.extend(extra);
         }
 
-        Ok((Self { params }, config.logger))
+        let http = config.http_settings.unwrap_or_default();
+        let cache_lock_policy = config.cache_lock_policy.unwrap_or_default();
+        let mut configuration = Self {
+            params,
+            http,
+            cache_lock_policy,
+        };
+        if !configuration.params.contains_key("ModelCacheDir") {
+            let stored = read_stored_location(&configuration.cache_location_file())?;
+            if let Some(dir) = stored {
+                configuration.params.insert("ModelCacheDir".into(), dir);
+            }
+        }
+        Ok((configuration, config.logger))
+    }
+
+    /// Where [`Catalog::relocate`](crate::Catalog::relocate) records the
+    /// model cache directory, used on later starts unless `ModelCacheDir`
+    /// is configured.
+    pub(crate) fn cache_location_file(&self) -> PathBuf {
+        self.app_data_dir().join(CACHE_LOCATION_FILE_NAME)
+    }
+
+    /// Resolve the application-data directory.
+    ///
+    /// Uses the configured `AppDataDir` when present and otherwise falls back
+    /// to `~/.{AppName}`, which matches the native core default.
+    pub fn app_data_dir(&self) -> PathBuf {
+        if let Some(dir) = self.params.get("AppDataDir") {
+            return PathBuf::from(dir);
+        }
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        let app_name = self.params.get("AppName").map(String::as_str).unwrap_or("");
+        home.join(format!(".{app_name}"))
     }
 }
 
+/// File in the app data directory holding the relocated model cache
+/// directory.
+const CACHE_LOCATION_FILE_NAME: &str = "model_cache_location.json";
+
+/// The stored model cache location as written on disk.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredCacheLocation {
+    model_cache_dir: String,
+}
+
+fn read_stored_location(path: &Path) -> Result<Option<String>> {
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            let stored: StoredCacheLocation = serde_json::from_slice(&bytes)?;
+            Ok(Some(stored.model_cache_dir))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Record `dir` in `path` as the model cache directory for later starts.
+pub(crate) fn store_cache_location(path: &Path, dir: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let stored = StoredCacheLocation {
+        model_cache_dir: dir.to_string_lossy().into_owned(),
+    };
+    std::fs::write(path, serde_json::to_vec_pretty(&stored)?)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,6 +507,63 @@ mod tests {
         assert!(Configuration::new(cfg).is_err());
     }
 
+    #[test]
+    fn app_data_dir_prefers_configured_value() {
+        let cfg = FoundryLocalConfig::new("App").app_data_dir("/tmp/app-data");
+        let (c, _) = Configuration::new(cfg).unwrap();
+        assert_eq!(c.app_data_dir(), PathBuf::from("/tmp/app-data"));
+    }
+
+    #[test]
+    fn uses_the_stored_cache_location_unless_configured() {
+        let app_data =
+            std::env::temp_dir().join(format!("foundry-local-config-{}", std::process::id()));
+        let app_data_str = app_data.to_string_lossy().into_owned();
+        let config = || FoundryLocalConfig::new("App").app_data_dir(app_data_str.clone());
+
+        let (c, _) = Configuration::new(config()).unwrap();
+        assert!(!c.params.contains_key("ModelCacheDir"));
+
+        store_cache_location(&c.cache_location_file(), Path::new("/mnt/models")).unwrap();
+        let (c, _) = Configuration::new(config()).unwrap();
+        assert_eq!(c.params["ModelCacheDir"], "/mnt/models");
+
+        let (c, _) = Configuration::new(config().model_cache_dir("/data/cache")).unwrap();
+        assert_eq!(c.params["ModelCacheDir"], "/data/cache");
+
+        std::fs::remove_dir_all(&app_data).unwrap();
+    }
+
+    #[test]
+    fn app_data_dir_defaults_to_dot_app_name() {
+        let (c, _) = Configuration::new(FoundryLocalConfig::new("App")).unwrap();
+        assert!(c.app_data_dir().ends_with(".App"));
+    }
+
+    #[test]
+    fn http_settings_build_a_client() {
+        let settings = HttpSettings::new()
+            .connect_timeout(Duration::from_secs(5))
+            .request_timeout(Duration::from_secs(60))
+            .api_key("api-key", "secret")
+            .proxy("http://proxy.local:3128")
+            .user_agent("my-app/1.0");
+        assert!(settings.build_client().is_ok());
+        assert!(!format!("{settings:?}").contains("secret"));
+    }
+
+    #[test]
+    fn http_settings_reject_invalid_values() {
+        let bad_header = HttpSettings::new().api_key("bad header", "secret");
+        assert!(bad_header.build_client().is_err());
+
+        let missing_ca = HttpSettings::new().ca_certificate("/nonexistent/ca.pem");
+        assert!(missing_ca.build_client().is_err());
+
+        let bad_proxy = HttpSettings::new().proxy("not a url");
+        assert!(bad_proxy.build_client().is_err());
+    }
+
     #[test]
     fn builder_additional_settings() {
         let cfg = FoundryLocalConfig::new("App").additional_setting("Foo", "bar");
@@ -0,0 +1,338 @@
+//! Cross-process advisory locks on model cache entries.
+//!
+//! Each variant has a lock file under `{cache_dir}/.locks`.  The OS lock on
+//! it is released when its holder exits, so a crashed process never blocks
+//! others.  While held, the file records the holder so contended callers can
+//! say who they are waiting for; a record found on a lock nobody holds was
+//! left by a process that exited without releasing it and is replaced.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use fs4::fs_std::FileExt;
+use serde::{Deserialize, Serialize};
+
+use super::core_interop::{get_cache_directory, CoreInterop};
+use crate::configuration::CacheLockPolicy;
+use crate::error::{FoundryLocalError, Result};
+
+/// Directory below the cache root holding the lock files.
+pub(crate) const LOCK_DIR: &str = ".locks";
+
+/// How often a waiting caller retries a contended lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Who holds a lock, written into the lock file while it is held.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct LockOwner {
+    pid: u32,
+    host: String,
+    operation: String,
+    acquired_at_unix: u64,
+}
+
+impl LockOwner {
+    fn current(operation: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            host: host_name(),
+            operation: operation.to_owned(),
+            acquired_at_unix: unix_now(),
+        }
+    }
+
+    fn describe(owner: Option<&Self>) -> String {
+        match owner {
+            Some(o) => format!(
+                "{} in process {} on '{}' ({}s ago)",
+                o.operation,
+                o.pid,
+                o.host,
+                unix_now().saturating_sub(o.acquired_at_unix)
+            ),
+            None => "another process".into(),
+        }
+    }
+}
+
+/// An exclusive lock on one variant, released on drop.
+#[derive(Debug)]
+pub(crate) struct CacheLockGuard {
+    file: File,
+    /// Record left by a holder that exited without releasing the lock.
+    stale_owner: Option<LockOwner>,
+}
+
+impl CacheLockGuard {
+    pub(crate) fn stale_owner(&self) -> Option<&LockOwner> {
+        self.stale_owner.as_ref()
+    }
+}
+
+impl Drop for CacheLockGuard {
+    fn drop(&mut self) {
+        // Clear the record first so the next holder does not mistake a clean
+        // release for a crash.
+        let _ = self.file.set_len(0);
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+pub(crate) enum TryLock {
+    Acquired(CacheLockGuard),
+    Held(Option<LockOwner>),
+}
+
+/// Acquires variant locks in the cache directory the native core is using.
+#[derive(Debug)]
+pub(crate) struct CacheLocks {
+    core: Arc<CoreInterop>,
+    policy: CacheLockPolicy,
+}
+
+impl CacheLocks {
+    pub(crate) fn new(core: Arc<CoreInterop>, policy: CacheLockPolicy) -> Self {
+        Self { core, policy }
+    }
+
+    /// Lock `model_id` for `operation` according to the configured policy.
+    pub(crate) async fn acquire(&self, model_id: &str, operation: &str) -> Result<CacheLockGuard> {
+        let cache_dir = get_cache_directory(&self.core).await?;
+        acquire(&cache_dir, model_id, operation, self.policy).await
+    }
+}
+
+async fn acquire(
+    cache_dir: &Path,
+    model_id: &str,
+    operation: &str,
+    policy: CacheLockPolicy,
+) -> Result<CacheLockGuard> {
+    let started = Instant::now();
+    loop {
+        let owner = match try_lock(cache_dir, model_id, operation)? {
+            TryLock::Acquired(guard) => return Ok(guard),
+            TryLock::Held(owner) => owner,
+        };
+        let give_up = match policy {
+            CacheLockPolicy::FailFast => true,
+            CacheLockPolicy::Wait { timeout } => timeout.is_some_and(|t| started.elapsed() >= t),
+        };
+        if give_up {
+            return Err(FoundryLocalError::CacheLocked {
+                model_id: model_id.to_owned(),
+                holder: LockOwner::describe(owner.as_ref()),
+            });
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Try once to lock `model_id` without waiting.
+pub(crate) fn try_lock(cache_dir: &Path, model_id: &str, operation: &str) -> Result<TryLock> {
+    let path = lock_path(cache_dir, model_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+
+    if !FileExt::try_lock_exclusive(&file)? {
+        return Ok(TryLock::Held(read_owner(&mut file)));
+    }
+
+    let stale_owner = read_owner(&mut file);
+    let record = serde_json::to_vec(&LockOwner::current(operation))?;
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&record)?;
+    file.flush()?;
+    Ok(TryLock::Acquired(CacheLockGuard { file, stale_owner }))
+}
+
+/// Fail with [`FoundryLocalError::CacheLocked`] if any variant lock in
+/// `cache_dir` is held, by this process or another.
+pub(crate) fn ensure_unlocked(cache_dir: &Path) -> Result<()> {
+    let entries = match std::fs::read_dir(cache_dir.join(LOCK_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("lock") {
+            continue;
+        }
+        let mut file = match OpenOptions::new().read(true).write(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if !FileExt::try_lock_exclusive(&file)? {
+            return Err(FoundryLocalError::CacheLocked {
+                model_id: path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                holder: LockOwner::describe(read_owner(&mut file).as_ref()),
+            });
+        }
+        FileExt::unlock(&file)?;
+    }
+    Ok(())
+}
+
+/// The recorded holder, if the file holds a readable record.  Reading fails
+/// on platforms where the OS lock is mandatory.
+fn read_owner(file: &mut File) -> Option<LockOwner> {
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_end(&mut bytes).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+fn lock_path(cache_dir: &Path, model_id: &str) -> PathBuf {
+    let name: String = model_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    cache_dir.join(LOCK_DIR).join(format!("{name}.lock"))
+}
+
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|h| h.trim().to_owned())
+        })
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown host".into())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("foundry-local-lock-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn expect_acquired(result: TryLock) -> CacheLockGuard {
+        match result {
+            TryLock::Acquired(guard) => guard,
+            TryLock::Held(_) => panic!("expected the lock to be free"),
+        }
+    }
+
+    #[test]
+    fn second_holder_sees_recorded_owner() {
+        let cache = temp_dir("contended");
+        let guard = expect_acquired(try_lock(&cache, "phi-4:1", "download").unwrap());
+        assert!(guard.stale_owner().is_none());
+
+        match try_lock(&cache, "phi-4:1", "remove").unwrap() {
+            TryLock::Held(owner) => {
+                if let Some(owner) = owner {
+                    assert_eq!(owner.pid, std::process::id());
+                    assert_eq!(owner.operation, "download");
+                }
+            }
+            TryLock::Acquired(_) => panic!("lock should be held"),
+        }
+        expect_acquired(try_lock(&cache, "qwen:2", "download").unwrap());
+
+        drop(guard);
+        let guard = expect_acquired(try_lock(&cache, "phi-4:1", "remove").unwrap());
+        assert!(guard.stale_owner().is_none());
+        drop(guard);
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn ensure_unlocked_fails_while_any_lock_is_held() {
+        let cache = temp_dir("any-held");
+        ensure_unlocked(&cache).unwrap();
+        drop(expect_acquired(
+            try_lock(&cache, "qwen:2", "download").unwrap(),
+        ));
+        let guard = expect_acquired(try_lock(&cache, "phi-4:1", "download").unwrap());
+
+        let err = ensure_unlocked(&cache).unwrap_err();
+        assert!(matches!(
+            err,
+            FoundryLocalError::CacheLocked { ref model_id, .. } if model_id == "phi-4_1"
+        ));
+        drop(guard);
+        ensure_unlocked(&cache).unwrap();
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn record_without_lock_is_reported_stale() {
+        let cache = temp_dir("stale");
+        let crashed = LockOwner {
+            pid: 999_999,
+            host: "build-07".into(),
+            operation: "download".into(),
+            acquired_at_unix: 1,
+        };
+        let path = lock_path(&cache, "phi-4:1");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_vec(&crashed).unwrap()).unwrap();
+
+        let guard = expect_acquired(try_lock(&cache, "phi-4:1", "download").unwrap());
+        assert_eq!(guard.stale_owner(), Some(&crashed));
+        drop(guard);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[tokio::test]
+    async fn policies_bound_the_wait() {
+        let cache = temp_dir("policy");
+        let _guard = expect_acquired(try_lock(&cache, "phi-4:1", "download").unwrap());
+
+        let err = acquire(&cache, "phi-4:1", "remove", CacheLockPolicy::FailFast)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FoundryLocalError::CacheLocked { .. }));
+
+        let wait = CacheLockPolicy::Wait {
+            timeout: Some(Duration::from_millis(300)),
+        };
+        let started = Instant::now();
+        assert!(acquire(&cache, "phi-4:1", "remove", wait).await.is_err());
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+}
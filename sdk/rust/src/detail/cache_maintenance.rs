@@ -0,0 +1,561 @@
+//! Classify, clean and relocate the model cache directory.
+//!
+//! Follows the native core's layout: each variant lives in a directory
+//! holding an `inference_model.json` with the variant `Name`, and a
+//! `download.tmp` marker is present while a download is incomplete.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+
+use super::cache_lock::{self, TryLock, LOCK_DIR};
+use crate::error::{FoundryLocalError, Result};
+
+const INFERENCE_MODEL: &str = "inference_model.json";
+const DOWNLOAD_MARKER: &str = "download.tmp";
+/// Staging directories left behind by an interrupted
+/// [`Catalog::import`](crate::Catalog::import).
+const IMPORT_STAGING_PREFIX: &str = ".import-";
+
+/// How an entry in the model cache directory was classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheEntryKind {
+    /// A complete download of a variant listed in the catalog.
+    Cached,
+    /// A complete model registered by the core as bring your own model.
+    Byom,
+    /// An interrupted download or import.
+    Partial,
+    /// A directory that holds no model, or a model that is neither in the
+    /// catalog nor a BYOM model, such as a variant the catalog dropped.
+    Orphaned,
+}
+
+/// A single entry in a [`CacheReport`].
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub kind: CacheEntryKind,
+    pub path: PathBuf,
+    /// Variant id read from `inference_model.json`, when available.
+    pub model_id: Option<String>,
+    pub size_bytes: u64,
+    /// Most recent modification time of the directory or any file in it.
+    pub modified: Option<SystemTime>,
+}
+
+/// What is in the model cache directory, as returned by
+/// [`Catalog::cache_report`](crate::Catalog::cache_report).
+#[derive(Debug, Clone)]
+pub struct CacheReport {
+    pub cache_dir: PathBuf,
+    /// Entries sorted by path.
+    pub entries: Vec<CacheEntry>,
+}
+
+impl CacheReport {
+    /// Entries of the given kind.
+    pub fn entries_of(&self, kind: CacheEntryKind) -> impl Iterator<Item = &CacheEntry> {
+        self.entries.iter().filter(move |e| e.kind == kind)
+    }
+
+    /// Total size of all entries.
+    pub fn total_size_bytes(&self) -> u64 {
+        self.entries.iter().map(|e| e.size_bytes).sum()
+    }
+
+    /// Size of the partial and orphaned entries that
+    /// [`Catalog::cleanup`](crate::Catalog::cleanup) may remove.
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.kind, CacheEntryKind::Partial | CacheEntryKind::Orphaned))
+            .map(|e| e.size_bytes)
+            .sum()
+    }
+}
+
+/// Outcome of [`Catalog::cleanup`](crate::Catalog::cleanup).
+#[derive(Debug, Clone, Default)]
+pub struct CleanupSummary {
+    /// Entries that were deleted.
+    pub removed: Vec<CacheEntry>,
+    /// Partial entries left in place because another process holds their
+    /// cache lock, or because they were modified recently and may belong to a
+    /// download that is still running.
+    pub skipped: Vec<CacheEntry>,
+    pub reclaimed_bytes: u64,
+}
+
+#[derive(Deserialize)]
+struct InferenceModel {
+    #[serde(rename = "Name")]
+    name: String,
+}
+
+/// Ids of the models the core knows, by where they come from.
+#[derive(Debug, Default)]
+pub(crate) struct KnownModels {
+    /// Catalog variants.
+    pub catalog: HashSet<String>,
+    /// Bring-your-own models found by the core.
+    pub byom: HashSet<String>,
+}
+
+/// Classify every entry below `cache_dir` against the `known` models.
+pub(crate) fn scan(cache_dir: &Path, known: &KnownModels) -> Result<CacheReport> {
+    let mut entries = Vec::new();
+    if cache_dir.is_dir() {
+        for entry in std::fs::read_dir(cache_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_str().unwrap_or_default();
+            if name == LOCK_DIR {
+                continue;
+            }
+            if name.starts_with(IMPORT_STAGING_PREFIX) {
+                entries.push(describe(CacheEntryKind::Partial, path, None)?);
+            } else if !scan_dir(&path, known, &mut entries)? {
+                entries.push(describe(CacheEntryKind::Orphaned, path, None)?);
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(CacheReport {
+        cache_dir: cache_dir.to_path_buf(),
+        entries,
+    })
+}
+
+/// Record the models and partial downloads below `dir`.  Returns `false`
+/// when there are none, leaving the caller to report `dir` as orphaned.
+fn scan_dir(dir: &Path, known: &KnownModels, out: &mut Vec<CacheEntry>) -> Result<bool> {
+    let model_id = read_model_id(dir);
+    if dir.join(DOWNLOAD_MARKER).exists() {
+        out.push(describe(
+            CacheEntryKind::Partial,
+            dir.to_path_buf(),
+            model_id,
+        )?);
+        return Ok(true);
+    }
+    if dir.join(INFERENCE_MODEL).exists() {
+        // A model directory is a leaf; an unreadable name means the
+        // download never completed.
+        let kind = match &model_id {
+            Some(id) if known.catalog.contains(id) => CacheEntryKind::Cached,
+            Some(id) if known.byom.contains(id) => CacheEntryKind::Byom,
+            Some(_) => CacheEntryKind::Orphaned,
+            None => CacheEntryKind::Partial,
+        };
+        out.push(describe(kind, dir.to_path_buf(), model_id)?);
+        return Ok(true);
+    }
+
+    let mut empty_children = Vec::new();
+    let mut found = false;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        if scan_dir(&path, known, out)? {
+            found = true;
+        } else {
+            empty_children.push(path);
+        }
+    }
+    if found {
+        for path in empty_children {
+            out.push(describe(CacheEntryKind::Orphaned, path, None)?);
+        }
+    }
+    Ok(found)
+}
+
+/// The variant id recorded in `dir`, with the `:0` version suffix the core
+/// appends when the name has none.
+fn read_model_id(dir: &Path) -> Option<String> {
+    let bytes = std::fs::read(dir.join(INFERENCE_MODEL)).ok()?;
+    let model: InferenceModel = serde_json::from_slice(&bytes).ok()?;
+    if model.name.is_empty() {
+        return None;
+    }
+    Some(if model.name.contains(':') {
+        model.name
+    } else {
+        format!("{}:0", model.name)
+    })
+}
+
+fn describe(kind: CacheEntryKind, path: PathBuf, model_id: Option<String>) -> Result<CacheEntry> {
+    let mut size_bytes = 0;
+    let mut modified = std::fs::metadata(&path)?.modified().ok();
+    visit_files(&path, &mut |meta| {
+        size_bytes += meta.len();
+        if let Ok(m) = meta.modified() {
+            modified = Some(modified.map_or(m, |cur: SystemTime| cur.max(m)));
+        }
+    })?;
+    Ok(CacheEntry {
+        kind,
+        path,
+        model_id,
+        size_bytes,
+        modified,
+    })
+}
+
+fn visit_files(dir: &Path, f: &mut impl FnMut(&std::fs::Metadata)) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            visit_files(&entry.path(), f)?;
+        } else {
+            f(&meta);
+        }
+    }
+    Ok(())
+}
+
+/// Delete the partial and orphaned entries in `report`.
+///
+/// Entries whose cache lock is held are skipped.  So are those modified
+/// within `min_age`, which may belong to a download that has not written
+/// its marker yet, unless their lock shows the download was abandoned by a
+/// process that exited without releasing it.
+pub(crate) fn cleanup(report: CacheReport, min_age: Duration) -> Result<CleanupSummary> {
+    let now = SystemTime::now();
+    let mut summary = CleanupSummary::default();
+    for entry in report.entries {
+        let mut _lock = None;
+        match entry.kind {
+            CacheEntryKind::Cached | CacheEntryKind::Byom => continue,
+            CacheEntryKind::Partial | CacheEntryKind::Orphaned => {
+                let mut abandoned = false;
+                if let Some(id) = &entry.model_id {
+                    match cache_lock::try_lock(&report.cache_dir, id, "cleanup")? {
+                        TryLock::Acquired(guard) => {
+                            abandoned = guard.stale_owner().is_some();
+                            _lock = Some(guard);
+                        }
+                        TryLock::Held(_) => {
+                            summary.skipped.push(entry);
+                            continue;
+                        }
+                    }
+                }
+                let recent = entry
+                    .modified
+                    .and_then(|m| now.duration_since(m).ok())
+                    .is_some_and(|age| age < min_age);
+                if recent && !abandoned {
+                    summary.skipped.push(entry);
+                    continue;
+                }
+            }
+        }
+
+        std::fs::remove_dir_all(&entry.path)?;
+        remove_empty_parents(&entry.path, &report.cache_dir);
+        summary.reclaimed_bytes += entry.size_bytes;
+        summary.removed.push(entry);
+    }
+    Ok(summary)
+}
+
+/// Remove directories between `path` and `root` that are left empty.
+fn remove_empty_parents(path: &Path, root: &Path) {
+    let mut dir = path.parent();
+    while let Some(d) = dir {
+        if d == root || std::fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+/// How [`move_cache`] moved the directory, so it can be undone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MoveMethod {
+    Renamed,
+    Copied,
+}
+
+/// Move the contents of `from` to `to`, renaming when both are on the same
+/// volume and copying otherwise.  A copy leaves `from` in place; it is
+/// removed by [`finish_move`] once the new location is in use.
+pub(crate) fn move_cache(from: &Path, to: &Path) -> Result<MoveMethod> {
+    if from == to || to.starts_with(from) || from.starts_with(to) {
+        return Err(FoundryLocalError::Validation {
+            reason: format!(
+                "cannot relocate the model cache from '{}' to '{}': the directories overlap",
+                from.display(),
+                to.display()
+            ),
+        });
+    }
+    if to.exists() {
+        if std::fs::read_dir(to)?.next().is_some() {
+            return Err(FoundryLocalError::Validation {
+                reason: format!(
+                    "cannot relocate the model cache to '{}': the directory is not empty",
+                    to.display()
+                ),
+            });
+        }
+        std::fs::remove_dir(to)?;
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if !from.exists() {
+        std::fs::create_dir(to)?;
+        return Ok(MoveMethod::Renamed);
+    }
+
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(MoveMethod::Renamed);
+    }
+
+    let copied = copy_tree(from, to).and_then(|()| verify_copy(from, to));
+    if let Err(e) = copied {
+        let _ = std::fs::remove_dir_all(to);
+        return Err(e);
+    }
+    Ok(MoveMethod::Copied)
+}
+
+/// Undo [`move_cache`] after the core rejected the new location.
+pub(crate) fn revert_move(from: &Path, to: &Path, method: MoveMethod) -> Result<()> {
+    match method {
+        MoveMethod::Renamed => Ok(std::fs::rename(to, from)?),
+        MoveMethod::Copied => Ok(std::fs::remove_dir_all(to)?),
+    }
+}
+
+/// Remove the old copy once the core uses the new location.
+pub(crate) fn finish_move(from: &Path, method: MoveMethod) -> Result<()> {
+    match method {
+        MoveMethod::Renamed => Ok(()),
+        MoveMethod::Copied => Ok(std::fs::remove_dir_all(from)?),
+    }
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_tree(&entry.path(), &dest)?;
+        } else {
+            std::fs::copy(entry.path(), &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check that every file under `from` exists under `to` with the same size.
+fn verify_copy(from: &Path, to: &Path) -> Result<()> {
+    let mut files = Vec::new();
+    super::model_archive::collect_files(from, Path::new(""), &mut files)?;
+    for rel in files {
+        let expected = std::fs::metadata(from.join(&rel))?.len();
+        let actual = std::fs::metadata(to.join(&rel)).map(|m| m.len()).ok();
+        if actual != Some(expected) {
+            return Err(FoundryLocalError::Internal {
+                reason: format!(
+                    "copy of '{}' to '{}' failed verification",
+                    from.join(&rel).display(),
+                    to.join(&rel).display()
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("foundry-local-cache-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_model(dir: &Path, name: &str, payload: usize) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join(INFERENCE_MODEL),
+            format!(r#"{{"Name":"{name}","PromptTemplate":null}}"#),
+        )
+        .unwrap();
+        std::fs::write(dir.join("model.onnx"), vec![0u8; payload]).unwrap();
+    }
+
+    fn populate(cache: &Path) {
+        write_model(&cache.join("Microsoft").join("phi-4-1"), "phi-4:1", 10);
+        write_model(&cache.join("Custom").join("my-model"), "my-model", 5);
+        let partial = cache.join("Microsoft").join("qwen-2");
+        write_model(&partial, "qwen:2", 7);
+        std::fs::write(partial.join(DOWNLOAD_MARKER), b"").unwrap();
+        std::fs::create_dir_all(cache.join("Microsoft").join("removed-1")).unwrap();
+        std::fs::create_dir_all(cache.join("Stale")).unwrap();
+        std::fs::write(cache.join("Stale").join("leftover.bin"), vec![0u8; 3]).unwrap();
+        std::fs::create_dir_all(cache.join(".import-1234")).unwrap();
+        write_model(&cache.join("Microsoft").join("retired-3"), "retired:3", 4);
+    }
+
+    fn known(catalog: &[&str]) -> KnownModels {
+        KnownModels {
+            catalog: catalog.iter().map(|id| id.to_string()).collect(),
+            byom: HashSet::from(["my-model:0".to_string()]),
+        }
+    }
+
+    #[test]
+    fn classifies_cache_entries() {
+        let cache = temp_dir("scan");
+        populate(&cache);
+        let report = scan(&cache, &known(&["phi-4:1", "qwen:2"])).unwrap();
+        let kind_of = |rel: &str| {
+            report
+                .entries
+                .iter()
+                .find(|e| e.path == cache.join(rel))
+                .map(|e| e.kind)
+        };
+        assert_eq!(kind_of("Microsoft/phi-4-1"), Some(CacheEntryKind::Cached));
+        assert_eq!(kind_of("Custom/my-model"), Some(CacheEntryKind::Byom));
+        assert_eq!(kind_of("Microsoft/qwen-2"), Some(CacheEntryKind::Partial));
+        assert_eq!(kind_of(".import-1234"), Some(CacheEntryKind::Partial));
+        assert_eq!(
+            kind_of("Microsoft/removed-1"),
+            Some(CacheEntryKind::Orphaned)
+        );
+        assert_eq!(kind_of("Stale"), Some(CacheEntryKind::Orphaned));
+        // A complete model the catalog no longer lists.
+        assert_eq!(
+            kind_of("Microsoft/retired-3"),
+            Some(CacheEntryKind::Orphaned)
+        );
+        assert_eq!(report.entries.len(), 7);
+
+        let byom = report.entries_of(CacheEntryKind::Byom).next().unwrap();
+        assert_eq!(byom.model_id.as_deref(), Some("my-model:0"));
+        let stale = report
+            .entries
+            .iter()
+            .find(|e| e.path == cache.join("Stale"));
+        assert_eq!(stale.unwrap().size_bytes, 3);
+        assert!(report.reclaimable_bytes() > 7 + 3);
+        assert!(report.reclaimable_bytes() < report.total_size_bytes());
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn cleanup_keeps_models_and_recent_entries() {
+        let cache = temp_dir("cleanup");
+        populate(&cache);
+        let known = known(&["phi-4:1"]);
+
+        let mut report = scan(&cache, &known).unwrap();
+        let day_ago = SystemTime::now() - Duration::from_secs(24 * 3600);
+        for entry in &mut report.entries {
+            if entry.path == cache.join("Stale") {
+                entry.modified = Some(day_ago);
+            }
+        }
+        let summary = cleanup(report, Duration::from_secs(3600)).unwrap();
+        assert_eq!(summary.skipped.len(), 4);
+        assert_eq!(summary.removed.len(), 1);
+        assert!(!cache.join("Stale").exists());
+        assert!(cache.join("Microsoft").join("removed-1").exists());
+        assert!(cache.join("Microsoft").join("qwen-2").exists());
+
+        let summary = cleanup(scan(&cache, &known).unwrap(), Duration::ZERO).unwrap();
+        assert_eq!(summary.removed.len(), 4);
+        assert!(!cache.join("Microsoft").join("removed-1").exists());
+        assert!(!cache.join("Microsoft").join("retired-3").exists());
+        assert!(cache.join("Microsoft").join("phi-4-1").exists());
+        assert!(cache.join("Custom").join("my-model").exists());
+        assert!(!cache.join("Microsoft").join("qwen-2").exists());
+        assert!(!cache.join(".import-1234").exists());
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn cleanup_respects_partial_download_locks() {
+        let cache = temp_dir("cleanup-locks");
+        for (dir, name) in [("qwen-2", "qwen:2"), ("phi-4-1", "phi-4:1")] {
+            let partial = cache.join("Microsoft").join(dir);
+            write_model(&partial, name, 7);
+            std::fs::write(partial.join(DOWNLOAD_MARKER), b"").unwrap();
+        }
+        let _held = match cache_lock::try_lock(&cache, "qwen:2", "download").unwrap() {
+            TryLock::Acquired(guard) => guard,
+            TryLock::Held(_) => panic!("lock should be free"),
+        };
+        // A record on a lock nobody holds marks an abandoned download.
+        let lock = cache.join(LOCK_DIR).join("phi-4_1.lock");
+        std::fs::write(
+            &lock,
+            r#"{"pid":1,"host":"h","operation":"download","acquired_at_unix":1}"#,
+        )
+        .unwrap();
+
+        let report = scan(&cache, &KnownModels::default()).unwrap();
+        let summary = cleanup(report, Duration::from_secs(3600)).unwrap();
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.removed.len(), 1);
+        assert!(cache.join("Microsoft").join("qwen-2").exists());
+        assert!(!cache.join("Microsoft").join("phi-4-1").exists());
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn move_cache_and_revert() {
+        let root = temp_dir("move");
+        let from = root.join("old");
+        let to = root.join("new");
+        write_model(&from.join("Microsoft").join("phi-4-1"), "phi-4:1", 10);
+
+        assert!(move_cache(&from, &from.join("nested")).is_err());
+
+        let method = move_cache(&from, &to).unwrap();
+        assert!(to
+            .join("Microsoft")
+            .join("phi-4-1")
+            .join("model.onnx")
+            .exists());
+        revert_move(&from, &to, method).unwrap();
+        assert!(from
+            .join("Microsoft")
+            .join("phi-4-1")
+            .join("model.onnx")
+            .exists());
+        assert!(!to.exists());
+
+        copy_tree(&from, &to).unwrap();
+        verify_copy(&from, &to).unwrap();
+        std::fs::write(to.join("Microsoft").join("phi-4-1").join("model.onnx"), b"").unwrap();
+        assert!(verify_copy(&from, &to).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
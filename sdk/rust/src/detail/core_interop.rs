@@ -651,16 +651,42 @@ impl CoreInterop {
         self: &Arc<Self>,
         command: String,
         params: Option<Value>,
+    ) -> Result<tokio::sync::mpsc::UnboundedReceiver<Result<String>>> {
+        self.streaming_channel(command, params, None)
+    }
+
+    /// Like [`Self::execute_command_streaming_channel`], but the native call
+    /// stops at the next chunk once `cancel_flag` is set, sending an
+    /// "Operation cancelled" error through the channel.
+    pub async fn execute_command_streaming_channel_cancellable(
+        self: &Arc<Self>,
+        command: String,
+        params: Option<Value>,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<tokio::sync::mpsc::UnboundedReceiver<Result<String>>> {
+        self.streaming_channel(command, params, Some(cancel_flag))
+    }
+
+    fn streaming_channel(
+        self: &Arc<Self>,
+        command: String,
+        params: Option<Value>,
+        cancel_flag: Option<Arc<AtomicBool>>,
     ) -> Result<tokio::sync::mpsc::UnboundedReceiver<Result<String>>> {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<Result<String>>();
         let this = Arc::clone(self);
 
         tokio::task::spawn_blocking(move || {
             let tx_chunk = tx.clone();
-            let result =
-                this.execute_command_streaming(&command, params.as_ref(), move |chunk: &str| {
-                    let _ = tx_chunk.send(Ok(chunk.to_owned()));
-                });
+            let mut callback = move |chunk: &str| {
+                let _ = tx_chunk.send(Ok(chunk.to_owned()));
+            };
+            let result = this.execute_command_streaming_impl(
+                &command,
+                params.as_ref(),
+                &mut callback,
+                cancel_flag,
+            );
 
             match result {
                 Ok(_final_payload) => {
@@ -810,6 +836,16 @@ impl CoreInterop {
     }
 }
 
+// ── Command helpers ──────────────────────────────────────────────────────────
+
+/// Query the model cache directory currently used by the native core.
+pub(crate) async fn get_cache_directory(core: &Arc<CoreInterop>) -> Result<PathBuf> {
+    let raw = core
+        .execute_command_async("get_cache_directory".into(), None)
+        .await?;
+    Ok(PathBuf::from(raw.trim()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::{checked_i32_length, StreamingCallbackState};
@@ -0,0 +1,119 @@
+//! RAII lease on a loaded model variant.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::model_variant::ModelVariant;
+use super::residency_manager::Lease;
+use super::UsageTracker;
+use crate::error::Result;
+use crate::openai::AudioClient;
+use crate::openai::ChatClient;
+use crate::openai::ChatSession;
+use crate::openai::EmbeddingClient;
+use crate::openai::ResponsesClient;
+use crate::types::ModelInfo;
+
+/// A guard that keeps a model variant loaded.
+///
+/// Returned by [`Model::acquire`](crate::Model::acquire).  The variant is
+/// loaded on first acquisition; further acquisitions and clones share the
+/// same lease.  When the last guard — including clients created from it — is
+/// dropped, the variant is unloaded unless it was [pinned](LoadedModel::pin).
+///
+/// While any guard is alive, [`Model::unload`](crate::Model::unload) fails
+/// for the variant and the [`ResidencyManager`](crate::ResidencyManager)
+/// neither evicts it nor unloads it when idle.
+#[derive(Clone)]
+pub struct LoadedModel {
+    variant: ModelVariant,
+    lease: Arc<Lease>,
+}
+
+impl fmt::Debug for LoadedModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadedModel")
+            .field("id", &self.variant.id())
+            .field("leases", &Arc::strong_count(&self.lease))
+            .finish()
+    }
+}
+
+impl LoadedModel {
+    pub(crate) fn new(variant: ModelVariant, lease: Arc<Lease>) -> Self {
+        Self { variant, lease }
+    }
+
+    /// Unique identifier of the leased variant.
+    pub fn id(&self) -> &str {
+        self.variant.id()
+    }
+
+    /// Alias shared by all variants of the model.
+    pub fn alias(&self) -> &str {
+        self.variant.alias()
+    }
+
+    /// Full catalog metadata of the leased variant.
+    pub fn info(&self) -> &ModelInfo {
+        self.variant.info()
+    }
+
+    /// Keep the variant loaded after the last guard is dropped.
+    ///
+    /// A pinned variant is also exempt from idle unloading and eviction.
+    /// It stays loaded until [`unpin`](LoadedModel::unpin) is called and it
+    /// is unloaded explicitly.
+    pub fn pin(&self) -> Result<()> {
+        self.lease.residency().set_pinned(self.id(), true)
+    }
+
+    /// Undo [`pin`](LoadedModel::pin).
+    pub fn unpin(&self) -> Result<()> {
+        self.lease.residency().set_pinned(self.id(), false)
+    }
+
+    /// Whether the variant is pinned.
+    pub fn is_pinned(&self) -> Result<bool> {
+        self.lease.residency().is_pinned(self.id())
+    }
+
+    /// Create a [`ChatClient`] that keeps this lease alive.
+    pub fn create_chat_client(&self) -> ChatClient {
+        self.variant
+            .create_chat_client()
+            .with_usage_tracker(self.usage_tracker())
+    }
+
+    /// Start a [`ChatSession`] that keeps this lease alive.
+    pub fn create_chat_session(&self) -> ChatSession {
+        super::model_variant::chat_session_for(self.create_chat_client(), self.info())
+    }
+
+    /// Create an [`AudioClient`] that keeps this lease alive.
+    pub fn create_audio_client(&self) -> AudioClient {
+        self.variant
+            .create_audio_client()
+            .with_usage_tracker(self.usage_tracker())
+    }
+
+    /// Create an [`EmbeddingClient`] that keeps this lease alive.
+    pub fn create_embedding_client(&self) -> EmbeddingClient {
+        self.variant
+            .create_embedding_client()
+            .with_usage_tracker(self.usage_tracker())
+    }
+
+    /// Create a [`ResponsesClient`] for the web service at `base_url` that
+    /// keeps this lease alive.
+    pub fn create_responses_client(&self, base_url: &str) -> Result<ResponsesClient> {
+        Ok(self
+            .variant
+            .create_responses_client(base_url)?
+            .with_usage_tracker(self.usage_tracker()))
+    }
+
+    fn usage_tracker(&self) -> UsageTracker {
+        UsageTracker::new(self.id(), self.lease.residency()).with_lease(Arc::clone(&self.lease))
+    }
+}
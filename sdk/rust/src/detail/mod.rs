@@ -1,6 +1,30 @@
+pub(crate) mod cache_lock;
+pub(crate) mod cache_maintenance;
 pub(crate) mod core_interop;
+pub(crate) mod loaded_model;
 pub(crate) mod model;
+pub(crate) mod model_archive;
+pub(crate) mod model_inspect;
 mod model_load_manager;
 pub(crate) mod model_variant;
+pub(crate) mod preflight;
+mod residency_manager;
+pub(crate) mod warm_up;
 
+pub(crate) use self::model_load_manager::LoadOptions;
 pub use self::model_load_manager::ModelLoadManager;
+pub use self::residency_manager::ResidencyManager;
+pub(crate) use self::residency_manager::UsageTracker;
+
+use crate::error::{FoundryLocalError, Result};
+
+/// Run blocking filesystem work off the async runtime.
+pub(crate) async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| FoundryLocalError::Internal {
+            reason: format!("task join error: {e}"),
+        })?
+}
@@ -5,17 +5,24 @@
 //! the same alias, but callers never need to know which kind they hold.
 
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
 use std::sync::Arc;
 
 use super::core_interop::CoreInterop;
+use super::loaded_model::LoadedModel;
+use super::model_inspect::ModelInspection;
 use super::model_variant::ModelVariant;
+use super::warm_up::WarmUpReport;
+use super::LoadOptions;
 use crate::error::{FoundryLocalError, Result};
 use crate::openai::AudioClient;
 use crate::openai::ChatClient;
+use crate::openai::ChatSession;
 use crate::openai::EmbeddingClient;
-use crate::types::ModelInfo;
+use crate::openai::ResponsesClient;
+use crate::tokenizer::Tokenizer;
+use crate::types::{ArenaExtendStrategy, ModelInfo, ModelLicense};
 
 /// The public model type.
 ///
@@ -33,14 +40,21 @@ pub struct Model {
 }
 
 type DownloadProgressCallback = Box<dyn FnMut(f64) + Send + 'static>;
+type LicenseAcceptanceHook = Box<dyn FnOnce(&ModelLicense) -> bool + Send + 'static>;
 
 /// Builder for configuring and running a model download.
 ///
 /// Use this builder when combining optional settings like progress and cancellation.
+///
+/// Before downloading, [`run`](DownloadBuilder::run) performs pre-flight
+/// checks: the license acceptance hook (if configured) and a free-space check
+/// of the model cache volume against the variant's `file_size_mb`.
 pub struct DownloadBuilder<'a> {
     model: &'a Model,
     progress: Option<DownloadProgressCallback>,
     cancel_flag: Option<Arc<AtomicBool>>,
+    license_hook: Option<LicenseAcceptanceHook>,
+    check_disk_space: bool,
 }
 
 impl<'a> DownloadBuilder<'a> {
@@ -49,6 +63,8 @@ impl<'a> DownloadBuilder<'a> {
             model,
             progress: None,
             cancel_flag: None,
+            license_hook: None,
+            check_disk_space: true,
         }
     }
 
@@ -67,15 +83,140 @@ impl<'a> DownloadBuilder<'a> {
         self
     }
 
-    /// Run the configured download.
+    /// Require the model license to be accepted before downloading.
+    ///
+    /// `hook` receives the license terms and returns `true` to accept or
+    /// `false` to veto, in which case [`run`](DownloadBuilder::run) fails with
+    /// [`FoundryLocalError::LicenseNotAccepted`].  Accepted licenses are
+    /// recorded under the app data directory, so the hook is not called again
+    /// for the same model and license.
+    pub fn accept_license<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&ModelLicense) -> bool + Send + 'static,
+    {
+        self.license_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Skip the free-space check of the model cache volume.
+    pub fn skip_disk_space_check(mut self) -> Self {
+        self.check_disk_space = false;
+        self
+    }
+
+    /// Run the pre-flight checks and then the configured download.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::LicenseNotAccepted`] when the license hook
+    /// vetoes the download and [`FoundryLocalError::InsufficientDiskSpace`]
+    /// when the cache volume cannot hold the variant.
     pub async fn run(self) -> Result<()> {
-        self.model
-            .selected_variant()
+        let variant = self.model.selected_variant();
+        if let Some(hook) = self.license_hook {
+            variant.ensure_license_accepted(hook)?;
+        }
+        if self.check_disk_space {
+            variant.check_disk_space().await?;
+        }
+        variant
             .download_with_options(self.progress, self.cancel_flag)
             .await
     }
 }
 
+/// Builder for loading a model with session options.
+///
+/// Options are validated against the variant's [`ModelInfo`] before the load
+/// request is sent; unset options keep the core defaults.
+///
+/// ```ignore
+/// model.load_builder()
+///     .intra_op_threads(4)
+///     .max_context_length(4096)
+///     .run()
+///     .await?;
+/// ```
+pub struct LoadBuilder<'a> {
+    model: &'a Model,
+    execution_provider: Option<String>,
+    options: LoadOptions,
+    warm_up: bool,
+}
+
+impl<'a> LoadBuilder<'a> {
+    fn new(model: &'a Model) -> Self {
+        Self {
+            model,
+            execution_provider: None,
+            options: LoadOptions::default(),
+            warm_up: false,
+        }
+    }
+
+    /// Load the variant built for `ep` (e.g. `"CUDAExecutionProvider"`)
+    /// instead of the selected one.  The selection itself is unchanged.
+    pub fn execution_provider(mut self, ep: impl Into<String>) -> Self {
+        self.execution_provider = Some(ep.into());
+        self
+    }
+
+    /// Number of threads used to parallelise execution within an operator.
+    ///
+    /// Cap this when several models share a machine.
+    pub fn intra_op_threads(mut self, threads: u32) -> Self {
+        self.options.intra_op_num_threads = Some(threads);
+        self
+    }
+
+    /// Maximum context length in tokens.  Must not exceed the model's
+    /// [`context_length`](Model::context_length).
+    pub fn max_context_length(mut self, tokens: u64) -> Self {
+        self.options.max_context_length = Some(tokens);
+        self
+    }
+
+    /// Enable or disable the ONNX Runtime memory arena.
+    pub fn memory_arena(mut self, enabled: bool) -> Self {
+        self.options.enable_memory_arena = Some(enabled);
+        self
+    }
+
+    /// Set how the memory arena grows when it runs out of space.
+    pub fn arena_extend_strategy(mut self, strategy: ArenaExtendStrategy) -> Self {
+        self.options.arena_extend_strategy = Some(strategy);
+        self
+    }
+
+    /// Run a [warm-up](Model::warm_up) request once the variant is loaded,
+    /// so the first real request does not pay for initialisation.
+    pub fn warm_up(mut self, enabled: bool) -> Self {
+        self.warm_up = enabled;
+        self
+    }
+
+    /// Validate the options and load the selected variant, or the one for
+    /// the requested execution provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::Validation`] if no variant targets the
+    /// requested execution provider, an option is incompatible with the
+    /// variant, or the variant is already loaded with other options; or the
+    /// warm-up error if the warm-up request fails.
+    pub async fn run(self) -> Result<()> {
+        let variant = match &self.execution_provider {
+            Some(ep) => self.model.variant_for_execution_provider(ep)?,
+            None => self.model.selected_variant(),
+        };
+        variant.load_with_options(&self.options).await?;
+        if self.warm_up {
+            variant.warm_up().await?;
+        }
+        Ok(())
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 enum ModelKind {
     /// A single model variant (from `get_model_variant` or `variants()`).
@@ -190,6 +331,27 @@ impl Model {
             } => &variants[selected.load(Relaxed)],
         }
     }
+
+    /// The variant built for `ep`, preferring the selected one.
+    fn variant_for_execution_provider(&self, ep: &str) -> Result<&ModelVariant> {
+        let targets = |v: &ModelVariant| {
+            v.info_ref()
+                .runtime
+                .as_ref()
+                .is_some_and(|r| r.execution_provider == ep)
+        };
+        let current = self.selected_variant();
+        if targets(current) {
+            return Ok(current);
+        }
+        let found = match &self.inner {
+            ModelKind::ModelVariant(_) => None,
+            ModelKind::Model { variants, .. } => variants.iter().find(|v| targets(v)),
+        };
+        found.ok_or_else(|| FoundryLocalError::Validation {
+            reason: format!("model '{}' has no variant for {ep}", self.alias()),
+        })
+    }
 }
 
 // ── Public API ───────────────────────────────────────────────────────────────
@@ -250,6 +412,9 @@ impl Model {
 
     /// Download the (selected) variant.  If `progress` is provided it
     /// receives download progress as a percentage (0.0–100.0).
+    ///
+    /// Holds the variant's model cache lock for the duration; see
+    /// [`CacheLockPolicy`](crate::CacheLockPolicy).
     pub async fn download<F>(&self, progress: Option<F>) -> Result<()>
     where
         F: FnMut(f64) + Send + 'static,
@@ -270,17 +435,101 @@ impl Model {
         self.selected_variant().path().await
     }
 
+    /// Describe the files of the (selected) cached variant.
+    ///
+    /// Lists every file with its size and reads `genai_config.json` and
+    /// `tokenizer_config.json` for the exported context length, vocabulary
+    /// size, end-of-sequence tokens and chat template.  Useful to size
+    /// prompts and to verify a download.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the variant is not cached or a config file is not
+    /// valid JSON.
+    pub async fn inspect(&self) -> Result<ModelInspection> {
+        self.selected_variant().inspect().await
+    }
+
+    /// Load the tokenizer of the (selected) cached variant.
+    ///
+    /// Reads the variant's `tokenizer.json` without loading the model, so
+    /// prompts can be counted and trimmed before any inference.  Chat
+    /// messages are counted with the variant's [`PromptTemplate`].
+    ///
+    /// [`PromptTemplate`]: crate::PromptTemplate
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the variant is not cached, has no
+    /// `tokenizer.json`, or uses a tokenizer other than byte-pair encoding.
+    pub async fn tokenizer(&self) -> Result<Tokenizer> {
+        self.selected_variant().tokenizer().await
+    }
+
+    /// Package the (selected) variant into a portable archive at `dest`.
+    ///
+    /// The archive holds every file of the cached variant together with its
+    /// [`ModelInfo`] and per-file SHA-256 digests.  Install it on another
+    /// machine with [`Catalog::import`](crate::Catalog::import).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the variant is not cached, or
+    /// [`FoundryLocalError::CacheLocked`] if a download or removal holds the
+    /// variant's cache lock.
+    pub async fn export(&self, dest: impl AsRef<Path>) -> Result<()> {
+        self.selected_variant().export(dest.as_ref()).await
+    }
+
     /// Load the (selected) variant into memory.
     pub async fn load(&self) -> Result<()> {
         self.selected_variant().load().await
     }
 
+    /// Configure and run a model load with session options.
+    pub fn load_builder(&self) -> LoadBuilder<'_> {
+        LoadBuilder::new(self)
+    }
+
+    /// Run a minimal request matched to the model's task and measure it.
+    ///
+    /// Chat models generate one token, embedding models embed a short
+    /// string and transcription models transcribe half a second of silence.
+    /// Call it after [`load`](Model::load) so the first real request does not
+    /// pay for graph initialisation; the reported latency also serves as a
+    /// readiness probe.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the variant is not loaded or its task has no
+    /// warm-up request.
+    pub async fn warm_up(&self) -> Result<WarmUpReport> {
+        self.selected_variant().warm_up().await
+    }
+
+    /// Load the (selected) variant and return a guard that keeps it loaded.
+    ///
+    /// Guards are reference counted across clones and tasks: the variant is
+    /// loaded on first acquisition and unloaded when the last guard is
+    /// dropped, unless it was pinned with [`LoadedModel::pin`].  Use this
+    /// when several parts of an application share a model.
+    pub async fn acquire(&self) -> Result<LoadedModel> {
+        self.selected_variant().acquire().await
+    }
+
     /// Unload the (selected) variant from memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error while a [`LoadedModel`] guard holds the variant.
     pub async fn unload(&self) -> Result<String> {
         self.selected_variant().unload().await
     }
 
     /// Remove the (selected) variant from the local cache.
+    ///
+    /// Holds the variant's model cache lock for the duration; see
+    /// [`CacheLockPolicy`](crate::CacheLockPolicy).
     pub async fn remove_from_cache(&self) -> Result<String> {
         self.selected_variant().remove_from_cache().await
     }
@@ -290,6 +539,12 @@ impl Model {
         self.selected_variant().create_chat_client()
     }
 
+    /// Start a [`ChatSession`] on the (selected) variant, trimmed to its
+    /// context length.
+    pub fn create_chat_session(&self) -> ChatSession {
+        self.selected_variant().create_chat_session()
+    }
+
     /// Create an [`AudioClient`] bound to the (selected) variant.
     pub fn create_audio_client(&self) -> AudioClient {
         self.selected_variant().create_audio_client()
@@ -300,6 +555,12 @@ impl Model {
         self.selected_variant().create_embedding_client()
     }
 
+    /// Create a [`ResponsesClient`] for the (selected) variant, talking to
+    /// the web service at `base_url`.
+    pub fn create_responses_client(&self, base_url: &str) -> Result<ResponsesClient> {
+        self.selected_variant().create_responses_client(base_url)
+    }
+
     /// Available variants of this model.
     ///
     /// For a single-variant model (e.g. from
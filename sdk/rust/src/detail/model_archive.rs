@@ -0,0 +1,445 @@
+//! Portable archives of cached model variants.
+//!
+//! An archive is a zip file containing a `manifest.json` entry and every
+//! file of the variant under `files/`.  The manifest carries the variant's
+//! [`ModelInfo`], its directory relative to the model cache, and the size and
+//! SHA-256 digest of each file so that [`install_archive`] can verify the
+//! contents before anything is placed in the cache.
+//!
+//! Files are stored uncompressed: model weights do not compress well and
+//! archives are typically several gigabytes.
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::{FoundryLocalError, Result};
+use crate::types::ModelInfo;
+
+/// Version of the archive layout written by [`write_archive`].
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const FILES_PREFIX: &str = "files/";
+
+/// Describes the contents of a model archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveManifest {
+    format_version: u32,
+    model_info: ModelInfo,
+    /// Directory of the variant relative to the model cache root, using `/`
+    /// separators.
+    relative_dir: String,
+    files: Vec<ArchiveFile>,
+}
+
+/// A single file inside a model archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveFile {
+    /// Path relative to the variant directory, using `/` separators.
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+/// Package the variant stored in `model_dir` into an archive at `dest`.
+///
+/// The archive is written to a temporary sibling file first and renamed into
+/// place once complete, so a failed export never leaves a truncated archive
+/// at `dest`.
+pub(crate) fn write_archive(
+    info: &ModelInfo,
+    cache_dir: &Path,
+    model_dir: &Path,
+    dest: &Path,
+) -> Result<()> {
+    let relative_dir = match model_dir.strip_prefix(cache_dir) {
+        Ok(rel) => to_archive_path(rel)?,
+        Err(_) => model_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| FoundryLocalError::ModelOperation {
+                reason: format!("invalid model directory '{}'", model_dir.display()),
+            })?,
+    };
+
+    let mut relative_files = Vec::new();
+    collect_files(model_dir, Path::new(""), &mut relative_files)?;
+    relative_files.sort();
+
+    let mut files = Vec::with_capacity(relative_files.len());
+    for rel in &relative_files {
+        let (size, sha256) = hash_file(&model_dir.join(rel))?;
+        files.push(ArchiveFile {
+            path: to_archive_path(rel)?,
+            size,
+            sha256,
+        });
+    }
+
+    let manifest = ArchiveManifest {
+        format_version: FORMAT_VERSION,
+        model_info: info.clone(),
+        relative_dir,
+        files,
+    };
+
+    let partial = partial_path(dest);
+    let result = (|| -> Result<()> {
+        let mut writer = ZipWriter::new(BufWriter::new(File::create(&partial)?));
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(true);
+
+        writer
+            .start_file(MANIFEST_ENTRY, options)
+            .map_err(zip_error)?;
+        writer.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+
+        for (rel, file) in relative_files.iter().zip(&manifest.files) {
+            writer
+                .start_file(format!("{FILES_PREFIX}{}", file.path), options)
+                .map_err(zip_error)?;
+            std::io::copy(
+                &mut BufReader::new(File::open(model_dir.join(rel))?),
+                &mut writer,
+            )?;
+        }
+
+        writer.finish().map_err(zip_error)?.flush()?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => Ok(std::fs::rename(&partial, dest)?),
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Verify the archive at `archive` and install its variant under `cache_dir`.
+///
+/// Files are extracted into a staging directory inside the cache and checked
+/// against the manifest; only a fully verified variant is moved to its final
+/// location.  Returns the [`ModelInfo`] recorded in the archive.
+///
+/// # Errors
+///
+/// Fails if the archive is malformed, a file does not match its recorded size
+/// or digest, an entry escapes the variant directory, or the variant already
+/// exists in the cache.
+pub(crate) fn install_archive(archive: &Path, cache_dir: &Path) -> Result<ModelInfo> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(archive)?)).map_err(zip_error)?;
+    let manifest = read_manifest(&mut zip)?;
+
+    let target = cache_dir.join(validated_relative_path(&manifest.relative_dir)?);
+    if target.exists() {
+        return Err(FoundryLocalError::ModelOperation {
+            reason: format!(
+                "model '{}' already exists in the cache at '{}'",
+                manifest.model_info.id,
+                target.display()
+            ),
+        });
+    }
+
+    let staging = create_staging_dir(cache_dir)?;
+    let result = (|| -> Result<()> {
+        for file in &manifest.files {
+            let dest = staging.join(validated_relative_path(&file.path)?);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let mut entry = zip
+                .by_name(&format!("{FILES_PREFIX}{}", file.path))
+                .map_err(zip_error)?;
+            let mut out = BufWriter::new(File::create(&dest)?);
+            let (size, sha256) = copy_and_hash(&mut entry, &mut out)?;
+            out.flush()?;
+
+            if size != file.size || sha256 != file.sha256 {
+                return Err(FoundryLocalError::Validation {
+                    reason: format!(
+                        "model archive entry '{}' failed verification (expected {} bytes, sha256 {}; got {size} bytes, sha256 {sha256})",
+                        file.path, file.size, file.sha256
+                    ),
+                });
+            }
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&staging, &target)?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    Ok(manifest.model_info)
+}
+
+/// The [`ModelInfo`] recorded in the archive at `archive`, without
+/// verifying or extracting its files.
+pub(crate) fn read_archive_info(archive: &Path) -> Result<ModelInfo> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(archive)?)).map_err(zip_error)?;
+    Ok(read_manifest(&mut zip)?.model_info)
+}
+
+fn read_manifest<R: Read + std::io::Seek>(zip: &mut ZipArchive<R>) -> Result<ArchiveManifest> {
+    let manifest: ArchiveManifest = {
+        let entry = zip.by_name(MANIFEST_ENTRY).map_err(zip_error)?;
+        serde_json::from_reader(entry)?
+    };
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(FoundryLocalError::Validation {
+            reason: format!(
+                "unsupported model archive format version {} (expected {FORMAT_VERSION})",
+                manifest.format_version
+            ),
+        });
+    }
+    Ok(manifest)
+}
+
+/// `dest` with `.partial` appended, keeping its extension.
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = OsString::from(dest.as_os_str());
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// Create an empty staging directory in `cache_dir` that no other import,
+/// in this or another process, uses.
+fn create_staging_dir(cache_dir: &Path) -> Result<PathBuf> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    std::fs::create_dir_all(cache_dir)?;
+    loop {
+        let staging = cache_dir.join(format!(
+            ".import-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        match std::fs::create_dir(&staging) {
+            Ok(()) => return Ok(staging),
+            // Left behind by an earlier process with the same id.
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+pub(super) fn collect_files(root: &Path, rel: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(root.join(rel))? {
+        let entry = entry?;
+        let rel_path = rel.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &rel_path, out)?;
+        } else {
+            out.push(rel_path);
+        }
+    }
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> Result<(u64, String)> {
+    copy_and_hash(&mut BufReader::new(File::open(path)?), &mut std::io::sink())
+}
+
+/// Copy `reader` into `writer`, returning the byte count and hex SHA-256.
+fn copy_and_hash(reader: &mut impl Read, writer: &mut impl Write) -> Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+    let digest = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok((size, digest))
+}
+
+/// Convert a relative file-system path into a `/`-separated archive path.
+fn to_archive_path(path: &Path) -> Result<String> {
+    let parts: Vec<&str> = path
+        .components()
+        .map(|c| match c {
+            Component::Normal(s) => s.to_str().ok_or_else(|| FoundryLocalError::Validation {
+                reason: format!("path '{}' is not valid UTF-8", path.display()),
+            }),
+            _ => Err(FoundryLocalError::Validation {
+                reason: format!("path '{}' is not a plain relative path", path.display()),
+            }),
+        })
+        .collect::<Result<_>>()?;
+    Ok(parts.join("/"))
+}
+
+/// Parse an archive path, rejecting anything that could escape its root.
+fn validated_relative_path(path: &str) -> Result<PathBuf> {
+    let mut out = PathBuf::new();
+    for part in path.split('/') {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => out.push(part),
+            _ => {
+                return Err(FoundryLocalError::Validation {
+                    reason: format!("model archive contains an unsafe path '{path}'"),
+                })
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn zip_error(e: zip::result::ZipError) -> FoundryLocalError {
+    FoundryLocalError::ModelOperation {
+        reason: format!("model archive error: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "foundry-local-archive-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn test_info() -> ModelInfo {
+        serde_json::from_value(serde_json::json!({
+            "id": "tiny-model-cpu:1",
+            "name": "tiny-model-cpu",
+            "version": 1,
+            "alias": "tiny-model",
+            "providerType": "AzureFoundry",
+            "uri": "azureml://tiny",
+            "modelType": "ONNX",
+            "cached": true
+        }))
+        .unwrap()
+    }
+
+    fn populate(cache: &Path) -> PathBuf {
+        let model_dir = cache.join("Microsoft").join("tiny-model-cpu-1");
+        std::fs::create_dir_all(model_dir.join("sub")).unwrap();
+        std::fs::write(model_dir.join("model.onnx"), b"weights").unwrap();
+        std::fs::write(model_dir.join("sub").join("genai_config.json"), b"{}").unwrap();
+        model_dir
+    }
+
+    #[test]
+    fn export_then_install_round_trips_files_and_layout() {
+        let root = temp_dir("roundtrip");
+        let source_cache = root.join("source");
+        let model_dir = populate(&source_cache);
+        let archive = root.join("tiny.flmodel");
+
+        write_archive(&test_info(), &source_cache, &model_dir, &archive).unwrap();
+
+        let dest_cache = root.join("dest");
+        let info = install_archive(&archive, &dest_cache).unwrap();
+        assert_eq!(info.id, "tiny-model-cpu:1");
+
+        let installed = dest_cache.join("Microsoft").join("tiny-model-cpu-1");
+        assert_eq!(
+            std::fs::read(installed.join("model.onnx")).unwrap(),
+            b"weights"
+        );
+        assert!(installed.join("sub").join("genai_config.json").is_file());
+
+        // Installing the same archive twice is rejected.
+        assert!(install_archive(&archive, &dest_cache).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn partial_and_staging_paths_are_unique() {
+        assert_eq!(
+            partial_path(Path::new("out/tiny.flmodel")),
+            Path::new("out/tiny.flmodel.partial")
+        );
+        assert_ne!(
+            partial_path(Path::new("tiny.a")),
+            partial_path(Path::new("tiny.b"))
+        );
+
+        let cache = temp_dir("staging");
+        let first = create_staging_dir(&cache).unwrap();
+        std::fs::write(first.join("model.onnx"), b"in progress").unwrap();
+        let second = create_staging_dir(&cache).unwrap();
+        assert_ne!(first, second);
+        assert!(first.join("model.onnx").is_file());
+        assert_eq!(std::fs::read_dir(&second).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn install_rejects_tampered_files() {
+        let root = temp_dir("tampered");
+        let source_cache = root.join("source");
+        let model_dir = populate(&source_cache);
+
+        // Build a manifest whose digest does not match the stored bytes.
+        let mut manifest = ArchiveManifest {
+            format_version: FORMAT_VERSION,
+            model_info: test_info(),
+            relative_dir: "tiny".into(),
+            files: vec![ArchiveFile {
+                path: "model.onnx".into(),
+                size: 7,
+                sha256: "00".repeat(32),
+            }],
+        };
+        let archive = root.join("bad.flmodel");
+        let mut writer = ZipWriter::new(File::create(&archive).unwrap());
+        let options = SimpleFileOptions::default();
+        writer.start_file(MANIFEST_ENTRY, options).unwrap();
+        writer
+            .write_all(&serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+        writer.start_file("files/model.onnx", options).unwrap();
+        writer
+            .write_all(&std::fs::read(model_dir.join("model.onnx")).unwrap())
+            .unwrap();
+        writer.finish().unwrap();
+
+        let dest_cache = root.join("dest");
+        assert!(install_archive(&archive, &dest_cache).is_err());
+        assert!(!dest_cache.join("tiny").exists());
+
+        // Entries escaping the cache are rejected before extraction.
+        manifest.relative_dir = "../escape".into();
+        assert!(validated_relative_path(&manifest.relative_dir).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,358 @@
+//! Describe a cached model variant from the files in its directory.
+//!
+//! Reads ONNX Runtime GenAI's `genai_config.json` and the Hugging Face
+//! `tokenizer_config.json` when present.  Every field is optional because
+//! embedding and speech models ship different subsets of these files.
+
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use super::model_archive::collect_files;
+use crate::error::Result;
+
+const GENAI_CONFIG: &str = "genai_config.json";
+const TOKENIZER_CONFIG: &str = "tokenizer_config.json";
+
+/// Quantization markers searched for in the variant id and file names, most
+/// specific first.
+const QUANTIZATION_MARKERS: &[&str] = &[
+    "int4", "int8", "uint8", "q4f16", "q4", "q8", "fp16", "bf16", "fp32", "awq", "gptq",
+];
+
+/// A single file in a model directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelFile {
+    /// Path relative to the model directory.
+    pub path: PathBuf,
+    pub size_bytes: u64,
+}
+
+/// What is on disk for a cached variant, as returned by
+/// [`Model::inspect`](crate::Model::inspect).
+#[derive(Debug, Clone)]
+pub struct ModelInspection {
+    pub model_id: String,
+    /// Directory holding the variant's files.
+    pub path: PathBuf,
+    /// Every file below [`path`](Self::path), sorted by path.
+    pub files: Vec<ModelFile>,
+    pub total_size_bytes: u64,
+    /// Architecture from `genai_config.json` (e.g. `"phi3"`, `"qwen2"`).
+    pub architecture: Option<String>,
+    /// Context length the model was exported with.
+    pub context_length: Option<u64>,
+    pub vocab_size: Option<u64>,
+    pub bos_token_id: Option<i64>,
+    pub eos_token_ids: Vec<i64>,
+    /// End-of-sequence token strings from the tokenizer config.
+    pub eos_tokens: Vec<String>,
+    /// Jinja chat template from the tokenizer config.
+    pub chat_template: Option<String>,
+    /// Quantization inferred from the variant id and file names
+    /// (e.g. `"int4"`, `"fp16"`); `None` when no known marker is found.
+    pub quantization: Option<String>,
+}
+
+// Each field is read on its own: one of unexpected shape is left empty
+// without losing the others.
+
+#[derive(Debug, Default, Deserialize)]
+struct GenAiConfig {
+    #[serde(default, deserialize_with = "lenient")]
+    model: Option<GenAiModel>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GenAiModel {
+    #[serde(rename = "type", default, deserialize_with = "lenient")]
+    architecture: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    context_length: Option<u64>,
+    #[serde(default, deserialize_with = "lenient")]
+    vocab_size: Option<u64>,
+    #[serde(default, deserialize_with = "lenient")]
+    bos_token_id: Option<i64>,
+    #[serde(default, deserialize_with = "lenient")]
+    eos_token_id: Option<OneOrMany<i64>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TokenizerConfig {
+    #[serde(default, deserialize_with = "lenient")]
+    eos_token: Option<TokenSpec>,
+    #[serde(default, deserialize_with = "lenient")]
+    chat_template: Option<ChatTemplate>,
+}
+
+/// `None` for a value that does not have the shape of `T`.
+fn lenient<'de, D: Deserializer<'de>, T: DeserializeOwned>(
+    deserializer: D,
+) -> std::result::Result<Option<T>, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            Self::One(v) => vec![v],
+            Self::Many(v) => v,
+        }
+    }
+}
+
+/// `"</s>"` or `{"content": "</s>", ...}`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TokenSpec {
+    Text(String),
+    Object { content: String },
+}
+
+/// A single template, or a list of named templates of which `"default"` is
+/// used.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ChatTemplate {
+    Single(String),
+    Named(Vec<NamedTemplate>),
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedTemplate {
+    name: String,
+    template: String,
+}
+
+impl ChatTemplate {
+    fn into_default(self) -> Option<String> {
+        match self {
+            Self::Single(t) => Some(t),
+            Self::Named(list) => {
+                let mut list = list.into_iter();
+                let first = list.next()?;
+                if first.name == "default" {
+                    return Some(first.template);
+                }
+                Some(
+                    list.find(|t| t.name == "default")
+                        .map_or(first.template, |t| t.template),
+                )
+            }
+        }
+    }
+}
+
+/// Describe the model files in `model_dir`.
+pub(crate) fn inspect_dir(model_id: &str, model_dir: &Path) -> Result<ModelInspection> {
+    let mut rel_paths = Vec::new();
+    collect_files(model_dir, Path::new(""), &mut rel_paths)?;
+    rel_paths.sort();
+
+    let mut files = Vec::with_capacity(rel_paths.len());
+    for path in rel_paths {
+        let size_bytes = std::fs::metadata(model_dir.join(&path))?.len();
+        files.push(ModelFile { path, size_bytes });
+    }
+    let total_size_bytes = files.iter().map(|f| f.size_bytes).sum();
+
+    let genai = read_json::<GenAiConfig>(&model_dir.join(GENAI_CONFIG))?
+        .model
+        .unwrap_or_default();
+    let tokenizer: TokenizerConfig = read_json(&model_dir.join(TOKENIZER_CONFIG))?;
+
+    let quantization = infer_quantization(
+        std::iter::once(model_id).chain(files.iter().filter_map(|f| f.path.to_str())),
+    );
+
+    Ok(ModelInspection {
+        model_id: model_id.to_owned(),
+        path: model_dir.to_path_buf(),
+        files,
+        total_size_bytes,
+        architecture: genai.architecture,
+        context_length: genai.context_length,
+        vocab_size: genai.vocab_size,
+        bos_token_id: genai.bos_token_id,
+        eos_token_ids: genai
+            .eos_token_id
+            .map(OneOrMany::into_vec)
+            .unwrap_or_default(),
+        eos_tokens: tokenizer
+            .eos_token
+            .map(|t| match t {
+                TokenSpec::Text(s) | TokenSpec::Object { content: s } => vec![s],
+            })
+            .unwrap_or_default(),
+        chat_template: tokenizer.chat_template.and_then(ChatTemplate::into_default),
+        quantization,
+    })
+}
+
+/// Parse an optional JSON file; a missing file yields the default value.
+/// Malformed JSON, or a file that is not a JSON object, is an error.
+fn read_json<T: Default + DeserializeOwned>(path: &Path) -> Result<T> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn infer_quantization<'a>(names: impl Iterator<Item = &'a str>) -> Option<String> {
+    let names: Vec<String> = names.map(str::to_ascii_lowercase).collect();
+    QUANTIZATION_MARKERS
+        .iter()
+        .find(|marker| names.iter().any(|n| contains_token(n, marker)))
+        .map(|m| (*m).to_owned())
+}
+
+/// Whether `marker` appears in `name` delimited by non-alphanumerics.
+fn contains_token(name: &str, marker: &str) -> bool {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|token| token == marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "foundry-local-inspect-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_genai_and_tokenizer_config() {
+        let dir = temp_dir("full");
+        std::fs::write(
+            dir.join(GENAI_CONFIG),
+            json!({
+                "model": {
+                    "type": "phi3",
+                    "context_length": 131072,
+                    "vocab_size": 32064,
+                    "bos_token_id": 1,
+                    "eos_token_id": [32007, 32001, 32000],
+                    "decoder": { "num_hidden_layers": 32 }
+                },
+                "search": { "max_length": 131072 }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(TOKENIZER_CONFIG),
+            json!({
+                "eos_token": { "content": "<|endoftext|>", "special": true },
+                "chat_template": "{% for m in messages %}{{ m.content }}{% endfor %}"
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::create_dir(dir.join("onnx")).unwrap();
+        std::fs::write(dir.join("onnx").join("model.onnx.data"), vec![0u8; 10]).unwrap();
+
+        let inspection = inspect_dir("Phi-3-mini-cuda-int4-awq:1", &dir).unwrap();
+        assert_eq!(inspection.architecture.as_deref(), Some("phi3"));
+        assert_eq!(inspection.context_length, Some(131072));
+        assert_eq!(inspection.vocab_size, Some(32064));
+        assert_eq!(inspection.eos_token_ids, vec![32007, 32001, 32000]);
+        assert_eq!(inspection.eos_tokens, vec!["<|endoftext|>".to_string()]);
+        assert!(inspection.chat_template.unwrap().contains("messages"));
+        assert_eq!(inspection.quantization.as_deref(), Some("int4"));
+        assert_eq!(inspection.files.len(), 3);
+        assert!(inspection
+            .files
+            .iter()
+            .any(|f| f.path == Path::new("onnx").join("model.onnx.data") && f.size_bytes == 10));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_configs_leave_fields_empty() {
+        let dir = temp_dir("bare");
+        std::fs::write(dir.join("model.onnx"), b"onnx").unwrap();
+
+        let inspection = inspect_dir("whisper-tiny-generic-cpu:1", &dir).unwrap();
+        assert_eq!(inspection.total_size_bytes, 4);
+        assert!(inspection.context_length.is_none());
+        assert!(inspection.eos_token_ids.is_empty());
+        assert!(inspection.chat_template.is_none());
+        assert!(inspection.quantization.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fields_of_unexpected_shape_do_not_hide_the_others() {
+        let dir = temp_dir("lenient");
+        std::fs::write(
+            dir.join(GENAI_CONFIG),
+            json!({
+                "model": {
+                    "type": "qwen2",
+                    "context_length": "32k",
+                    "vocab_size": 151936,
+                    "eos_token_id": [151645, "im_end"]
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(TOKENIZER_CONFIG),
+            json!({ "eos_token": null, "chat_template": 3 }).to_string(),
+        )
+        .unwrap();
+
+        let inspection = inspect_dir("qwen2.5-0.5b:1", &dir).unwrap();
+        assert_eq!(inspection.architecture.as_deref(), Some("qwen2"));
+        assert_eq!(inspection.vocab_size, Some(151936));
+        assert!(inspection.context_length.is_none());
+        assert!(inspection.eos_token_ids.is_empty());
+        assert!(inspection.eos_tokens.is_empty());
+        assert!(inspection.chat_template.is_none());
+
+        std::fs::write(dir.join(GENAI_CONFIG), "[1, 2]").unwrap();
+        assert!(inspect_dir("qwen2.5-0.5b:1", &dir).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn named_chat_templates_prefer_default() {
+        let named: ChatTemplate = serde_json::from_value(json!([
+            { "name": "tool_use", "template": "tools" },
+            { "name": "default", "template": "plain" }
+        ]))
+        .unwrap();
+        assert_eq!(named.into_default().as_deref(), Some("plain"));
+    }
+
+    #[test]
+    fn quantization_markers_must_be_whole_tokens() {
+        assert_eq!(
+            infer_quantization(["qwen2.5-0.5b-instruct-generic-cpu-fp16:4"].into_iter()).as_deref(),
+            Some("fp16")
+        );
+        assert_eq!(infer_quantization(["model-int48x"].into_iter()), None);
+    }
+}
@@ -1,15 +1,306 @@
 //! Manages model loading and unloading.
 //!
 //! When an external service URL is configured the manager delegates to HTTP
-//! endpoints (`models/load/{id}`, `models/unload/{id}`, `models/loaded`).
+//! endpoints (`models/load/{id}`, `models/unload/{id}`, `models/loaded`)
+//! using the client built from [`HttpSettings`](crate::HttpSettings).
 //! Otherwise it falls through to the native core library via [`CoreInterop`].
+//!
+//! Loads and unloads are coordinated per model: concurrent loads of the same
+//! model join the one in flight, and operations on one model never overlap.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
 
-use serde_json::json;
+use serde_json::{json, Value};
+use tokio::sync::watch;
 
 use crate::detail::core_interop::CoreInterop;
-use crate::error::Result;
+use crate::error::{FoundryLocalError, Result};
+use crate::types::{ArenaExtendStrategy, ModelInfo};
+
+/// Session options applied when loading a model.
+///
+/// Unset options are omitted from the request so the core keeps its defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LoadOptions {
+    pub intra_op_num_threads: Option<u32>,
+    pub max_context_length: Option<u64>,
+    pub enable_memory_arena: Option<bool>,
+    pub arena_extend_strategy: Option<ArenaExtendStrategy>,
+}
+
+impl LoadOptions {
+    /// Check the options against the metadata of the variant being loaded.
+    pub fn validate(&self, info: &ModelInfo) -> Result<()> {
+        if self.intra_op_num_threads == Some(0) {
+            return Err(FoundryLocalError::Validation {
+                reason: "intra-op thread count must be at least 1".into(),
+            });
+        }
+        if let Some(max_len) = self.max_context_length {
+            if max_len == 0 {
+                return Err(FoundryLocalError::Validation {
+                    reason: "maximum context length must be at least 1".into(),
+                });
+            }
+            if let Some(limit) = info.context_length {
+                if max_len > limit {
+                    return Err(FoundryLocalError::Validation {
+                        reason: format!(
+                            "maximum context length {max_len} exceeds the {limit} tokens supported by model '{}'",
+                            info.id
+                        ),
+                    });
+                }
+            }
+        }
+        if self.enable_memory_arena == Some(false) && self.arena_extend_strategy.is_some() {
+            return Err(FoundryLocalError::Validation {
+                reason: "an arena extend strategy needs the memory arena enabled".into(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Key-value pairs sent to the core (or service) alongside the model id.
+    fn to_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(n) = self.intra_op_num_threads {
+            params.push(("IntraOpNumThreads", n.to_string()));
+        }
+        if let Some(n) = self.max_context_length {
+            params.push(("MaxLength", n.to_string()));
+        }
+        if let Some(enabled) = self.enable_memory_arena {
+            params.push(("EnableMemoryArena", enabled.to_string()));
+        }
+        if let Some(strategy) = self.arena_extend_strategy {
+            params.push(("ArenaExtendStrategy", strategy.as_core_str().into()));
+        }
+        params
+    }
+}
+
+/// Lifecycle state of a model as tracked by [`LoadCoordinator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoadState {
+    Unloaded,
+    Loading,
+    Loaded,
+    Unloading,
+}
+
+/// Outcome of a load, published to callers that joined it.  Errors are
+/// carried as messages since [`FoundryLocalError`] is not `Clone`.
+type LoadOutcome = Option<std::result::Result<(), String>>;
+
+#[derive(Debug, Default)]
+struct ModelSlot {
+    /// `None` until learned from a load, an unload or a listing.
+    state: Option<LoadState>,
+    /// Options of the in-flight or completed load through this manager;
+    /// `None` if the model was loaded elsewhere or is not loaded.
+    options: Option<LoadOptions>,
+    /// Completion signal of the in-flight load, for concurrent callers.
+    inflight_load: Option<watch::Receiver<LoadOutcome>>,
+    /// Serialises load and unload of this model.
+    op: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// Per-model single-flight coordination of load and unload operations.
+#[derive(Debug, Default)]
+struct LoadCoordinator {
+    slots: Mutex<HashMap<String, ModelSlot>>,
+}
+
+impl LoadCoordinator {
+    /// Run `load` unless the model is already loaded or a load of it is in
+    /// flight, in which case the caller waits for that load's outcome.  If
+    /// the leading caller is cancelled, a waiting caller leads a new load.
+    ///
+    /// Fails if the model was loaded, or is being loaded, with options
+    /// other than `options`.
+    async fn load<F>(&self, model_id: &str, options: &LoadOptions, load: F) -> Result<()>
+    where
+        F: Future<Output = Result<()>>,
+    {
+        let (tx, op) = loop {
+            let rx = {
+                let mut slots = self.lock_slots()?;
+                let slot = slots.entry(model_id.to_owned()).or_default();
+                // A closed channel means the leading caller was cancelled.
+                let inflight = slot
+                    .inflight_load
+                    .as_ref()
+                    .filter(|rx| rx.has_changed().is_ok());
+                if inflight.is_some() || slot.state == Some(LoadState::Loaded) {
+                    if let Some(current) = slot.options.as_ref().filter(|o| *o != options) {
+                        return Err(FoundryLocalError::Validation {
+                            reason: format!(
+                                "model '{model_id}' is loaded with {current:?}; unload it before loading with {options:?}"
+                            ),
+                        });
+                    }
+                }
+                match inflight {
+                    Some(rx) => rx.clone(),
+                    None if slot.state == Some(LoadState::Loaded) => return Ok(()),
+                    None => {
+                        let (tx, rx) = watch::channel(None);
+                        slot.inflight_load = Some(rx);
+                        slot.options = Some(options.clone());
+                        break (tx, Arc::clone(&slot.op));
+                    }
+                }
+            };
+            if let Some(outcome) = Self::join_load(rx).await {
+                return outcome;
+            }
+        };
+
+        let _op = op.lock().await;
+        let pending = self.begin(model_id, LoadState::Loading)?;
+        let result = load.await;
+        pending.finish();
+        {
+            let mut slots = self.lock_slots()?;
+            let slot = slots.entry(model_id.to_owned()).or_default();
+            slot.inflight_load = None;
+            // A failed load may have left the model in any state.
+            slot.state = result.is_ok().then_some(LoadState::Loaded);
+            if result.is_err() {
+                slot.options = None;
+            }
+        }
+        let _ = tx.send(Some(result.as_ref().map(|_| ()).map_err(|e| e.to_string())));
+        result
+    }
+
+    /// Wait for the outcome of a joined load, or `None` if its leading
+    /// caller was cancelled.
+    async fn join_load(mut rx: watch::Receiver<LoadOutcome>) -> Option<Result<()>> {
+        loop {
+            if let Some(outcome) = rx.borrow_and_update().clone() {
+                return Some(
+                    outcome.map_err(|reason| FoundryLocalError::ModelOperation { reason }),
+                );
+            }
+            rx.changed().await.ok()?;
+        }
+    }
+
+    /// Run `unload` once no other operation on the model is in progress.
+    async fn unload<F>(&self, model_id: &str, unload: F) -> Result<String>
+    where
+        F: Future<Output = Result<String>>,
+    {
+        let op = Arc::clone(
+            &self
+                .lock_slots()?
+                .entry(model_id.to_owned())
+                .or_default()
+                .op,
+        );
+        let _op = op.lock().await;
+        let pending = self.begin(model_id, LoadState::Unloading)?;
+        let result = unload.await;
+        pending.finish();
+        self.set_state(model_id, result.is_ok().then_some(LoadState::Unloaded))?;
+        if result.is_ok() {
+            self.forget_options(model_id)?;
+        }
+        result
+    }
+
+    /// Enter `state` until the returned guard is finished.  A guard dropped
+    /// unfinished, because the operation's future was cancelled, forgets
+    /// the state and options so the next query lists the loaded models.
+    fn begin<'a>(&'a self, model_id: &'a str, state: LoadState) -> Result<PendingOp<'a>> {
+        self.set_state(model_id, Some(state))?;
+        Ok(PendingOp {
+            coordinator: self,
+            model_id,
+            finished: false,
+        })
+    }
+
+    /// Tracked answer to "is the model loaded?", or `None` if unknown.
+    ///
+    /// A model being unloaded still counts as loaded; one being loaded does
+    /// not yet.
+    fn is_loaded(&self, model_id: &str) -> Result<Option<bool>> {
+        let state = self.lock_slots()?.get(model_id).and_then(|s| s.state);
+        Ok(state.map(|s| matches!(s, LoadState::Loaded | LoadState::Unloading)))
+    }
+
+    /// Bring settled states in line with a fresh listing of loaded models.
+    /// Models with an operation in progress are left alone.
+    fn reconcile(&self, loaded: &[String]) -> Result<()> {
+        let mut slots = self.lock_slots()?;
+        for (id, slot) in slots.iter_mut() {
+            if !matches!(slot.state, Some(LoadState::Loading | LoadState::Unloading)) {
+                slot.state = Some(if loaded.contains(id) {
+                    LoadState::Loaded
+                } else {
+                    slot.options = None;
+                    LoadState::Unloaded
+                });
+            }
+        }
+        for id in loaded {
+            slots
+                .entry(id.clone())
+                .or_default()
+                .state
+                .get_or_insert(LoadState::Loaded);
+        }
+        Ok(())
+    }
+
+    fn forget_options(&self, model_id: &str) -> Result<()> {
+        if let Some(slot) = self.lock_slots()?.get_mut(model_id) {
+            slot.options = None;
+        }
+        Ok(())
+    }
+
+    fn set_state(&self, model_id: &str, state: Option<LoadState>) -> Result<()> {
+        self.lock_slots()?
+            .entry(model_id.to_owned())
+            .or_default()
+            .state = state;
+        Ok(())
+    }
+
+    fn lock_slots(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, ModelSlot>>> {
+        self.slots.lock().map_err(|_| FoundryLocalError::Internal {
+            reason: "model load state mutex poisoned".into(),
+        })
+    }
+}
+
+/// Resets a model's state if its load or unload does not run to completion.
+struct PendingOp<'a> {
+    coordinator: &'a LoadCoordinator,
+    model_id: &'a str,
+    finished: bool,
+}
+
+impl PendingOp<'_> {
+    fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for PendingOp<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.coordinator.set_state(self.model_id, None);
+            let _ = self.coordinator.forget_options(self.model_id);
+        }
+    }
+}
 
 /// Manages the lifecycle of loaded models.
 #[derive(Debug)]
@@ -17,25 +308,55 @@ pub struct ModelLoadManager {
     core: Arc<CoreInterop>,
     external_service_url: Option<String>,
     client: reqwest::Client,
+    coordinator: LoadCoordinator,
 }
 
 impl ModelLoadManager {
-    pub(crate) fn new(core: Arc<CoreInterop>, external_service_url: Option<String>) -> Self {
+    pub(crate) fn new(
+        core: Arc<CoreInterop>,
+        external_service_url: Option<String>,
+        client: reqwest::Client,
+    ) -> Self {
         Self {
             core,
             external_service_url,
-            client: reqwest::Client::new(),
+            client,
+            coordinator: LoadCoordinator::default(),
         }
     }
 
-    /// Load a model by its identifier.
-    pub async fn load(&self, model_id: &str) -> Result<()> {
+    /// The HTTP client built from [`HttpSettings`](crate::HttpSettings).
+    pub(crate) fn http_client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+
+    /// Load a model, passing session options to the core or service.
+    ///
+    /// Returns immediately if the model is already loaded, and joins a load
+    /// of the same model that is already in flight.
+    pub(crate) async fn load(&self, model_id: &str, options: &LoadOptions) -> Result<()> {
+        self.coordinator
+            .load(
+                model_id,
+                options,
+                self.load_uncoordinated(model_id, options),
+            )
+            .await
+    }
+
+    async fn load_uncoordinated(&self, model_id: &str, options: &LoadOptions) -> Result<()> {
+        let settings = options.to_params();
         if let Some(base_url) = &self.external_service_url {
             let encoded_id = urlencoding::encode(model_id);
-            self.http_get(&format!("{base_url}/models/load/{encoded_id}"))
+            self.http_post(&format!("{base_url}/models/load/{encoded_id}"), &settings)
                 .await?;
         } else {
-            let params = json!({ "Params": { "Model": model_id } });
+            let mut params = serde_json::Map::new();
+            params.insert("Model".into(), json!(model_id));
+            for (key, value) in settings {
+                params.insert(key.into(), json!(value));
+            }
+            let params = json!({ "Params": Value::Object(params) });
             self.core
                 .execute_command_async("load_model".into(), Some(params))
                 .await?;
@@ -44,10 +365,18 @@ impl ModelLoadManager {
     }
 
     /// Unload a previously loaded model.
+    ///
+    /// Waits for any load of the same model that is in flight.
     pub async fn unload(&self, model_id: &str) -> Result<String> {
+        self.coordinator
+            .unload(model_id, self.unload_uncoordinated(model_id))
+            .await
+    }
+
+    async fn unload_uncoordinated(&self, model_id: &str) -> Result<String> {
         if let Some(base_url) = &self.external_service_url {
             let encoded_id = urlencoding::encode(model_id);
-            self.http_get(&format!("{base_url}/models/unload/{encoded_id}"))
+            self.http_post(&format!("{base_url}/models/unload/{encoded_id}"), &[])
                 .await
         } else {
             let params = json!({ "Params": { "Model": model_id } });
@@ -72,9 +401,22 @@ impl ModelLoadManager {
         } else {
             serde_json::from_str(&raw)?
         };
+        self.coordinator.reconcile(&ids)?;
         Ok(ids)
     }
 
+    /// Whether a model is loaded.
+    ///
+    /// Answered from tracked state when the model was loaded, unloaded or
+    /// listed through this manager; otherwise the loaded models are listed.
+    pub(crate) async fn is_loaded(&self, model_id: &str) -> Result<bool> {
+        if let Some(loaded) = self.coordinator.is_loaded(model_id)? {
+            return Ok(loaded);
+        }
+        let loaded = self.list_loaded().await?;
+        Ok(loaded.iter().any(|id| id == model_id))
+    }
+
     async fn http_get(&self, url: &str) -> Result<String> {
         let body = self
             .client
@@ -86,4 +428,279 @@ impl ModelLoadManager {
             .await?;
         Ok(body)
     }
+
+    /// POST a state-changing request, retrying with GET for services that
+    /// only accept GET on these endpoints.
+    async fn http_post(&self, url: &str, query: &[(&str, String)]) -> Result<String> {
+        let mut response = self.client.post(url).query(query).send().await?;
+        if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            response = self.client.get(url).query(query).send().await?;
+        }
+        Ok(response.error_for_status()?.text().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(context_length: Option<u64>) -> ModelInfo {
+        serde_json::from_value(json!({
+            "id": "tiny-model-gpu:1",
+            "name": "tiny-model-gpu",
+            "version": 1,
+            "alias": "tiny-model",
+            "providerType": "AzureFoundry",
+            "uri": "azureml://tiny",
+            "modelType": "ONNX",
+            "cached": true,
+            "contextLength": context_length
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn default_options_send_no_settings() {
+        assert!(LoadOptions::default().to_params().is_empty());
+        assert!(LoadOptions::default().validate(&info(None)).is_ok());
+    }
+
+    #[test]
+    fn options_serialize_to_core_params() {
+        let options = LoadOptions {
+            intra_op_num_threads: Some(4),
+            max_context_length: Some(2048),
+            enable_memory_arena: Some(true),
+            arena_extend_strategy: Some(ArenaExtendStrategy::SameAsRequested),
+        };
+        assert_eq!(
+            options.to_params(),
+            vec![
+                ("IntraOpNumThreads", "4".to_string()),
+                ("MaxLength", "2048".to_string()),
+                ("EnableMemoryArena", "true".to_string()),
+                ("ArenaExtendStrategy", "kSameAsRequested".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_checks_options_against_the_model() {
+        let info = info(Some(4096));
+        let invalid = [
+            LoadOptions {
+                intra_op_num_threads: Some(0),
+                ..LoadOptions::default()
+            },
+            LoadOptions {
+                max_context_length: Some(8192),
+                ..LoadOptions::default()
+            },
+            LoadOptions {
+                enable_memory_arena: Some(false),
+                arena_extend_strategy: Some(ArenaExtendStrategy::NextPowerOfTwo),
+                ..LoadOptions::default()
+            },
+        ];
+        for options in invalid {
+            assert!(options.validate(&info).is_err(), "{options:?}");
+        }
+
+        let fits = LoadOptions {
+            intra_op_num_threads: Some(2),
+            max_context_length: Some(4096),
+            ..LoadOptions::default()
+        };
+        assert!(fits.validate(&info).is_ok());
+    }
+
+    #[tokio::test]
+    async fn loading_with_other_options_fails_until_unloaded() {
+        let coordinator = LoadCoordinator::default();
+        let capped = LoadOptions {
+            intra_op_num_threads: Some(2),
+            ..LoadOptions::default()
+        };
+        coordinator
+            .load("m", &capped, async { Ok(()) })
+            .await
+            .unwrap();
+        coordinator
+            .load("m", &capped, async { panic!("should not reload") })
+            .await
+            .unwrap();
+        let err = coordinator
+            .load("m", &LoadOptions::default(), async { Ok(()) })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FoundryLocalError::Validation { .. }));
+
+        coordinator
+            .unload("m", async { Ok(String::new()) })
+            .await
+            .unwrap();
+        coordinator
+            .load("m", &LoadOptions::default(), async { Ok(()) })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn concurrent_loads_share_one_operation() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let coordinator = Arc::new(LoadCoordinator::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let coordinator = Arc::clone(&coordinator);
+                let calls = Arc::clone(&calls);
+                tokio::spawn(async move {
+                    coordinator
+                        .load("m", &LoadOptions::default(), async {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                            Ok(())
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(coordinator.is_loaded("m").unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn joined_load_reports_the_leader_failure() {
+        let coordinator = Arc::new(LoadCoordinator::default());
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+
+        let leader = {
+            let coordinator = Arc::clone(&coordinator);
+            tokio::spawn(async move {
+                coordinator
+                    .load("m", &LoadOptions::default(), async {
+                        released.await.ok();
+                        Err(FoundryLocalError::ModelOperation {
+                            reason: "out of memory".into(),
+                        })
+                    })
+                    .await
+            })
+        };
+        tokio::task::yield_now().await;
+        let follower = {
+            let coordinator = Arc::clone(&coordinator);
+            tokio::spawn(async move {
+                coordinator
+                    .load("m", &LoadOptions::default(), async { Ok(()) })
+                    .await
+            })
+        };
+        tokio::task::yield_now().await;
+        release.send(()).unwrap();
+
+        assert!(leader.await.unwrap().is_err());
+        let err = follower.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("out of memory"));
+        assert_eq!(coordinator.is_loaded("m").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn waiter_leads_a_new_load_when_the_leader_is_cancelled() {
+        let coordinator = Arc::new(LoadCoordinator::default());
+        let leader = {
+            let coordinator = Arc::clone(&coordinator);
+            tokio::spawn(async move {
+                coordinator
+                    .load("m", &LoadOptions::default(), std::future::pending())
+                    .await
+            })
+        };
+        tokio::task::yield_now().await;
+        let follower = {
+            let coordinator = Arc::clone(&coordinator);
+            tokio::spawn(async move {
+                coordinator
+                    .load("m", &LoadOptions::default(), async { Ok(()) })
+                    .await
+            })
+        };
+        tokio::task::yield_now().await;
+        leader.abort();
+
+        follower.await.unwrap().unwrap();
+        assert_eq!(coordinator.is_loaded("m").unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn loaded_model_is_not_reloaded_and_unload_is_tracked() {
+        let coordinator = LoadCoordinator::default();
+        coordinator
+            .load("m", &LoadOptions::default(), async { Ok(()) })
+            .await
+            .unwrap();
+        // Already loaded: the second load must not run.
+        coordinator
+            .load("m", &LoadOptions::default(), async {
+                panic!("should not reload")
+            })
+            .await
+            .unwrap();
+
+        coordinator
+            .unload("m", async { Ok(String::new()) })
+            .await
+            .unwrap();
+        assert_eq!(coordinator.is_loaded("m").unwrap(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn cancelled_operations_forget_the_state() {
+        let coordinator = LoadCoordinator::default();
+        coordinator
+            .load("m", &LoadOptions::default(), async { Ok(()) })
+            .await
+            .unwrap();
+
+        let unload = coordinator.unload("m", std::future::pending());
+        let timeout = std::time::Duration::from_millis(10);
+        assert!(tokio::time::timeout(timeout, unload).await.is_err());
+        assert_eq!(coordinator.is_loaded("m").unwrap(), None);
+
+        let defaults = LoadOptions::default();
+        let load = coordinator.load("m", &defaults, std::future::pending());
+        assert!(tokio::time::timeout(timeout, load).await.is_err());
+        assert_eq!(coordinator.is_loaded("m").unwrap(), None);
+
+        // The next load leads a fresh operation instead of joining the
+        // cancelled one.
+        coordinator
+            .load("m", &LoadOptions::default(), async { Ok(()) })
+            .await
+            .unwrap();
+        assert_eq!(coordinator.is_loaded("m").unwrap(), Some(true));
+    }
+
+    #[test]
+    fn reconcile_updates_settled_states_only() {
+        let coordinator = LoadCoordinator::default();
+        coordinator
+            .set_state("gone", Some(LoadState::Loaded))
+            .unwrap();
+        coordinator
+            .set_state("busy", Some(LoadState::Loading))
+            .unwrap();
+
+        coordinator.reconcile(&["external".to_string()]).unwrap();
+
+        assert_eq!(coordinator.is_loaded("gone").unwrap(), Some(false));
+        assert_eq!(coordinator.is_loaded("busy").unwrap(), Some(false));
+        assert_eq!(coordinator.is_loaded("external").unwrap(), Some(true));
+        assert_eq!(coordinator.is_loaded("unknown").unwrap(), None);
+    }
 }
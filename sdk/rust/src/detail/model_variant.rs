@@ -4,20 +4,29 @@
 //! [`Arc<Model>`](crate::Model) instead.
 
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use serde_json::json;
 
-use super::core_interop::CoreInterop;
-use super::ModelLoadManager;
+use super::cache_lock::CacheLocks;
+use super::core_interop::{get_cache_directory, CoreInterop};
+use super::loaded_model::LoadedModel;
+use super::model_archive;
+use super::model_inspect::{self, ModelInspection};
+use super::preflight::{self, LicenseStore};
+use super::warm_up::{self, WarmUpReport};
+use super::{run_blocking, LoadOptions, ModelLoadManager, ResidencyManager, UsageTracker};
 use crate::catalog::CacheInvalidator;
-use crate::error::Result;
+use crate::error::{FoundryLocalError, Result};
 use crate::openai::AudioClient;
 use crate::openai::ChatClient;
+use crate::openai::ChatSession;
 use crate::openai::EmbeddingClient;
-use crate::types::ModelInfo;
+use crate::openai::ResponsesClient;
+use crate::tokenizer::Tokenizer;
+use crate::types::{ModelInfo, ModelLicense};
 
 /// Represents one specific variant of a model (a particular id within an alias
 /// group).
@@ -28,6 +37,9 @@ pub(crate) struct ModelVariant {
     info: ModelInfo,
     core: Arc<CoreInterop>,
     model_load_manager: Arc<ModelLoadManager>,
+    residency: Arc<ResidencyManager>,
+    license_store: Arc<LicenseStore>,
+    cache_locks: Arc<CacheLocks>,
     cache_invalidator: CacheInvalidator,
 }
 
@@ -45,12 +57,18 @@ impl ModelVariant {
         info: ModelInfo,
         core: Arc<CoreInterop>,
         model_load_manager: Arc<ModelLoadManager>,
+        residency: Arc<ResidencyManager>,
+        license_store: Arc<LicenseStore>,
+        cache_locks: Arc<CacheLocks>,
         cache_invalidator: CacheInvalidator,
     ) -> Self {
         Self {
             info,
             core,
             model_load_manager,
+            residency,
+            license_store,
+            cache_locks,
             cache_invalidator,
         }
     }
@@ -84,8 +102,7 @@ impl ModelVariant {
     }
 
     pub(crate) async fn is_loaded(&self) -> Result<bool> {
-        let loaded = self.model_load_manager.list_loaded().await?;
-        Ok(loaded.iter().any(|id| id == &self.info.id))
+        self.model_load_manager.is_loaded(&self.info.id).await
     }
 
     pub(crate) async fn download<F>(&self, progress: Option<F>) -> Result<()>
@@ -103,6 +120,7 @@ impl ModelVariant {
     where
         F: FnMut(f64) + Send + 'static,
     {
+        let _lock = self.cache_locks.acquire(&self.info.id, "download").await?;
         let params = json!({ "Params": { "Model": self.info.id } });
         if progress.is_none() && cancel_flag.is_none() {
             self.core
@@ -137,6 +155,59 @@ impl ModelVariant {
         Ok(())
     }
 
+    /// Consult `hook` unless the variant's license was accepted before.
+    ///
+    /// Variants without a license in the catalog need no acceptance.
+    pub(crate) fn ensure_license_accepted<F>(&self, hook: F) -> Result<()>
+    where
+        F: FnOnce(&ModelLicense) -> bool,
+    {
+        let Some(license) = self.info.license.as_deref() else {
+            return Ok(());
+        };
+        if self.license_store.is_accepted(&self.info.name, license)? {
+            return Ok(());
+        }
+
+        let terms = ModelLicense {
+            model_id: self.info.id.clone(),
+            model_name: self.info.name.clone(),
+            license: license.to_owned(),
+            license_description: self.info.license_description.clone(),
+        };
+        if !hook(&terms) {
+            return Err(FoundryLocalError::LicenseNotAccepted {
+                model_id: self.info.id.clone(),
+                license: license.to_owned(),
+            });
+        }
+        self.license_store.record(&self.info.name, license)
+    }
+
+    /// Fail if the model cache volume cannot hold `file_size_mb`.
+    ///
+    /// Skipped when the size is unknown or the variant is already cached.
+    pub(crate) async fn check_disk_space(&self) -> Result<()> {
+        let Some(size_mb) = self.info.file_size_mb else {
+            return Ok(());
+        };
+        if self.is_cached().await? {
+            return Ok(());
+        }
+
+        let cache_dir = get_cache_directory(&self.core).await?;
+        let required_bytes = size_mb.saturating_mul(1024 * 1024);
+        let available_bytes = preflight::available_space(&cache_dir)?;
+        if available_bytes < required_bytes {
+            return Err(FoundryLocalError::InsufficientDiskSpace {
+                path: cache_dir,
+                required_bytes,
+                available_bytes,
+            });
+        }
+        Ok(())
+    }
+
     pub(crate) async fn path(&self) -> Result<PathBuf> {
         let params = json!({ "Params": { "Model": self.info.id } });
         let path_str = self
@@ -146,15 +217,103 @@ impl ModelVariant {
         Ok(PathBuf::from(path_str))
     }
 
+    /// Directory holding the variant's files.  The core may report the
+    /// path of the main model file instead of its directory.
+    async fn model_dir(&self) -> Result<PathBuf> {
+        let model_path = self.path().await?;
+        Ok(if model_path.is_file() {
+            model_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or(model_path)
+        } else {
+            model_path
+        })
+    }
+
+    async fn ensure_cached(&self, action: &str) -> Result<()> {
+        if !self.is_cached().await? {
+            return Err(FoundryLocalError::ModelOperation {
+                reason: format!(
+                    "model '{}' must be downloaded before it can be {action}",
+                    self.info.id
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn inspect(&self) -> Result<ModelInspection> {
+        self.ensure_cached("inspected").await?;
+        let model_dir = self.model_dir().await?;
+        let model_id = self.info.id.clone();
+
+        run_blocking(move || model_inspect::inspect_dir(&model_id, &model_dir)).await
+    }
+
+    pub(crate) async fn tokenizer(&self) -> Result<Tokenizer> {
+        self.ensure_cached("tokenized").await?;
+        let path = self.model_dir().await?.join("tokenizer.json");
+        if !path.is_file() {
+            return Err(FoundryLocalError::ModelOperation {
+                reason: format!("model '{}' has no tokenizer.json", self.info.id),
+            });
+        }
+        let template = self.info.prompt_template.clone();
+
+        let tokenizer = run_blocking(move || Tokenizer::from_file(&path)).await?;
+        Ok(match template {
+            Some(template) => tokenizer.prompt_template(template),
+            None => tokenizer,
+        })
+    }
+
+    pub(crate) async fn export(&self, dest: &Path) -> Result<()> {
+        self.ensure_cached("exported").await?;
+        let _lock = self.cache_locks.acquire(&self.info.id, "export").await?;
+        let model_dir = self.model_dir().await?;
+        let cache_dir = get_cache_directory(&self.core).await?;
+        let info = self.info.clone();
+        let dest = dest.to_path_buf();
+
+        run_blocking(move || model_archive::write_archive(&info, &cache_dir, &model_dir, &dest))
+            .await
+    }
+
     pub(crate) async fn load(&self) -> Result<()> {
-        self.model_load_manager.load(&self.info.id).await
+        self.residency
+            .load(&self.info, &LoadOptions::default())
+            .await
+    }
+
+    pub(crate) async fn load_with_options(&self, options: &LoadOptions) -> Result<()> {
+        options.validate(&self.info)?;
+        self.residency.load(&self.info, options).await
+    }
+
+    pub(crate) async fn acquire(&self) -> Result<LoadedModel> {
+        let lease = self.residency.acquire(&self.info).await?;
+        Ok(LoadedModel::new(self.clone(), lease))
+    }
+
+    pub(crate) async fn warm_up(&self) -> Result<WarmUpReport> {
+        if !self.is_loaded().await? {
+            return Err(FoundryLocalError::ModelOperation {
+                reason: format!(
+                    "model '{}' must be loaded before it can be warmed up",
+                    self.info.id
+                ),
+            });
+        }
+        warm_up::warm_up(self).await
     }
 
     pub(crate) async fn unload(&self) -> Result<String> {
-        self.model_load_manager.unload(&self.info.id).await
+        self.residency.unload(&self.info.id).await
     }
 
     pub(crate) async fn remove_from_cache(&self) -> Result<String> {
+        let _lock = self.cache_locks.acquire(&self.info.id, "remove").await?;
         let params = json!({ "Params": { "Model": self.info.id } });
         let result = self
             .core
@@ -166,13 +325,41 @@ impl ModelVariant {
 
     pub(crate) fn create_chat_client(&self) -> ChatClient {
         ChatClient::new(&self.info.id, Arc::clone(&self.core))
+            .with_usage_tracker(self.usage_tracker())
+            .with_input_modalities(self.info.input_modalities.clone())
+            .with_capabilities(self.info.capabilities.clone())
+    }
+
+    pub(crate) fn create_chat_session(&self) -> ChatSession {
+        chat_session_for(self.create_chat_client(), &self.info)
     }
 
     pub(crate) fn create_audio_client(&self) -> AudioClient {
         AudioClient::new(&self.info.id, Arc::clone(&self.core))
+            .with_usage_tracker(self.usage_tracker())
     }
 
     pub(crate) fn create_embedding_client(&self) -> EmbeddingClient {
         EmbeddingClient::new(&self.info.id, Arc::clone(&self.core))
+            .with_usage_tracker(self.usage_tracker())
+    }
+
+    pub(crate) fn create_responses_client(&self, base_url: &str) -> Result<ResponsesClient> {
+        Ok(ResponsesClient::new(base_url, Some(&self.info.id))?
+            .with_http_client(self.model_load_manager.http_client())
+            .with_usage_tracker(self.usage_tracker()))
+    }
+
+    fn usage_tracker(&self) -> UsageTracker {
+        UsageTracker::new(&self.info.id, &self.residency)
+    }
+}
+
+/// A session on `client` limited to the variant's context length.
+pub(crate) fn chat_session_for(client: ChatClient, info: &ModelInfo) -> ChatSession {
+    let session = ChatSession::new(client);
+    match info.context_length {
+        Some(tokens) => session.context_length(tokens),
+        None => session,
     }
 }
@@ -0,0 +1,151 @@
+//! Pre-flight checks run before a model download.
+//!
+//! * [`available_space`] reports the free space of the volume that holds the
+//!   model cache.
+//! * [`LicenseStore`] persists the licenses a user has accepted so the
+//!   license acceptance hook on [`DownloadBuilder`](crate::DownloadBuilder)
+//!   is consulted only once per model and license.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{FoundryLocalError, Result};
+
+/// File name of the accepted-license record inside the app data directory.
+const LICENSE_FILE_NAME: &str = "accepted_licenses.json";
+
+/// Bytes available to the current user on the volume containing `path`.
+///
+/// `path` does not need to exist yet — the nearest existing ancestor is
+/// queried instead, so a cache directory that will be created by the first
+/// download can still be checked.
+pub(crate) fn available_space(path: &Path) -> Result<u64> {
+    let existing =
+        path.ancestors()
+            .find(|p| p.exists())
+            .ok_or_else(|| FoundryLocalError::Validation {
+                reason: format!("no existing ancestor for path '{}'", path.display()),
+            })?;
+    Ok(fs4::available_space(existing)?)
+}
+
+/// A single accepted license as stored on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AcceptedLicense {
+    model_name: String,
+    license: String,
+    accepted_at_unix: u64,
+}
+
+/// Persistent record of accepted model licenses.
+///
+/// Entries are keyed by model name (not id) so that a new version of a model
+/// published under the same license does not prompt the user again.
+#[derive(Debug)]
+pub(crate) struct LicenseStore {
+    path: PathBuf,
+    /// Serialises read-modify-write cycles within this process.
+    lock: Mutex<()>,
+}
+
+impl LicenseStore {
+    pub(crate) fn new(app_data_dir: &Path) -> Self {
+        Self {
+            path: app_data_dir.join(LICENSE_FILE_NAME),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Whether `license` has already been accepted for `model_name`.
+    pub(crate) fn is_accepted(&self, model_name: &str, license: &str) -> Result<bool> {
+        let _guard = self.lock_store()?;
+        Ok(self
+            .read()?
+            .iter()
+            .any(|e| e.model_name == model_name && e.license == license))
+    }
+
+    /// Record that `license` has been accepted for `model_name`.
+    pub(crate) fn record(&self, model_name: &str, license: &str) -> Result<()> {
+        let _guard = self.lock_store()?;
+        let mut entries = self.read()?;
+        if entries
+            .iter()
+            .any(|e| e.model_name == model_name && e.license == license)
+        {
+            return Ok(());
+        }
+
+        let accepted_at_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        entries.push(AcceptedLicense {
+            model_name: model_name.to_owned(),
+            license: license.to_owned(),
+            accepted_at_unix,
+        });
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&entries)?)?;
+        Ok(())
+    }
+
+    fn read(&self) -> Result<Vec<AcceptedLicense>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) if bytes.is_empty() => Ok(Vec::new()),
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn lock_store(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.lock.lock().map_err(|_| FoundryLocalError::Internal {
+            reason: "license store mutex poisoned".into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "foundry-local-preflight-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn license_store_records_and_reports_acceptance() {
+        let dir = temp_dir("record");
+        let store = LicenseStore::new(&dir);
+
+        assert!(!store.is_accepted("phi-4", "MIT").unwrap());
+        store.record("phi-4", "MIT").unwrap();
+        assert!(store.is_accepted("phi-4", "MIT").unwrap());
+        assert!(!store.is_accepted("phi-4", "Apache-2.0").unwrap());
+        assert!(!store.is_accepted("qwen", "MIT").unwrap());
+
+        // A fresh store over the same directory sees the persisted record.
+        let reopened = LicenseStore::new(&dir);
+        assert!(reopened.is_accepted("phi-4", "MIT").unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn available_space_walks_up_to_existing_ancestor() {
+        let missing = temp_dir("space").join("cache").join("models");
+        assert!(available_space(&missing).unwrap() > 0);
+    }
+}
@@ -0,0 +1,578 @@
+//! Keeps the set of loaded models within a memory budget.
+//!
+//! [`ResidencyManager`] sits on top of [`ModelLoadManager`] and tracks every
+//! model loaded through the SDK together with its estimated memory footprint
+//! and the time it was last used.  Two policies are applied:
+//!
+//! * **Memory budget** – before a load, least-recently-used models are
+//!   unloaded until the new model fits.
+//! * **Idle timeouts** – a background task unloads models that have not been
+//!   used for longer than their idle timeout.
+//!
+//! Models held by a [`LoadedModel`](crate::LoadedModel) lease or pinned are
+//! exempt from both policies.
+//!
+//! Footprints default to the catalog's `file_size_mb` and can be replaced by
+//! measured values via [`ResidencyManager::set_measured_usage_mb`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use super::{LoadOptions, ModelLoadManager};
+use crate::error::{FoundryLocalError, Result};
+use crate::types::ModelInfo;
+
+/// Bounds for the idle sweeper's sleep between checks.
+const MIN_SWEEP_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Book-keeping for one resident model.
+#[derive(Debug, Clone)]
+struct ResidentModel {
+    size_mb: u64,
+    last_used: Instant,
+}
+
+/// All mutable residency data behind a single lock.
+#[derive(Debug, Default)]
+struct ResidencyState {
+    budget_mb: Option<u64>,
+    default_idle_timeout: Option<Duration>,
+    idle_timeouts: HashMap<String, Duration>,
+    measured_mb: HashMap<String, u64>,
+    resident: HashMap<String, ResidentModel>,
+    /// Live leases per model id; the entry is stale once the lease drops.
+    leases: HashMap<String, Weak<Lease>>,
+    pinned: HashSet<String>,
+    /// The idle sweeper task; it ends when the manager is dropped or its
+    /// runtime shuts down.
+    sweeper: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl ResidencyState {
+    fn idle_timeout(&self, model_id: &str) -> Option<Duration> {
+        self.idle_timeouts
+            .get(model_id)
+            .copied()
+            .or(self.default_idle_timeout)
+    }
+
+    fn live_lease(&self, model_id: &str) -> Option<Arc<Lease>> {
+        self.leases.get(model_id).and_then(Weak::upgrade)
+    }
+
+    /// Whether a model must stay loaded: it is leased or pinned.
+    fn is_held(&self, model_id: &str) -> bool {
+        self.pinned.contains(model_id)
+            || self
+                .leases
+                .get(model_id)
+                .is_some_and(|l| l.strong_count() > 0)
+    }
+
+    fn used_mb(&self) -> u64 {
+        self.resident.values().map(|m| m.size_mb).sum()
+    }
+
+    /// Least-recently-used models to unload so that `required_mb` more fits
+    /// in the budget.  `loading` and held models are never selected.
+    ///
+    /// Returns `None` when the budget cannot be met even after evicting every
+    /// candidate.
+    fn eviction_candidates(&self, loading: &str, required_mb: u64) -> Option<Vec<String>> {
+        let Some(budget) = self.budget_mb else {
+            return Some(Vec::new());
+        };
+
+        let mut used: u64 = self
+            .resident
+            .iter()
+            .filter(|(id, _)| id.as_str() != loading)
+            .map(|(_, m)| m.size_mb)
+            .sum();
+        let mut by_age: Vec<(&String, &ResidentModel)> = self
+            .resident
+            .iter()
+            .filter(|(id, _)| id.as_str() != loading && !self.is_held(id))
+            .collect();
+        by_age.sort_by_key(|(_, m)| m.last_used);
+
+        let mut evict = Vec::new();
+        for (id, m) in by_age {
+            if used + required_mb <= budget {
+                break;
+            }
+            used -= m.size_mb;
+            evict.push(id.clone());
+        }
+        (used + required_mb <= budget).then_some(evict)
+    }
+
+    /// Models whose idle timeout elapsed at `now`.
+    fn expired(&self, now: Instant) -> Vec<String> {
+        self.resident
+            .iter()
+            .filter(|(id, m)| {
+                !self.is_held(id)
+                    && self
+                        .idle_timeout(id)
+                        .is_some_and(|t| now.saturating_duration_since(m.last_used) >= t)
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Time until the next idle timeout may elapse.
+    fn next_sweep_in(&self, now: Instant) -> Option<Duration> {
+        self.resident
+            .iter()
+            .filter(|(id, _)| !self.is_held(id))
+            .filter_map(|(id, m)| {
+                let deadline = m.last_used + self.idle_timeout(id)?;
+                Some(deadline.saturating_duration_since(now))
+            })
+            .min()
+    }
+}
+
+/// Unloads idle models and keeps loaded models within a memory budget.
+///
+/// Obtain it with
+/// [`FoundryLocalManager::residency`](crate::FoundryLocalManager::residency).
+/// Loads and unloads made through [`Model`](crate::Model) are tracked
+/// automatically, and every request made through a client created from a
+/// model counts as use of that model.
+#[derive(Debug)]
+pub struct ResidencyManager {
+    model_load_manager: Arc<ModelLoadManager>,
+    state: Mutex<ResidencyState>,
+    /// Serialises make-room-then-load so concurrent loads cannot both pass
+    /// the budget check.
+    admission: tokio::sync::Mutex<()>,
+    this: Weak<ResidencyManager>,
+}
+
+impl ResidencyManager {
+    pub(crate) fn new(model_load_manager: Arc<ModelLoadManager>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            model_load_manager,
+            state: Mutex::new(ResidencyState::default()),
+            admission: tokio::sync::Mutex::new(()),
+            this: this.clone(),
+        })
+    }
+
+    /// Set the global memory budget in megabytes, or `None` for no limit.
+    pub fn set_memory_budget_mb(&self, budget_mb: Option<u64>) -> Result<()> {
+        self.lock_state()?.budget_mb = budget_mb;
+        Ok(())
+    }
+
+    /// The configured memory budget in megabytes.
+    pub fn memory_budget_mb(&self) -> Result<Option<u64>> {
+        Ok(self.lock_state()?.budget_mb)
+    }
+
+    /// Estimated memory used by resident models, in megabytes.
+    pub fn resident_usage_mb(&self) -> Result<u64> {
+        Ok(self.lock_state()?.used_mb())
+    }
+
+    /// Ids of the models currently tracked as resident.
+    pub fn resident_models(&self) -> Result<Vec<String>> {
+        Ok(self.lock_state()?.resident.keys().cloned().collect())
+    }
+
+    /// Idle timeout applied to models without a per-model timeout.
+    ///
+    /// `None` (the default) keeps models loaded until they are unloaded
+    /// explicitly or evicted to respect the memory budget.
+    pub fn set_default_idle_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.lock_state()?.default_idle_timeout = timeout;
+        self.ensure_sweeper()
+    }
+
+    /// Idle timeout for a single model, overriding the default.
+    ///
+    /// Pass `None` to fall back to the default timeout.
+    pub fn set_idle_timeout(&self, model_id: &str, timeout: Option<Duration>) -> Result<()> {
+        {
+            let mut s = self.lock_state()?;
+            match timeout {
+                Some(t) => s.idle_timeouts.insert(model_id.to_owned(), t),
+                None => s.idle_timeouts.remove(model_id),
+            };
+        }
+        self.ensure_sweeper()
+    }
+
+    /// Replace the `file_size_mb` estimate of a model with a measured value.
+    pub fn set_measured_usage_mb(&self, model_id: &str, usage_mb: u64) -> Result<()> {
+        let mut s = self.lock_state()?;
+        s.measured_mb.insert(model_id.to_owned(), usage_mb);
+        if let Some(m) = s.resident.get_mut(model_id) {
+            m.size_mb = usage_mb;
+        }
+        Ok(())
+    }
+
+    /// Load a model, first unloading least-recently-used models if the
+    /// memory budget would otherwise be exceeded.
+    pub(crate) async fn load(&self, info: &ModelInfo, options: &LoadOptions) -> Result<()> {
+        let _admission = self.admission.lock().await;
+        self.load_admitted(info, options).await
+    }
+
+    /// Load `info` and return a lease that keeps it loaded, joining an
+    /// existing lease if the model is already held.
+    pub(crate) async fn acquire(&self, info: &ModelInfo) -> Result<Arc<Lease>> {
+        let _admission = self.admission.lock().await;
+        let existing = self.lock_state()?.live_lease(&info.id);
+        if let Some(lease) = existing {
+            self.touch(&info.id);
+            return Ok(lease);
+        }
+
+        self.load_admitted(info, &LoadOptions::default()).await?;
+
+        let residency = self
+            .this
+            .upgrade()
+            .ok_or_else(|| FoundryLocalError::Internal {
+                reason: "residency manager dropped".into(),
+            })?;
+        let lease = Arc::new(Lease {
+            model_id: info.id.clone(),
+            residency,
+        });
+        self.lock_state()?
+            .leases
+            .insert(info.id.clone(), Arc::downgrade(&lease));
+        Ok(lease)
+    }
+
+    /// Keep a model loaded after its last lease is dropped, and exempt it
+    /// from idle unloading and eviction.
+    pub(crate) fn set_pinned(&self, model_id: &str, pinned: bool) -> Result<()> {
+        let mut s = self.lock_state()?;
+        if pinned {
+            s.pinned.insert(model_id.to_owned());
+        } else {
+            s.pinned.remove(model_id);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_pinned(&self, model_id: &str) -> Result<bool> {
+        Ok(self.lock_state()?.pinned.contains(model_id))
+    }
+
+    /// Must be called with the admission gate held.
+    async fn load_admitted(&self, info: &ModelInfo, options: &LoadOptions) -> Result<()> {
+        let (required_mb, evict) = {
+            let s = self.lock_state()?;
+            let required_mb = s
+                .measured_mb
+                .get(&info.id)
+                .copied()
+                .or(info.file_size_mb)
+                .unwrap_or(0);
+            if let Some(budget) = s.budget_mb {
+                if required_mb > budget {
+                    return Err(FoundryLocalError::ModelOperation {
+                        reason: format!(
+                            "model '{}' needs {required_mb} MB, which exceeds the {budget} MB memory budget",
+                            info.id
+                        ),
+                    });
+                }
+            }
+            let evict = s.eviction_candidates(&info.id, required_mb).ok_or_else(|| {
+                FoundryLocalError::ModelOperation {
+                    reason: format!(
+                        "cannot free {required_mb} MB for model '{}': the remaining models are leased or pinned",
+                        info.id
+                    ),
+                }
+            })?;
+            (required_mb, evict)
+        };
+
+        for id in evict {
+            self.unload_untracked(&id).await?;
+        }
+
+        self.model_load_manager.load(&info.id, options).await?;
+
+        self.lock_state()?.resident.insert(
+            info.id.clone(),
+            ResidentModel {
+                size_mb: required_mb,
+                last_used: Instant::now(),
+            },
+        );
+        self.ensure_sweeper()
+    }
+
+    /// Unload a model and stop tracking it.
+    ///
+    /// Fails while a [`LoadedModel`](crate::LoadedModel) lease holds the
+    /// model, so one user cannot unload it from under another.
+    pub(crate) async fn unload(&self, model_id: &str) -> Result<String> {
+        let existing = self.lock_state()?.live_lease(model_id);
+        if let Some(lease) = existing {
+            // `lease` is a temporary upgrade; it does not count as a holder.
+            let holders = Arc::strong_count(&lease) - 1;
+            return Err(FoundryLocalError::ModelOperation {
+                reason: format!("model '{model_id}' is in use by {holders} active lease(s)"),
+            });
+        }
+        self.unload_untracked(model_id).await
+    }
+
+    async fn unload_untracked(&self, model_id: &str) -> Result<String> {
+        let result = self.model_load_manager.unload(model_id).await?;
+        self.lock_state()?.resident.remove(model_id);
+        Ok(result)
+    }
+
+    /// Record use of a model, postponing its idle timeout.
+    pub(crate) fn touch(&self, model_id: &str) {
+        if let Ok(mut s) = self.state.lock() {
+            if let Some(m) = s.resident.get_mut(model_id) {
+                m.last_used = Instant::now();
+            }
+        }
+    }
+
+    /// Start the idle sweeper if any idle timeout is configured and it is not
+    /// running, including when its previous task has ended.  Requires a
+    /// Tokio runtime; without one the sweeper starts on the next load.
+    fn ensure_sweeper(&self) -> Result<()> {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return Ok(());
+        };
+        let mut s = self.lock_state()?;
+        let has_timeouts = s.default_idle_timeout.is_some() || !s.idle_timeouts.is_empty();
+        let running = s.sweeper.as_ref().is_some_and(|task| !task.is_finished());
+        if running || !has_timeouts {
+            return Ok(());
+        }
+
+        let this = self.this.clone();
+        s.sweeper = Some(handle.spawn(async move {
+            loop {
+                let wait = match this.upgrade() {
+                    Some(manager) => manager.sweep_idle().await,
+                    None => return,
+                };
+                tokio::time::sleep(wait).await;
+            }
+        }));
+        Ok(())
+    }
+
+    /// Unload expired models and return how long to wait before the next
+    /// check.
+    async fn sweep_idle(&self) -> Duration {
+        let expired = match self.state.lock() {
+            Ok(s) => s.expired(Instant::now()),
+            Err(_) => return MAX_SWEEP_INTERVAL,
+        };
+
+        for id in expired {
+            // Hold the admission gate so an eviction cannot race a load of
+            // the same model.
+            let _admission = self.admission.lock().await;
+            let still_expired = self
+                .state
+                .lock()
+                .map(|s| s.expired(Instant::now()).contains(&id))
+                .unwrap_or(false);
+            if still_expired {
+                // A failed unload is retried on the next sweep.
+                let _ = self.unload_untracked(&id).await;
+            }
+        }
+
+        self.state
+            .lock()
+            .ok()
+            .and_then(|s| s.next_sweep_in(Instant::now()))
+            .unwrap_or(MAX_SWEEP_INTERVAL)
+            .clamp(MIN_SWEEP_INTERVAL, MAX_SWEEP_INTERVAL)
+    }
+
+    /// Called when the last lease on `model_id` is dropped.
+    ///
+    /// Unloading is asynchronous, so it runs on the current Tokio runtime.
+    /// Outside a runtime the model stays loaded and is left to the idle
+    /// timeout and memory budget.
+    fn release(&self, model_id: &str) {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let this = self.this.clone();
+        let model_id = model_id.to_owned();
+        handle.spawn(async move {
+            let Some(manager) = this.upgrade() else {
+                return;
+            };
+            // A new lease may have been taken since the drop; the admission
+            // gate orders this check against `acquire`.
+            let _admission = manager.admission.lock().await;
+            let held = manager
+                .state
+                .lock()
+                .map(|s| s.is_held(&model_id))
+                .unwrap_or(true);
+            if !held {
+                let _ = manager.unload_untracked(&model_id).await;
+            }
+        });
+    }
+
+    fn lock_state(&self) -> Result<std::sync::MutexGuard<'_, ResidencyState>> {
+        self.state.lock().map_err(|_| FoundryLocalError::Internal {
+            reason: "residency state mutex poisoned".into(),
+        })
+    }
+}
+
+/// Keeps a model loaded while alive.  Shared by all clones of a
+/// [`LoadedModel`](crate::LoadedModel) and the clients created from them;
+/// dropping the last reference unloads the model unless it is pinned.
+#[derive(Debug)]
+pub(crate) struct Lease {
+    model_id: String,
+    residency: Arc<ResidencyManager>,
+}
+
+impl Lease {
+    pub(crate) fn residency(&self) -> &Arc<ResidencyManager> {
+        &self.residency
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.residency.release(&self.model_id);
+    }
+}
+
+/// Handle given to clients so that their requests count as use of a model.
+///
+/// When created from a [`LoadedModel`](crate::LoadedModel) it also holds the
+/// lease, keeping the model loaded for as long as the client lives.
+#[derive(Debug, Clone)]
+pub(crate) struct UsageTracker {
+    model_id: String,
+    residency: Weak<ResidencyManager>,
+    _lease: Option<Arc<Lease>>,
+}
+
+impl UsageTracker {
+    pub(crate) fn new(model_id: &str, residency: &Arc<ResidencyManager>) -> Self {
+        Self {
+            model_id: model_id.to_owned(),
+            residency: Arc::downgrade(residency),
+            _lease: None,
+        }
+    }
+
+    pub(crate) fn with_lease(mut self, lease: Arc<Lease>) -> Self {
+        self._lease = Some(lease);
+        self
+    }
+
+    pub(crate) fn touch(&self) {
+        if let Some(residency) = self.residency.upgrade() {
+            residency.touch(&self.model_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with(models: &[(&str, u64, u64)], budget_mb: Option<u64>) -> ResidencyState {
+        // `(id, size_mb, seconds_since_use)`
+        let now = Instant::now();
+        let mut state = ResidencyState {
+            budget_mb,
+            ..ResidencyState::default()
+        };
+        for (id, size_mb, age) in models {
+            state.resident.insert(
+                (*id).to_owned(),
+                ResidentModel {
+                    size_mb: *size_mb,
+                    last_used: now - Duration::from_secs(*age),
+                },
+            );
+        }
+        state
+    }
+
+    #[test]
+    fn no_budget_evicts_nothing() {
+        let state = state_with(&[("a", 4000, 10)], None);
+        assert_eq!(state.eviction_candidates("b", 10_000), Some(Vec::new()));
+    }
+
+    #[test]
+    fn evicts_least_recently_used_until_model_fits() {
+        let state = state_with(
+            &[("old", 2000, 30), ("mid", 2000, 20), ("new", 2000, 10)],
+            Some(7000),
+        );
+        assert_eq!(
+            state.eviction_candidates("x", 3000),
+            Some(vec!["old".to_string()])
+        );
+        assert_eq!(
+            state.eviction_candidates("x", 5000),
+            Some(vec!["old".to_string(), "mid".to_string()])
+        );
+        assert_eq!(state.eviction_candidates("x", 1000), Some(Vec::new()));
+        assert_eq!(state.eviction_candidates("x", 8000), None);
+    }
+
+    #[test]
+    fn reloading_a_resident_model_does_not_evict_it() {
+        let state = state_with(&[("a", 3000, 30), ("b", 3000, 10)], Some(6000));
+        assert_eq!(state.eviction_candidates("a", 3000), Some(Vec::new()));
+    }
+
+    #[test]
+    fn pinned_models_are_neither_evicted_nor_expired() {
+        let mut state = state_with(&[("old", 3000, 30), ("new", 3000, 10)], Some(6000));
+        state.pinned.insert("old".into());
+        state.default_idle_timeout = Some(Duration::from_secs(5));
+
+        assert_eq!(
+            state.eviction_candidates("x", 3000),
+            Some(vec!["new".to_string()])
+        );
+        assert_eq!(state.eviction_candidates("x", 4000), None);
+        assert_eq!(state.expired(Instant::now()), vec!["new".to_string()]);
+    }
+
+    #[test]
+    fn idle_timeouts_use_per_model_override() {
+        let mut state = state_with(&[("a", 1, 30), ("b", 1, 30), ("c", 1, 5)], None);
+        state.default_idle_timeout = Some(Duration::from_secs(20));
+        state
+            .idle_timeouts
+            .insert("b".into(), Duration::from_secs(60));
+
+        let mut expired = state.expired(Instant::now());
+        expired.sort();
+        assert_eq!(expired, vec!["a".to_string()]);
+
+        let next = state.next_sweep_in(Instant::now()).unwrap();
+        assert!(next <= Duration::from_secs(15));
+    }
+}
@@ -0,0 +1,189 @@
+//! Warm-up requests that pay for graph initialisation before real traffic.
+//!
+//! The first request after a load is much slower than later ones.  A warm-up
+//! runs a minimal request matched to the model's task and measures how long
+//! the first output took, which doubles as a readiness probe.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use async_openai::types::chat::{ChatCompletionRequestMessage, ChatCompletionRequestUserMessage};
+use tokio_stream::StreamExt;
+
+use super::model_variant::ModelVariant;
+use crate::error::{FoundryLocalError, Result};
+
+/// Sample rate of the silent clip used to warm up transcription models.
+const WARM_UP_SAMPLE_RATE: u32 = 16_000;
+/// Length of the silent clip in milliseconds.
+const WARM_UP_CLIP_MS: u32 = 500;
+
+/// The kind of request a warm-up runs, derived from [`ModelInfo::task`].
+///
+/// [`ModelInfo::task`]: crate::ModelInfo::task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmUpKind {
+    /// A one-token chat completion.
+    Chat,
+    /// A single short embedding.
+    Embedding,
+    /// A transcription of half a second of silence.
+    Transcription,
+}
+
+impl WarmUpKind {
+    fn for_task(task: Option<&str>) -> Result<Self> {
+        match task {
+            None | Some("chat-completion") | Some("vision-language-chat") => Ok(Self::Chat),
+            Some("embeddings") => Ok(Self::Embedding),
+            Some("automatic-speech-recognition") => Ok(Self::Transcription),
+            Some(other) => Err(FoundryLocalError::Validation {
+                reason: format!("cannot warm up a model with task '{other}'"),
+            }),
+        }
+    }
+}
+
+/// Timings measured by [`Model::warm_up`](crate::Model::warm_up).
+#[derive(Debug, Clone)]
+pub struct WarmUpReport {
+    pub model_id: String,
+    pub kind: WarmUpKind,
+    /// Time until the first output arrived: the first streamed token or
+    /// segment, or the whole response for embeddings.
+    pub first_token_latency: Duration,
+    /// Time until the request completed.
+    pub total_latency: Duration,
+}
+
+/// Run a minimal request against a loaded variant and time it.
+pub(crate) async fn warm_up(variant: &ModelVariant) -> Result<WarmUpReport> {
+    let kind = WarmUpKind::for_task(variant.info().task.as_deref())?;
+    let started = Instant::now();
+    let first_token_latency = match kind {
+        WarmUpKind::Chat => warm_up_chat(variant, started).await?,
+        WarmUpKind::Embedding => {
+            variant
+                .create_embedding_client()
+                .generate_embedding("warm-up")
+                .await?;
+            started.elapsed()
+        }
+        WarmUpKind::Transcription => warm_up_transcription(variant, started).await?,
+    };
+
+    Ok(WarmUpReport {
+        model_id: variant.id().to_owned(),
+        kind,
+        first_token_latency,
+        total_latency: started.elapsed(),
+    })
+}
+
+async fn warm_up_chat(variant: &ModelVariant, started: Instant) -> Result<Duration> {
+    let messages: [ChatCompletionRequestMessage; 1] =
+        [ChatCompletionRequestUserMessage::from("Hi").into()];
+    let mut stream = variant
+        .create_chat_client()
+        .max_tokens(1)
+        .temperature(0.0)
+        .complete_streaming_chat(&messages, None)
+        .await?;
+
+    let mut first = None;
+    while let Some(chunk) = stream.next().await {
+        chunk?;
+        first.get_or_insert_with(|| started.elapsed());
+    }
+    Ok(first.unwrap_or_else(|| started.elapsed()))
+}
+
+async fn warm_up_transcription(variant: &ModelVariant, started: Instant) -> Result<Duration> {
+    let clip = silent_clip_path();
+    std::fs::write(&clip, silent_wav(WARM_UP_SAMPLE_RATE, WARM_UP_CLIP_MS))?;
+
+    let result = transcribe_first(variant, &clip, started).await;
+    let _ = std::fs::remove_file(&clip);
+    result
+}
+
+async fn transcribe_first(
+    variant: &ModelVariant,
+    clip: &Path,
+    started: Instant,
+) -> Result<Duration> {
+    let mut stream = variant
+        .create_audio_client()
+        .transcribe_streaming(clip)
+        .await?;
+
+    let mut first = None;
+    while let Some(chunk) = stream.next().await {
+        chunk?;
+        first.get_or_insert_with(|| started.elapsed());
+    }
+    Ok(first.unwrap_or_else(|| started.elapsed()))
+}
+
+/// Unique per call so concurrent warm-ups do not share a clip.
+fn silent_clip_path() -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    std::env::temp_dir().join(format!(
+        "foundry-local-warm-up-{}-{}.wav",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// A mono 16-bit PCM WAV file of `ms` milliseconds of silence.
+fn silent_wav(sample_rate: u32, ms: u32) -> Vec<u8> {
+    let data_len = sample_rate * ms / 1000 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.resize(44 + data_len as usize, 0);
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_selects_warm_up_kind() {
+        assert_eq!(WarmUpKind::for_task(None).unwrap(), WarmUpKind::Chat);
+        assert_eq!(
+            WarmUpKind::for_task(Some("embeddings")).unwrap(),
+            WarmUpKind::Embedding
+        );
+        assert_eq!(
+            WarmUpKind::for_task(Some("automatic-speech-recognition")).unwrap(),
+            WarmUpKind::Transcription
+        );
+        assert!(WarmUpKind::for_task(Some("text-to-image")).is_err());
+    }
+
+    #[test]
+    fn silent_wav_has_consistent_header() {
+        let wav = silent_wav(16_000, 500);
+        assert_eq!(wav.len(), 44 + 16_000);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(wav[4..8].try_into().unwrap()),
+            36 + 16_000
+        );
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 16_000);
+        assert!(wav[44..].iter().all(|&b| b == 0));
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 /// Errors that can occur when using the Foundry Local SDK.
@@ -27,6 +29,31 @@ pub enum FoundryLocalError {
     /// An I/O error occurred.
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
+    /// The model cache volume does not have enough free space for a download.
+    #[error(
+        "insufficient disk space in {}: {required_bytes} bytes required, {available_bytes} bytes available",
+        path.display()
+    )]
+    InsufficientDiskSpace {
+        path: PathBuf,
+        required_bytes: u64,
+        available_bytes: u64,
+    },
+    /// The license acceptance hook declined the license of a model.
+    #[error("license '{license}' for model '{model_id}' was not accepted")]
+    LicenseNotAccepted { model_id: String, license: String },
+    /// Another process holds the model cache lock for a variant.
+    #[error("model '{model_id}' is locked in the model cache by {holder}")]
+    CacheLocked { model_id: String, holder: String },
+    /// No structured response matched the requested type within the
+    /// allowed attempts.  `raw` holds the model's last reply.
+    #[error("response does not match {type_name} after {attempts} attempt(s): {reason}")]
+    StructuredOutput {
+        type_name: String,
+        reason: String,
+        raw: String,
+        attempts: u32,
+    },
     /// An internal SDK error (e.g. poisoned lock).
     #[error("internal error: {reason}")]
     Internal { reason: String },
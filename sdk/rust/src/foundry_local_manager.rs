@@ -4,16 +4,21 @@
 //! library, provides access to the model [`Catalog`], and can start / stop
 //! the local web service.
 
-use std::sync::atomic::AtomicBool;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 
 use serde_json::json;
 
 use crate::catalog::Catalog;
 use crate::configuration::{Configuration, FoundryLocalConfig, Logger};
+use crate::detail::cache_lock::CacheLocks;
 use crate::detail::core_interop::CoreInterop;
-use crate::detail::ModelLoadManager;
+use crate::detail::model::Model;
+use crate::detail::preflight::LicenseStore;
+use crate::detail::{ModelLoadManager, ResidencyManager};
 use crate::error::{FoundryLocalError, Result};
+use crate::openai::ResponsesClient;
 use crate::types::{EpDownloadResult, EpInfo};
 
 /// Global singleton holder — only stores a successfully initialised manager.
@@ -28,7 +33,10 @@ static INIT_GUARD: Mutex<()> = Mutex::new(());
 pub struct FoundryLocalManager {
     core: Arc<CoreInterop>,
     catalog: Catalog,
+    residency: Arc<ResidencyManager>,
     urls: Mutex<Vec<String>>,
+    /// HTTP client built from [`HttpSettings`](crate::HttpSettings).
+    http_client: reqwest::Client,
     /// Application logger (stub — not yet wired into the native core).
     _logger: Option<Box<dyn Logger>>,
 }
@@ -94,6 +102,185 @@ impl<'a> EpDownloadBuilder<'a> {
     }
 }
 
+/// Phase reported by [`PrepareBuilder`] while making a model ready.
+///
+/// Phases are reported in order; phases with nothing to do (e.g. downloading
+/// an already cached model) are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrepareProgress {
+    /// The model and its best variant are being resolved from the catalog.
+    /// Reported again after execution providers were registered.
+    ResolvingModel,
+    /// An execution provider that a variant of the model runs on is being
+    /// downloaded and registered.
+    RegisteringExecutionProviders { ep_name: String, percent: f64 },
+    /// The selected variant is being downloaded.
+    Downloading { percent: f64 },
+    /// The selected variant is being loaded into memory.
+    Loading,
+    /// The model is loaded and ready to serve requests.
+    Ready,
+}
+
+type PrepareProgressCallback = Arc<Mutex<dyn FnMut(PrepareProgress) + Send + 'static>>;
+
+/// Builder that registers execution providers, resolves, downloads and loads
+/// a model in one call.
+///
+/// Created by [`FoundryLocalManager::prepare`].
+pub struct PrepareBuilder<'a> {
+    manager: &'a FoundryLocalManager,
+    alias: String,
+    register_eps: bool,
+    progress_callback: Option<PrepareProgressCallback>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+}
+
+impl<'a> PrepareBuilder<'a> {
+    fn new(manager: &'a FoundryLocalManager, alias: String) -> Self {
+        Self {
+            manager,
+            alias,
+            register_eps: true,
+            progress_callback: None,
+            cancel_flag: None,
+        }
+    }
+
+    /// Do not download or register the model's execution providers.
+    pub fn skip_execution_providers(mut self) -> Self {
+        self.register_eps = false;
+        self
+    }
+
+    /// Report progress across all phases.
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(PrepareProgress) + Send + 'static,
+    {
+        self.progress_callback = Some(Arc::new(Mutex::new(callback)));
+        self
+    }
+
+    /// Cancel preparation when `cancel_flag` is set to `true`.
+    ///
+    /// Downloads stop at the next progress update; otherwise the flag is
+    /// checked between phases.  A load that has already started runs to
+    /// completion.
+    pub fn cancel(mut self, cancel_flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// Run all phases and return the ready model.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::CommandExecution`] with an
+    /// "Operation cancelled" reason if cancelled, or the error of the phase
+    /// that failed.
+    pub async fn run(self) -> Result<Arc<Model>> {
+        let report = |progress: PrepareProgress| {
+            if let Some(cb) = &self.progress_callback {
+                if let Ok(mut cb) = cb.lock() {
+                    cb(progress);
+                }
+            }
+        };
+
+        self.check_cancelled()?;
+        report(PrepareProgress::ResolvingModel);
+        let mut model = self.manager.catalog.get_model(&self.alias).await?;
+
+        if self.register_eps && self.register_model_eps(&model).await? {
+            // Registering invalidates the catalog; the best variant may now
+            // be one that runs on a newly registered execution provider.
+            self.check_cancelled()?;
+            report(PrepareProgress::ResolvingModel);
+            model = self.manager.catalog.get_model(&self.alias).await?;
+        }
+
+        if !model.is_cached().await? {
+            self.check_cancelled()?;
+            let mut builder = model.download_builder();
+            if let Some(cb) = &self.progress_callback {
+                let cb = Arc::clone(cb);
+                builder = builder.progress(move |percent: f64| {
+                    if let Ok(mut cb) = cb.lock() {
+                        cb(PrepareProgress::Downloading { percent });
+                    }
+                });
+            }
+            if let Some(flag) = &self.cancel_flag {
+                builder = builder.cancel(Arc::clone(flag));
+            }
+            builder.run().await?;
+        }
+
+        if !model.is_loaded().await? {
+            self.check_cancelled()?;
+            report(PrepareProgress::Loading);
+            model.load().await?;
+        }
+
+        report(PrepareProgress::Ready);
+        Ok(model)
+    }
+
+    /// Download and register the unregistered execution providers that
+    /// variants of `model` run on.  Returns whether any were registered.
+    async fn register_model_eps(&self, model: &Model) -> Result<bool> {
+        let model_eps: HashSet<String> = model
+            .variants()
+            .iter()
+            .filter_map(|v| v.info().runtime.as_ref())
+            .map(|r| r.execution_provider.clone())
+            .collect();
+        let unregistered: Vec<String> = self
+            .manager
+            .discover_eps()?
+            .into_iter()
+            .filter(|ep| !ep.is_registered && model_eps.contains(&ep.name))
+            .map(|ep| ep.name)
+            .collect();
+        if unregistered.is_empty() {
+            return Ok(false);
+        }
+
+        let mut builder = self
+            .manager
+            .download_and_register_eps_builder()
+            .names(unregistered);
+        if let Some(cb) = &self.progress_callback {
+            let cb = Arc::clone(cb);
+            builder = builder.progress(move |ep_name: &str, percent: f64| {
+                if let Ok(mut cb) = cb.lock() {
+                    cb(PrepareProgress::RegisteringExecutionProviders {
+                        ep_name: ep_name.to_owned(),
+                        percent,
+                    });
+                }
+            });
+        }
+        if let Some(flag) = &self.cancel_flag {
+            builder = builder.cancel(Arc::clone(flag));
+        }
+        builder.run().await?;
+        Ok(true)
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancel_flag {
+            Some(flag) if flag.load(Ordering::Acquire) => {
+                Err(FoundryLocalError::CommandExecution {
+                    reason: "Operation cancelled".into(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl FoundryLocalManager {
     /// Initialise the SDK.
     ///
@@ -126,15 +313,37 @@ impl FoundryLocalManager {
 
         let service_endpoint = internal_config.params.get("WebServiceExternalUrl").cloned();
 
-        let model_load_manager =
-            Arc::new(ModelLoadManager::new(Arc::clone(&core), service_endpoint));
+        let http_client = internal_config.http.build_client()?;
+        let model_load_manager = Arc::new(ModelLoadManager::new(
+            Arc::clone(&core),
+            service_endpoint,
+            http_client.clone(),
+        ));
 
-        let catalog = Catalog::new(Arc::clone(&core), Arc::clone(&model_load_manager))?;
+        let license_store = Arc::new(LicenseStore::new(&internal_config.app_data_dir()));
+
+        let residency = ResidencyManager::new(Arc::clone(&model_load_manager));
+
+        let cache_locks = Arc::new(CacheLocks::new(
+            Arc::clone(&core),
+            internal_config.cache_lock_policy,
+        ));
+
+        let catalog = Catalog::new(
+            Arc::clone(&core),
+            Arc::clone(&model_load_manager),
+            Arc::clone(&residency),
+            license_store,
+            cache_locks,
+            internal_config.cache_location_file(),
+        )?;
 
         let manager = FoundryLocalManager {
             core,
             catalog,
+            residency,
             urls: Mutex::new(Vec::new()),
+            http_client,
             _logger: logger,
         };
 
@@ -153,6 +362,31 @@ impl FoundryLocalManager {
         &self.catalog
     }
 
+    /// Access the residency manager, which unloads idle models and keeps
+    /// loaded models within a memory budget.
+    pub fn residency(&self) -> &ResidencyManager {
+        &self.residency
+    }
+
+    /// Make the model with the given alias ready to use in one call.
+    ///
+    /// Registers missing execution providers that the model's variants run
+    /// on, picks the best variant, downloads it if it is not cached and
+    /// loads it if it is not loaded.
+    /// Configure progress and cancellation on the returned builder:
+    ///
+    /// ```ignore
+    /// let model = manager
+    ///     .prepare("phi-3.5-mini")
+    ///     .progress(|p| println!("{p:?}"))
+    ///     .run()
+    ///     .await?;
+    /// let client = model.create_chat_client();
+    /// ```
+    pub fn prepare(&self, alias: impl Into<String>) -> PrepareBuilder<'_> {
+        PrepareBuilder::new(self, alias.into())
+    }
+
     /// URLs that the local web service is listening on.
     ///
     /// Empty until [`Self::start_web_service`] has been called.
@@ -183,6 +417,21 @@ impl FoundryLocalManager {
         Ok(())
     }
 
+    /// Create a [`ResponsesClient`] for the running web service, with
+    /// `model_id` as the default model of its requests.
+    ///
+    /// Fails with `Validation` if [`Self::start_web_service`] has not been
+    /// called.
+    pub fn create_responses_client(&self, model_id: Option<&str>) -> Result<ResponsesClient> {
+        let urls = self.urls()?;
+        let Some(url) = urls.first() else {
+            return Err(FoundryLocalError::Validation {
+                reason: "web service is not running; call start_web_service() first".into(),
+            });
+        };
+        Ok(ResponsesClient::new(url, model_id)?.with_http_client(self.http_client.clone()))
+    }
+
     /// Stop the local web service.
     pub async fn stop_web_service(&self) -> Result<()> {
         self.core
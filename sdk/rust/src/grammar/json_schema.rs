@@ -0,0 +1,368 @@
+//! Conversion of JSON schemas into grammars for compact JSON.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use super::{Grammar, GrammarExpr};
+use crate::error::{FoundryLocalError, Result};
+
+const STRING: &str = r#""(\\(["\\/bfnrt]|u[0-9a-fA-F]{4})|[^"\\\x00-\x1F])*""#;
+const NUMBER: &str = r"-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?";
+const INTEGER: &str = r"-?(0|[1-9][0-9]*)";
+
+/// Keywords that restrict values in ways a grammar cannot express.
+const UNSUPPORTED: &[&str] = &[
+    "pattern",
+    "patternProperties",
+    "not",
+    "if",
+    "dependentRequired",
+    "dependentSchemas",
+    "uniqueItems",
+    "prefixItems",
+    "contains",
+];
+
+pub(super) fn convert(schema: &Value) -> Result<Grammar> {
+    let mut converter = Converter {
+        root: schema,
+        grammar: Grammar::new().rule("start", GrammarExpr::rule("value")),
+        refs: HashMap::new(),
+        next_id: 0,
+    };
+    let start = converter.expr(schema, "")?;
+    Ok(converter.grammar.rule("start", start))
+}
+
+struct Converter<'a> {
+    root: &'a Value,
+    grammar: Grammar,
+    /// Rule names of the `$ref`s converted so far.
+    refs: HashMap<String, String>,
+    next_id: usize,
+}
+
+impl<'a> Converter<'a> {
+    fn expr(&mut self, schema: &'a Value, path: &str) -> Result<GrammarExpr> {
+        let schema = match schema {
+            Value::Bool(true) => return Ok(self.any_value()),
+            Value::Bool(false) => return Err(unsupported(path, "a schema that accepts nothing")),
+            Value::Object(schema) => schema,
+            _ => return Err(unsupported(path, "a schema that is not an object")),
+        };
+        if let Some(keyword) = UNSUPPORTED.iter().find(|k| schema.contains_key(**k)) {
+            return Err(unsupported(path, &format!("'{keyword}'")));
+        }
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return self.reference(reference, path);
+        }
+        if let Some(value) = schema.get("const") {
+            return Ok(json_literal(value));
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            return Ok(GrammarExpr::choice(values.iter().map(json_literal)));
+        }
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(options) = schema.get(keyword).and_then(Value::as_array) {
+                let choices = options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option)| self.expr(option, &format!("{path}/{keyword}/{i}")))
+                    .collect::<Result<Vec<_>>>()?;
+                return Ok(GrammarExpr::choice(choices));
+            }
+        }
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            return match all.as_slice() {
+                [only] => self.expr(only, &format!("{path}/allOf/0")),
+                _ => Err(unsupported(path, "'allOf' with more than one schema")),
+            };
+        }
+
+        match schema.get("type") {
+            Some(Value::String(name)) => self.typed(name, schema, path),
+            Some(Value::Array(names)) => {
+                let choices = names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|name| self.typed(name, schema, path))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(GrammarExpr::choice(choices))
+            }
+            Some(_) => Err(unsupported(path, "a 'type' that is not a string or list")),
+            None if schema.contains_key("properties") => self.typed("object", schema, path),
+            None if schema.contains_key("items") => self.typed("array", schema, path),
+            None => Ok(self.any_value()),
+        }
+    }
+
+    fn typed(
+        &mut self,
+        name: &str,
+        schema: &'a Map<String, Value>,
+        path: &str,
+    ) -> Result<GrammarExpr> {
+        Ok(match name {
+            "string" => self.terminal("STRING", STRING),
+            "number" => self.terminal("NUMBER", NUMBER),
+            "integer" => self.terminal("INTEGER", INTEGER),
+            "boolean" => GrammarExpr::one_of(&["true", "false"]),
+            "null" => GrammarExpr::literal("null"),
+            "object" => match schema.get("properties").and_then(Value::as_object) {
+                Some(properties) => self.object(properties, schema, path)?,
+                None => self.any_object(),
+            },
+            "array" => {
+                let item = match schema.get("items") {
+                    Some(items) => {
+                        let item = self.expr(items, &format!("{path}/items"))?;
+                        self.named(item)
+                    }
+                    None => self.any_value(),
+                };
+                let non_empty = schema
+                    .get("minItems")
+                    .and_then(Value::as_u64)
+                    .is_some_and(|n| n > 0);
+                array(item, non_empty)
+            }
+            other => return Err(unsupported(path, &format!("the type '{other}'"))),
+        })
+    }
+
+    /// A closed object listing the properties in key order.
+    fn object(
+        &mut self,
+        properties: &'a Map<String, Value>,
+        schema: &'a Map<String, Value>,
+        path: &str,
+    ) -> Result<GrammarExpr> {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let mut members = Vec::new();
+        for (name, property) in properties {
+            let value = self.expr(property, &format!("{path}/properties/{name}"))?;
+            let key = format!("{}:", Value::String(name.clone()));
+            members.push((
+                GrammarExpr::seq([GrammarExpr::literal(key), value]),
+                required.contains(&name.as_str()),
+            ));
+        }
+        let mut parts = vec![GrammarExpr::literal("{")];
+        parts.extend(members_expr(&members, 0, true));
+        parts.push(GrammarExpr::literal("}"));
+        Ok(GrammarExpr::seq(parts))
+    }
+
+    fn reference(&mut self, reference: &str, path: &str) -> Result<GrammarExpr> {
+        if let Some(name) = self.refs.get(reference) {
+            return Ok(GrammarExpr::rule(name.clone()));
+        }
+        let target = reference
+            .strip_prefix('#')
+            .and_then(|pointer| self.root.pointer(pointer))
+            .ok_or_else(|| unsupported(path, &format!("the $ref '{reference}'")))?;
+        let name = self.fresh("def");
+        // Register first so recursive references resolve to the rule.
+        self.refs.insert(reference.to_owned(), name.clone());
+        let expr = self.expr(target, reference.trim_start_matches('#'))?;
+        self.grammar = std::mem::take(&mut self.grammar).rule(name.clone(), expr);
+        Ok(GrammarExpr::rule(name))
+    }
+
+    /// Move `expr` into a rule of its own unless it is already a name.
+    fn named(&mut self, expr: GrammarExpr) -> GrammarExpr {
+        if matches!(expr, GrammarExpr::Rule(_)) {
+            return expr;
+        }
+        let name = self.fresh("item");
+        self.grammar = std::mem::take(&mut self.grammar).rule(name.clone(), expr);
+        GrammarExpr::rule(name)
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}_{}", self.next_id)
+    }
+
+    fn terminal(&mut self, name: &str, pattern: &str) -> GrammarExpr {
+        if !self.grammar.names().any(|n| n == name) {
+            self.grammar =
+                std::mem::take(&mut self.grammar).terminal(name, GrammarExpr::regex(pattern));
+        }
+        GrammarExpr::rule(name)
+    }
+
+    /// Rules for arbitrary JSON, added on first use.
+    fn any_value(&mut self) -> GrammarExpr {
+        if !self.grammar.names().any(|n| n == "json_value") {
+            let string = self.terminal("STRING", STRING);
+            let number = self.terminal("NUMBER", NUMBER);
+            let value = GrammarExpr::rule("json_value");
+            self.grammar = std::mem::take(&mut self.grammar)
+                .rule(
+                    "json_value",
+                    GrammarExpr::choice([
+                        GrammarExpr::rule("json_object"),
+                        GrammarExpr::rule("json_array"),
+                        string.clone(),
+                        number,
+                        GrammarExpr::one_of(&["true", "false", "null"]),
+                    ]),
+                )
+                .rule(
+                    "json_object",
+                    GrammarExpr::seq([
+                        GrammarExpr::literal("{"),
+                        list(GrammarExpr::rule("json_member")).optional(),
+                        GrammarExpr::literal("}"),
+                    ]),
+                )
+                .rule(
+                    "json_member",
+                    GrammarExpr::seq([string, GrammarExpr::literal(":"), value.clone()]),
+                )
+                .rule("json_array", array(value, false));
+        }
+        GrammarExpr::rule("json_value")
+    }
+
+    fn any_object(&mut self) -> GrammarExpr {
+        self.any_value();
+        GrammarExpr::rule("json_object")
+    }
+}
+
+/// The members from `index` on.  `first` means no member has been written
+/// yet, so the next one needs no comma.  Optional members after the first
+/// written one become `("," member)?`, which keeps the expression linear
+/// in the number of members.
+fn members_expr(members: &[(GrammarExpr, bool)], index: usize, first: bool) -> Option<GrammarExpr> {
+    let (member, required) = members.get(index)?;
+    let lead = if first {
+        member.clone()
+    } else {
+        GrammarExpr::seq([GrammarExpr::literal(","), member.clone()])
+    };
+    let rest = members_expr(members, index + 1, false);
+    let then = |lead: GrammarExpr| match &rest {
+        Some(rest) => GrammarExpr::seq([lead, rest.clone()]),
+        None => lead,
+    };
+    Some(if *required {
+        then(lead)
+    } else if !first {
+        then(lead.optional())
+    } else {
+        match members_expr(members, index + 1, true) {
+            Some(skip) => GrammarExpr::choice([then(lead), skip]),
+            None => then(lead).optional(),
+        }
+    })
+}
+
+/// `item ("," item)*`
+fn list(item: GrammarExpr) -> GrammarExpr {
+    GrammarExpr::seq([
+        item.clone(),
+        GrammarExpr::seq([GrammarExpr::literal(","), item]).zero_or_more(),
+    ])
+}
+
+fn array(item: GrammarExpr, non_empty: bool) -> GrammarExpr {
+    let items = list(item);
+    GrammarExpr::seq([
+        GrammarExpr::literal("["),
+        if non_empty { items } else { items.optional() },
+        GrammarExpr::literal("]"),
+    ])
+}
+
+fn json_literal(value: &Value) -> GrammarExpr {
+    GrammarExpr::literal(value.to_string())
+}
+
+fn unsupported(path: &str, what: &str) -> FoundryLocalError {
+    let at = if path.is_empty() { "/" } else { path };
+    FoundryLocalError::Validation {
+        reason: format!("cannot express {what} at {at} in a grammar"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn converts_an_object_with_optional_members() {
+        let grammar = convert(&json!({
+            "type": "object",
+            "properties": {
+                "label": { "enum": ["spam", "ham"] },
+                "score": { "type": "number" },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["label"]
+        }))
+        .unwrap();
+        let text = grammar.to_string();
+        assert!(
+            text.starts_with(
+                "start: \"{\" \"\\\"label\\\":\" (\"\\\"spam\\\"\" | \"\\\"ham\\\"\") \
+                 (\",\" \"\\\"score\\\":\" NUMBER)? (\",\" \"\\\"tags\\\":\" \"[\" (STRING (\",\" STRING)*)? \"]\")? \"}\"\n"
+            ),
+            "{text}"
+        );
+        Grammar::validate_lark(&text).unwrap();
+    }
+
+    #[test]
+    fn leading_optional_members_need_no_comma() {
+        let members = [
+            (GrammarExpr::literal("a"), false),
+            (GrammarExpr::literal("b"), false),
+        ];
+        let mut text = String::new();
+        members_expr(&members, 0, true)
+            .unwrap()
+            .render(0, &mut text);
+        assert_eq!(text, "\"a\" (\",\" \"b\")? | \"b\"?");
+    }
+
+    #[test]
+    fn resolves_recursive_refs_and_rejects_patterns() {
+        let grammar = convert(&json!({
+            "$ref": "#/$defs/Node",
+            "$defs": {
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "children": { "type": "array", "items": { "$ref": "#/$defs/Node" } },
+                        "extra": true
+                    },
+                    "required": ["children", "extra"]
+                }
+            }
+        }))
+        .unwrap();
+        assert!(grammar.to_string().starts_with("start: def_1\n"));
+        grammar.validate().unwrap();
+
+        let err = convert(&json!({
+            "type": "object",
+            "properties": { "zip": { "type": "string", "pattern": "^[0-9]{5}$" } }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "validation error: cannot express 'pattern' at /properties/zip in a grammar"
+        );
+    }
+}
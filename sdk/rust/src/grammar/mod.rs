@@ -0,0 +1,404 @@
+//! Typed builder for the Lark grammars accepted by
+//! [`ChatResponseFormat::LarkGrammar`].
+
+mod json_schema;
+mod syntax;
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::error::{FoundryLocalError, Result};
+use crate::types::ChatResponseFormat;
+
+/// One expansion of a grammar rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrammarExpr {
+    /// Exact text.
+    Literal(String),
+    /// Text matching a regular expression.
+    Regex(String),
+    /// Reference to a rule or terminal by name.
+    Rule(String),
+    /// Each expression in turn.
+    Sequence(Vec<GrammarExpr>),
+    /// Exactly one of the expressions.
+    Choice(Vec<GrammarExpr>),
+    /// The expression or nothing.
+    Optional(Box<GrammarExpr>),
+    /// The expression repeated zero or more times.
+    ZeroOrMore(Box<GrammarExpr>),
+    /// The expression repeated one or more times.
+    OneOrMore(Box<GrammarExpr>),
+}
+
+impl GrammarExpr {
+    pub fn literal(text: impl Into<String>) -> Self {
+        Self::Literal(text.into())
+    }
+
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self::Regex(pattern.into())
+    }
+
+    pub fn rule(name: impl Into<String>) -> Self {
+        Self::Rule(name.into())
+    }
+
+    pub fn seq(items: impl IntoIterator<Item = GrammarExpr>) -> Self {
+        Self::Sequence(items.into_iter().collect())
+    }
+
+    pub fn choice(items: impl IntoIterator<Item = GrammarExpr>) -> Self {
+        Self::Choice(items.into_iter().collect())
+    }
+
+    /// A choice between literal texts.
+    pub fn one_of<S: AsRef<str>>(texts: &[S]) -> Self {
+        Self::choice(texts.iter().map(|t| Self::literal(t.as_ref())))
+    }
+
+    pub fn optional(self) -> Self {
+        Self::Optional(Box::new(self))
+    }
+
+    pub fn zero_or_more(self) -> Self {
+        Self::ZeroOrMore(Box::new(self))
+    }
+
+    pub fn one_or_more(self) -> Self {
+        Self::OneOrMore(Box::new(self))
+    }
+
+    /// The kind of the first empty sequence or choice in the expression,
+    /// which would render as nothing.
+    fn empty_group(&self) -> Option<&'static str> {
+        match self {
+            Self::Literal(_) | Self::Regex(_) | Self::Rule(_) => None,
+            Self::Sequence(items) if items.is_empty() => Some("sequence"),
+            Self::Choice(items) if items.is_empty() => Some("choice"),
+            Self::Sequence(items) | Self::Choice(items) => {
+                items.iter().find_map(GrammarExpr::empty_group)
+            }
+            Self::Optional(inner) | Self::ZeroOrMore(inner) | Self::OneOrMore(inner) => {
+                inner.empty_group()
+            }
+        }
+    }
+
+    /// Render at `level`: 0 allows a bare choice, 1 a bare sequence and 2
+    /// only an atom.
+    fn render(&self, level: u8, out: &mut String) {
+        match self {
+            Self::Literal(text) => render_literal(text, out),
+            Self::Regex(pattern) => render_regex(pattern, out),
+            Self::Rule(name) => out.push_str(name),
+            Self::Sequence(items) if items.len() == 1 => items[0].render(level, out),
+            Self::Sequence(items) => {
+                group(level >= 2, out, |out| {
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            out.push(' ');
+                        }
+                        item.render(1, out);
+                    }
+                });
+            }
+            Self::Choice(items) if items.len() == 1 => items[0].render(level, out),
+            Self::Choice(items) => {
+                group(level >= 1, out, |out| {
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            out.push_str(" | ");
+                        }
+                        item.render(1, out);
+                    }
+                });
+            }
+            Self::Optional(inner) => postfix(inner, '?', out),
+            Self::ZeroOrMore(inner) => postfix(inner, '*', out),
+            Self::OneOrMore(inner) => postfix(inner, '+', out),
+        }
+    }
+}
+
+fn group(parens: bool, out: &mut String, body: impl FnOnce(&mut String)) {
+    if parens {
+        out.push('(');
+    }
+    body(out);
+    if parens {
+        out.push(')');
+    }
+}
+
+fn postfix(inner: &GrammarExpr, op: char, out: &mut String) {
+    inner.render(2, out);
+    out.push(op);
+}
+
+fn render_literal(text: &str, out: &mut String) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn render_regex(pattern: &str, out: &mut String) {
+    out.push('/');
+    let mut escaped = false;
+    for c in pattern.chars() {
+        match c {
+            '/' if !escaped => out.push_str("\\/"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    out.push('/');
+}
+
+/// A Lark grammar, built from named rules.
+///
+/// Rule names are lowercase; uppercase names define terminals, which match
+/// text without referring to rules.  Generation starts at the rule `start`.
+///
+/// ```ignore
+/// let grammar = Grammar::new()
+///     .rule("start", GrammarExpr::seq([
+///         GrammarExpr::one_of(&["positive", "negative"]),
+///         GrammarExpr::literal(" "),
+///         GrammarExpr::rule("SCORE"),
+///     ]))
+///     .terminal("SCORE", GrammarExpr::regex("[0-9]{1,3}"));
+///
+/// let client = model.create_chat_client().response_format(grammar.try_into()?);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Grammar {
+    rules: Vec<Definition>,
+}
+
+/// A rule or terminal of a [`Grammar`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition {
+    name: String,
+    expr: GrammarExpr,
+    /// Added with [`Grammar::terminal`].
+    terminal: bool,
+}
+
+impl Grammar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the rule `name`.
+    pub fn rule(self, name: impl Into<String>, expr: GrammarExpr) -> Self {
+        self.define(name.into(), expr, false)
+    }
+
+    /// Add or replace the terminal `name`, which must be uppercase;
+    /// [`Grammar::validate`] rejects other names.
+    pub fn terminal(self, name: impl Into<String>, expr: GrammarExpr) -> Self {
+        self.define(name.into(), expr, true)
+    }
+
+    fn define(mut self, name: String, expr: GrammarExpr, terminal: bool) -> Self {
+        let definition = Definition {
+            name,
+            expr,
+            terminal,
+        };
+        match self.rules.iter_mut().find(|d| d.name == definition.name) {
+            Some(existing) => *existing = definition,
+            None => self.rules.push(definition),
+        }
+        self
+    }
+
+    /// Output restricted to exactly one of `choices`, e.g. for
+    /// classification.
+    pub fn one_of<S: AsRef<str>>(choices: &[S]) -> Self {
+        Self::new().rule("start", GrammarExpr::one_of(choices))
+    }
+
+    /// Output matching the regular expression `pattern`.
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self::new().rule("start", GrammarExpr::regex(pattern))
+    }
+
+    /// Compact JSON matching `schema`.
+    ///
+    /// Supports `type` (including type lists), `properties` and `required`
+    /// (objects are closed and list their properties in the key order of
+    /// the parsed schema), `items` with `minItems` of 0 or 1, `enum`,
+    /// `const`, `anyOf`/`oneOf`, single-element `allOf` and local `$ref`s.
+    /// Numeric and length bounds are not expressed in the grammar; check
+    /// them with [`ChatClient::complete_json`](crate::openai::ChatClient::complete_json).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::Validation`] for keywords that would
+    /// change the accepted values but cannot be expressed, such as `pattern`
+    /// or multi-element `allOf`, and for an empty `enum`, `anyOf`, `oneOf`
+    /// or `type` list, which no value matches.
+    pub fn from_json_schema(schema: &Value) -> Result<Self> {
+        let grammar = json_schema::convert(schema)?;
+        grammar.check_definitions()?;
+        Ok(grammar)
+    }
+
+    /// Names of the rules and terminals, in definition order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|d| d.name.as_str())
+    }
+
+    /// Check that terminal names are uppercase and that no sequence or
+    /// choice is empty, then check the rendered grammar with
+    /// [`Grammar::validate_lark`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::Validation`] naming the first problem.
+    pub fn validate(&self) -> Result<()> {
+        self.check_definitions()?;
+        Self::validate_lark(&self.to_string())
+    }
+
+    fn check_definitions(&self) -> Result<()> {
+        for definition in &self.rules {
+            let name = &definition.name;
+            if definition.terminal && !syntax::is_terminal(name) {
+                return Err(invalid(format!("terminal '{name}' must be uppercase")));
+            }
+            if let Some(kind) = definition.expr.empty_group() {
+                return Err(invalid(format!("'{name}' contains an empty {kind}")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the syntax of a Lark grammar without sending it.
+    ///
+    /// Verifies that every definition parses, that rule and terminal names
+    /// are well formed and unique, that every referenced name is defined or
+    /// imported, that terminals only refer to terminals and that a `start`
+    /// rule exists.  Regular expressions are passed through unchecked, and
+    /// grammars using templates, rule attributes, `%override`, `%extend` or
+    /// other syntax outside this subset are accepted without checking.
+    /// [`ChatClient`](crate::openai::ChatClient) sends
+    /// [`ChatResponseFormat::LarkGrammar`] text as is, so call this to fail
+    /// fast on hand-written grammars.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::Validation`](crate::FoundryLocalError::Validation)
+    /// naming the line of the first problem.
+    pub fn validate_lark(text: &str) -> Result<()> {
+        syntax::validate(text)
+    }
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for Definition { name, expr, .. } in &self.rules {
+            let mut line = String::new();
+            expr.render(0, &mut line);
+            writeln!(f, "{name}: {line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts after [`Grammar::validate`] succeeds.
+impl TryFrom<Grammar> for ChatResponseFormat {
+    type Error = FoundryLocalError;
+
+    fn try_from(grammar: Grammar) -> Result<Self> {
+        grammar.validate()?;
+        Ok(ChatResponseFormat::LarkGrammar(grammar.to_string()))
+    }
+}
+
+fn invalid(reason: String) -> FoundryLocalError {
+    FoundryLocalError::Validation {
+        reason: format!("invalid grammar: {reason}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_with_minimal_parentheses() {
+        let grammar = Grammar::new()
+            .rule(
+                "start",
+                GrammarExpr::seq([
+                    GrammarExpr::one_of(&["yes", "no"]),
+                    GrammarExpr::seq([GrammarExpr::literal(","), GrammarExpr::rule("REASON")])
+                        .optional(),
+                ]),
+            )
+            .terminal("REASON", GrammarExpr::regex("[a-z /]+"));
+        assert_eq!(
+            grammar.to_string(),
+            "start: (\"yes\" | \"no\") (\",\" REASON)?\nREASON: /[a-z \\/]+/\n"
+        );
+        grammar.validate().unwrap();
+    }
+
+    #[test]
+    fn escapes_literals() {
+        let grammar = Grammar::one_of(&["say \"hi\"", "a\\b\n"]);
+        assert_eq!(
+            grammar.to_string(),
+            "start: \"say \\\"hi\\\"\" | \"a\\\\b\\n\"\n"
+        );
+        grammar.validate().unwrap();
+    }
+
+    #[test]
+    fn converts_into_a_response_format() {
+        match ChatResponseFormat::try_from(Grammar::regex(r"\d{3}-\d{4}")).unwrap() {
+            ChatResponseFormat::LarkGrammar(text) => assert_eq!(text, "start: /\\d{3}-\\d{4}/\n"),
+            other => panic!("unexpected format {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_lowercase_terminals_and_empty_groups() {
+        let lowercase = Grammar::new()
+            .rule("start", GrammarExpr::rule("score"))
+            .terminal("score", GrammarExpr::regex("[0-9]+"));
+        let err = ChatResponseFormat::try_from(lowercase).unwrap_err();
+        assert!(
+            err.to_string().contains("'score' must be uppercase"),
+            "{err}"
+        );
+
+        let empty: &[&str] = &[];
+        let err = Grammar::one_of(empty).validate().unwrap_err();
+        assert!(err.to_string().contains("empty choice"), "{err}");
+
+        let nested = Grammar::new().rule(
+            "start",
+            GrammarExpr::seq([GrammarExpr::literal("a"), GrammarExpr::seq([]).optional()]),
+        );
+        let err = nested.validate().unwrap_err();
+        assert!(err.to_string().contains("empty sequence"), "{err}");
+
+        let err = Grammar::from_json_schema(&serde_json::json!({ "enum": [] })).unwrap_err();
+        assert!(matches!(err, FoundryLocalError::Validation { .. }), "{err}");
+    }
+}
@@ -0,0 +1,723 @@
+//! Local syntax check for Lark grammars, so mistakes surface before a
+//! request is sent.
+//!
+//! Only the common subset of Lark is checked.  Grammars using templates,
+//! rule attributes, `%override`, `%extend` or other syntax the checker does
+//! not model are accepted unchecked and left to the service.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{FoundryLocalError, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Name(String),
+    Directive(String),
+    Str,
+    EmptyStr,
+    Regex,
+    Json,
+    /// An llguidance special token such as `<|end|>`.
+    Special,
+    /// A character outside the modelled subset.
+    Other(char),
+    Num,
+    Colon,
+    Pipe,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Question,
+    Bang,
+    Star,
+    Plus,
+    Tilde,
+    Comma,
+    Dot,
+    DotDot,
+    Arrow,
+    Newline,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    line: usize,
+}
+
+fn error(line: usize, message: impl AsRef<str>) -> FoundryLocalError {
+    FoundryLocalError::Validation {
+        reason: format!("invalid Lark grammar: line {line}: {}", message.as_ref()),
+    }
+}
+
+pub(super) fn validate(text: &str) -> Result<()> {
+    let tokens = tokenize(text)?;
+    if unmodelled(&tokens) {
+        return Ok(());
+    }
+    let mut defined: HashMap<String, usize> = HashMap::new();
+    let mut imported: HashSet<String> = HashSet::new();
+    let mut references: Vec<(String, usize, Option<String>)> = Vec::new();
+
+    for statement in statements(&tokens) {
+        let line = statement[0].line;
+        match &statement[0].tok {
+            Tok::Directive(name) => match name.as_str() {
+                "ignore" => {
+                    let mut parser = Parser::new(&statement[1..], line);
+                    parser.definition_body()?;
+                    references.extend(parser.refs.into_iter().map(|(r, l)| (r, l, None)));
+                }
+                "import" | "declare" => {
+                    imported.extend(imported_names(name, &statement[1..]));
+                }
+                _ => {}
+            },
+            _ => {
+                let (name, body) = definition_head(&statement, line)?;
+                if defined.insert(name.clone(), line).is_some() {
+                    return Err(error(line, format!("'{name}' is defined more than once")));
+                }
+                let mut parser = Parser::new(body, line);
+                parser.definition_body()?;
+                let owner = is_terminal(&name).then_some(name);
+                references.extend(parser.refs.into_iter().map(|(r, l)| (r, l, owner.clone())));
+            }
+        }
+    }
+
+    for (name, line, terminal) in &references {
+        if !defined.contains_key(name) && !imported.contains(name) {
+            return Err(error(*line, format!("'{name}' is not defined")));
+        }
+        if let Some(terminal) = terminal {
+            if !is_terminal(name) {
+                return Err(error(
+                    *line,
+                    format!("terminal '{terminal}' refers to the rule '{name}'"),
+                ));
+            }
+        }
+    }
+    if !defined.contains_key("start") {
+        return Err(error(1, "the grammar has no 'start' rule"));
+    }
+    Ok(())
+}
+
+/// Whether the grammar uses syntax the checker does not model: unknown
+/// characters or directives, templates (`name{a, b}`) or rule attributes
+/// (`name[stop=""]:`).
+fn unmodelled(tokens: &[Token]) -> bool {
+    const KNOWN_DIRECTIVES: [&str; 4] = ["ignore", "import", "declare", "llguidance"];
+    tokens.iter().enumerate().any(|(i, token)| {
+        let next = tokens.get(i + 1).map(|t| &t.tok);
+        match &token.tok {
+            Tok::Other(_) => true,
+            Tok::Directive(name) => !KNOWN_DIRECTIVES.contains(&name.as_str()),
+            Tok::Name(_) if next == Some(&Tok::LBrace) => {
+                !matches!(tokens.get(i + 2).map(|t| &t.tok), Some(Tok::Num))
+            }
+            Tok::Name(_) if next == Some(&Tok::LBracket) => {
+                i == 0 || matches!(tokens[i - 1].tok, Tok::Newline | Tok::Question | Tok::Bang)
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Split the tokens into top-level statements.  A line break ends a
+/// statement unless it is inside brackets or the next line starts with `|`.
+fn statements(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.tok {
+            Tok::LParen | Tok::LBracket | Tok::LBrace => depth += 1,
+            Tok::RParen | Tok::RBracket | Tok::RBrace => depth = depth.saturating_sub(1),
+            Tok::Newline => {
+                let continues = tokens[i + 1..]
+                    .iter()
+                    .find(|t| t.tok != Tok::Newline)
+                    .is_some_and(|t| t.tok == Tok::Pipe);
+                if depth == 0 && !continues && !current.is_empty() {
+                    statements.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(token.clone());
+    }
+    if !current.is_empty() {
+        statements.push(current);
+    }
+    statements
+}
+
+/// The defined name and the tokens after `:`.  Accepts the `?` and `!`
+/// rule modifiers and a `.priority` suffix.
+fn definition_head(statement: &[Token], line: usize) -> Result<(String, &[Token])> {
+    let mut i = 0;
+    if matches!(statement[0].tok, Tok::Question | Tok::Bang) {
+        i += 1;
+    }
+    let name = match statement.get(i).map(|t| &t.tok) {
+        Some(Tok::Name(name)) => name.clone(),
+        _ => return Err(error(line, "expected a rule or terminal name")),
+    };
+    if !is_rule(&name) && !is_terminal(&name) {
+        return Err(error(
+            line,
+            format!("'{name}' must be lowercase (a rule) or uppercase (a terminal)"),
+        ));
+    }
+    i += 1;
+    if statement.get(i).map(|t| &t.tok) == Some(&Tok::Dot) {
+        if statement.get(i + 1).map(|t| &t.tok) != Some(&Tok::Num) {
+            return Err(error(line, format!("expected a priority after '{name}.'")));
+        }
+        i += 2;
+    }
+    if statement.get(i).map(|t| &t.tok) != Some(&Tok::Colon) {
+        return Err(error(line, format!("expected ':' after '{name}'")));
+    }
+    let body = &statement[i + 1..];
+    if body.is_empty() {
+        return Err(error(line, format!("'{name}' has no definition")));
+    }
+    Ok((name, body))
+}
+
+/// Names made available by `%import` or `%declare`.
+fn imported_names(directive: &str, tokens: &[Token]) -> Vec<String> {
+    let mut names = tokens.iter().filter_map(|t| match &t.tok {
+        Tok::Name(n) => Some(n.clone()),
+        _ => None,
+    });
+    if directive == "declare" {
+        return names.collect();
+    }
+    // `%import module.NAME`, `%import module.NAME -> ALIAS` or
+    // `%import module (A, B)` define the last name of each path.
+    if tokens.iter().any(|t| t.tok == Tok::LParen) {
+        let start = tokens
+            .iter()
+            .position(|t| t.tok == Tok::LParen)
+            .unwrap_or(0);
+        return tokens[start..]
+            .iter()
+            .filter_map(|t| match &t.tok {
+                Tok::Name(n) => Some(n.clone()),
+                _ => None,
+            })
+            .collect();
+    }
+    names.next_back().into_iter().collect()
+}
+
+fn is_rule(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+pub(super) fn is_terminal(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_uppercase() || c == '_')
+        && name.chars().any(|c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    line: usize,
+    refs: Vec<(String, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Token], line: usize) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            line,
+            refs: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn here(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(self.line, |t| t.line)
+    }
+
+    fn eat(&mut self, tok: &Tok) -> bool {
+        if self.peek() == Some(tok) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, tok: &Tok, what: &str) -> Result<()> {
+        if self.eat(tok) {
+            Ok(())
+        } else {
+            Err(error(self.here(), format!("expected {what}")))
+        }
+    }
+
+    fn definition_body(&mut self) -> Result<()> {
+        self.alternatives()?;
+        match self.peek() {
+            None => Ok(()),
+            Some(tok) => Err(error(self.here(), format!("unexpected {}", describe(tok)))),
+        }
+    }
+
+    fn alternatives(&mut self) -> Result<()> {
+        self.alternative()?;
+        while self.eat(&Tok::Pipe) {
+            self.alternative()?;
+        }
+        Ok(())
+    }
+
+    fn alternative(&mut self) -> Result<()> {
+        let start = self.pos;
+        while self.starts_atom() {
+            self.item()?;
+        }
+        if self.pos == start {
+            return Err(error(self.here(), "empty alternative"));
+        }
+        if self.eat(&Tok::Arrow) {
+            self.expect_name("an alias name after '->'")?;
+        }
+        Ok(())
+    }
+
+    fn starts_atom(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(
+                Tok::LParen
+                    | Tok::LBracket
+                    | Tok::Str
+                    | Tok::EmptyStr
+                    | Tok::Regex
+                    | Tok::Name(_)
+                    | Tok::Json
+                    | Tok::Special
+            )
+        )
+    }
+
+    fn item(&mut self) -> Result<()> {
+        self.atom()?;
+        match self.peek() {
+            Some(Tok::Question | Tok::Star | Tok::Plus) => self.pos += 1,
+            Some(Tok::Tilde) => {
+                self.pos += 1;
+                self.expect(&Tok::Num, "a repeat count after '~'")?;
+                if self.eat(&Tok::DotDot) {
+                    self.expect(&Tok::Num, "an upper repeat count after '..'")?;
+                }
+            }
+            Some(Tok::LBrace) => {
+                self.pos += 1;
+                self.expect(&Tok::Num, "a repeat count after '{'")?;
+                if self.eat(&Tok::Comma) {
+                    self.eat(&Tok::Num);
+                }
+                self.expect(&Tok::RBrace, "'}'")?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self) -> Result<()> {
+        let line = self.here();
+        match self.peek().cloned() {
+            Some(Tok::LParen) => {
+                self.pos += 1;
+                self.alternatives()?;
+                self.expect(&Tok::RParen, "')'")
+            }
+            Some(Tok::LBracket) => {
+                self.pos += 1;
+                self.alternatives()?;
+                self.expect(&Tok::RBracket, "']'")
+            }
+            Some(Tok::Str) => {
+                self.pos += 1;
+                if self.eat(&Tok::DotDot) {
+                    self.expect(&Tok::Str, "a string after '..'")?;
+                }
+                Ok(())
+            }
+            Some(Tok::EmptyStr) => Err(error(line, "empty string literal")),
+            Some(Tok::Regex | Tok::Json | Tok::Special) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(Tok::Name(name)) => {
+                self.pos += 1;
+                self.refs.push((name, line));
+                Ok(())
+            }
+            other => Err(error(
+                line,
+                format!(
+                    "expected an expression, found {}",
+                    other.as_ref().map_or("the end of the definition", describe)
+                ),
+            )),
+        }
+    }
+
+    fn expect_name(&mut self, what: &str) -> Result<()> {
+        match self.peek() {
+            Some(Tok::Name(_)) => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(error(self.here(), format!("expected {what}"))),
+        }
+    }
+}
+
+fn describe(tok: &Tok) -> &'static str {
+    match tok {
+        Tok::Name(_) => "a name",
+        Tok::Directive(_) => "a directive",
+        Tok::Str | Tok::EmptyStr => "a string",
+        Tok::Regex => "a regular expression",
+        Tok::Json => "a JSON schema",
+        Tok::Special => "a special token",
+        Tok::Other(_) => "an unexpected character",
+        Tok::Num => "a number",
+        Tok::Colon => "':'",
+        Tok::Pipe => "'|'",
+        Tok::LParen => "'('",
+        Tok::RParen => "')'",
+        Tok::LBracket => "'['",
+        Tok::RBracket => "']'",
+        Tok::LBrace => "'{'",
+        Tok::RBrace => "'}'",
+        Tok::Question => "'?'",
+        Tok::Bang => "'!'",
+        Tok::Star => "'*'",
+        Tok::Plus => "'+'",
+        Tok::Tilde => "'~'",
+        Tok::Comma => "','",
+        Tok::Dot => "'.'",
+        Tok::DotDot => "'..'",
+        Tok::Arrow => "'->'",
+        Tok::Newline => "a line break",
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let mut push = |tok: Tok| tokens.push(Token { tok, line });
+        match c {
+            '\n' => {
+                push(Tok::Newline);
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '"' => {
+                let end = closing(&chars, i, '"')
+                    .ok_or_else(|| error(line, "unterminated string literal"))?;
+                push(if end == i + 1 {
+                    Tok::EmptyStr
+                } else {
+                    Tok::Str
+                });
+                i = end + 1;
+                if chars.get(i) == Some(&'i') {
+                    i += 1;
+                }
+            }
+            '/' => {
+                let end = closing(&chars, i, '/')
+                    .ok_or_else(|| error(line, "unterminated regular expression"))?;
+                if end == i + 1 {
+                    return Err(error(line, "empty regular expression"));
+                }
+                push(Tok::Regex);
+                i = end + 1;
+                while chars.get(i).is_some_and(|c| "imslux".contains(*c)) {
+                    i += 1;
+                }
+            }
+            '%' => {
+                let start = i + 1;
+                i = start;
+                while chars.get(i).is_some_and(|c| c.is_ascii_alphabetic()) {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                if name.is_empty() {
+                    return Err(error(line, "expected a directive name after '%'"));
+                }
+                let mut j = i;
+                while chars.get(j).is_some_and(|c| *c == ' ' || *c == '\t') {
+                    j += 1;
+                }
+                if chars.get(j) == Some(&'{') && (name == "json" || name == "llguidance") {
+                    let (end, lines) = json_block(&chars, j)
+                        .ok_or_else(|| error(line, format!("unterminated %{name} block")))?;
+                    push(if name == "json" {
+                        Tok::Json
+                    } else {
+                        Tok::Directive(name)
+                    });
+                    line += lines;
+                    i = end + 1;
+                } else if name == "json" {
+                    return Err(error(line, "expected a JSON schema after %json"));
+                } else {
+                    push(Tok::Directive(name));
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while chars
+                    .get(i)
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    i += 1;
+                }
+                push(Tok::Name(chars[start..i].iter().collect()));
+            }
+            c if c.is_ascii_digit() => {
+                while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+                    i += 1;
+                }
+                push(Tok::Num);
+            }
+            '<' if next == Some('|') => {
+                let end = special_end(&chars, i + 2)
+                    .ok_or_else(|| error(line, "unterminated special token"))?;
+                push(Tok::Special);
+                i = end;
+            }
+            '-' if next == Some('>') => {
+                push(Tok::Arrow);
+                i += 2;
+            }
+            '.' if next == Some('.') => {
+                push(Tok::DotDot);
+                i += 2;
+            }
+            _ => {
+                let tok = match c {
+                    ':' => Tok::Colon,
+                    '|' => Tok::Pipe,
+                    '(' => Tok::LParen,
+                    ')' => Tok::RParen,
+                    '[' => Tok::LBracket,
+                    ']' => Tok::RBracket,
+                    '{' => Tok::LBrace,
+                    '}' => Tok::RBrace,
+                    '?' => Tok::Question,
+                    '!' => Tok::Bang,
+                    '*' => Tok::Star,
+                    '+' => Tok::Plus,
+                    '~' => Tok::Tilde,
+                    ',' => Tok::Comma,
+                    '.' => Tok::Dot,
+                    other => Tok::Other(other),
+                };
+                push(tok);
+                i += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Index of the unescaped `delimiter` closing the literal opened at
+/// `open`, on the same line.
+fn closing(chars: &[char], open: usize, delimiter: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in chars.iter().enumerate().skip(open + 1) {
+        match *c {
+            '\n' => return None,
+            '\\' if !escaped => escaped = true,
+            c if c == delimiter && !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Index just past the `|>` closing a special token, searching from `from`
+/// on the same line.
+fn special_end(chars: &[char], from: usize) -> Option<usize> {
+    (from..chars.len())
+        .take_while(|&i| chars[i] != '\n')
+        .find(|&i| chars[i..].starts_with(&['|', '>']))
+        .map(|i| i + 2)
+}
+
+/// End index and line count of the balanced JSON object opened at `open`.
+fn json_block(chars: &[char], open: usize) -> Option<(usize, usize)> {
+    let mut depth = 0usize;
+    let mut lines = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in chars.iter().enumerate().skip(open) {
+        if *c == '\n' {
+            lines += 1;
+        }
+        if in_string {
+            match *c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => in_string = false,
+                _ => escaped = false,
+            }
+            continue;
+        }
+        match *c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((i, lines));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(text: &str) -> String {
+        match validate(text) {
+            Err(FoundryLocalError::Validation { reason }) => reason,
+            other => panic!("expected a validation error for {text:?}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_common_lark() {
+        validate(
+            r#"
+// Sentiment with an optional confidence.
+start: label (" " CONFIDENCE)?
+     | "unknown"
+?label: "positive"i | "negative" -> neg
+CONFIDENCE.2: /0\.[0-9]+/ | "1"
+list: "[" [item ("," item)*] "]"
+item: ("a".."z")~1..3 | DIGIT{1,2}
+%import common.DIGIT
+%ignore /[ \t]+/
+json_reply: %json {
+    "type": "object", "properties": { "a": { "type": "string", "pattern": "}" } }
+}
+"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reports_the_first_problem_with_its_line() {
+        assert_eq!(
+            reason("start: answer\n"),
+            "invalid Lark grammar: line 1: 'answer' is not defined"
+        );
+        assert_eq!(
+            reason("answer: \"yes\"\n"),
+            "invalid Lark grammar: line 1: the grammar has no 'start' rule"
+        );
+        assert_eq!(
+            reason("start: (\"a\" | \"b\"\nx: \"c\"\n"),
+            "invalid Lark grammar: line 2: expected ')'"
+        );
+        assert_eq!(
+            reason("start: \"a\"\nstart: \"b\"\n"),
+            "invalid Lark grammar: line 2: 'start' is defined more than once"
+        );
+        assert_eq!(
+            reason("start: WORD\nWORD: word\nword: \"w\"\n"),
+            "invalid Lark grammar: line 2: terminal 'WORD' refers to the rule 'word'"
+        );
+        assert_eq!(
+            reason("start: \"a\" |\n"),
+            "invalid Lark grammar: line 1: empty alternative"
+        );
+        assert_eq!(
+            reason("start: /[a-z]+\n"),
+            "invalid Lark grammar: line 1: unterminated regular expression"
+        );
+        assert_eq!(
+            reason("Start: \"a\"\n"),
+            "invalid Lark grammar: line 1: 'Start' must be lowercase (a rule) or uppercase (a terminal)"
+        );
+        assert_eq!(
+            reason("start: \"a\" <|end\n"),
+            "invalid Lark grammar: line 1: unterminated special token"
+        );
+    }
+
+    #[test]
+    fn accepts_llguidance_special_tokens() {
+        validate("start: \"a\" <|end|>\n").unwrap();
+        assert_eq!(
+            reason("start: <|end|> answer\n"),
+            "invalid Lark grammar: line 1: 'answer' is not defined"
+        );
+    }
+
+    #[test]
+    fn leaves_unmodelled_syntax_to_the_service() {
+        for grammar in [
+            "start: _sep{WORD, \",\"}\n_sep{x, sep}: x (sep x)*\n%import common.WORD\n",
+            "%import common.NUMBER\n%override NUMBER: /[0-9]+/\nstart: NUMBER\n",
+            "%import common.NUMBER\n%extend NUMBER: \"x\"\nstart: NUMBER\n",
+            "start: text \"!\"\ntext[stop=\"\"]: /.*/\n",
+            "start: <[^<>]*>\n",
+            "start: \"a\" @ b\n",
+        ] {
+            validate(grammar).unwrap_or_else(|e| panic!("{grammar:?} was rejected: {e}"));
+        }
+    }
+}
@@ -6,19 +6,34 @@ mod catalog;
 mod configuration;
 mod error;
 mod foundry_local_manager;
+mod grammar;
+mod tokenizer;
 mod types;
 
 pub(crate) mod detail;
 pub mod openai;
 
 pub use self::catalog::Catalog;
-pub use self::configuration::{FoundryLocalConfig, LogLevel, Logger};
-pub use self::detail::model::{DownloadBuilder, Model};
+pub use self::configuration::{
+    CacheLockPolicy, FoundryLocalConfig, HttpSettings, LogLevel, Logger,
+};
+pub use self::detail::cache_maintenance::{
+    CacheEntry, CacheEntryKind, CacheReport, CleanupSummary,
+};
+pub use self::detail::loaded_model::LoadedModel;
+pub use self::detail::model::{DownloadBuilder, LoadBuilder, Model};
+pub use self::detail::model_inspect::{ModelFile, ModelInspection};
+pub use self::detail::warm_up::{WarmUpKind, WarmUpReport};
+pub use self::detail::ResidencyManager;
 pub use self::error::FoundryLocalError;
-pub use self::foundry_local_manager::{EpDownloadBuilder, FoundryLocalManager};
+pub use self::foundry_local_manager::{
+    EpDownloadBuilder, FoundryLocalManager, PrepareBuilder, PrepareProgress,
+};
+pub use self::grammar::{Grammar, GrammarExpr};
+pub use self::tokenizer::Tokenizer;
 pub use self::types::{
-    ChatResponseFormat, ChatToolChoice, DeviceType, EpDownloadResult, EpInfo, ModelInfo,
-    ModelSettings, Parameter, PromptTemplate, Runtime,
+    ArenaExtendStrategy, ChatResponseFormat, ChatToolChoice, DeviceType, EpDownloadResult, EpInfo,
+    ModelInfo, ModelLicense, ModelSettings, Parameter, PromptTemplate, Runtime,
 };
 
 // Re-export OpenAI request types so callers can construct typed messages.
@@ -26,7 +41,7 @@ pub use async_openai::types::chat::{
     ChatCompletionNamedToolChoice, ChatCompletionRequestAssistantMessage,
     ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
     ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
-    ChatCompletionToolChoiceOption, ChatCompletionTools, FunctionObject,
+    ChatCompletionToolChoiceOption, ChatCompletionTools, FunctionObject, ImageDetail,
 };
 
 // Re-export OpenAI response types for convenience.
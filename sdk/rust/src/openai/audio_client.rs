@@ -6,6 +6,7 @@ use std::sync::Arc;
 use serde_json::{json, Value};
 
 use crate::detail::core_interop::CoreInterop;
+use crate::detail::UsageTracker;
 use crate::error::{FoundryLocalError, Result};
 
 use super::json_stream::JsonStream;
@@ -114,6 +115,7 @@ pub struct AudioClient {
     model_id: String,
     core: Arc<CoreInterop>,
     settings: AudioClientSettings,
+    usage: Option<UsageTracker>,
 }
 
 impl AudioClient {
@@ -122,9 +124,16 @@ impl AudioClient {
             model_id: model_id.to_owned(),
             core,
             settings: AudioClientSettings::default(),
+            usage: None,
         }
     }
 
+    /// Count requests from this client as use of the model.
+    pub(crate) fn with_usage_tracker(mut self, tracker: UsageTracker) -> Self {
+        self.usage = Some(tracker);
+        self
+    }
+
     /// Set the language hint for transcription.
     pub fn language(mut self, lang: impl Into<String>) -> Self {
         self.settings.language = Some(lang.into());
@@ -150,6 +159,7 @@ impl AudioClient {
                     reason: "audio file path is not valid UTF-8".into(),
                 })?;
         Self::validate_path(path_str)?;
+        self.touch();
 
         let request = self.settings.serialize(&self.model_id, path_str);
         let params = json!({
@@ -180,6 +190,7 @@ impl AudioClient {
                     reason: "audio file path is not valid UTF-8".into(),
                 })?;
         Self::validate_path(path_str)?;
+        self.touch();
 
         let request = self.settings.serialize(&self.model_id, path_str);
 
@@ -206,6 +217,12 @@ impl AudioClient {
         LiveAudioTranscriptionSession::new(&self.model_id, Arc::clone(&self.core))
     }
 
+    fn touch(&self) {
+        if let Some(usage) = &self.usage {
+            usage.touch();
+        }
+    }
+
     fn validate_path(path: &str) -> Result<()> {
         if path.trim().is_empty() {
             return Err(FoundryLocalError::Validation {
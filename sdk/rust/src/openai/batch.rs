@@ -0,0 +1,402 @@
+//! Running many independent chat completions with bounded concurrency.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use async_openai::types::chat::{ChatCompletionRequestMessage, CreateChatCompletionResponse};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use crate::error::{FoundryLocalError, Result};
+
+use super::chat_client::ChatClient;
+use super::reasoning::{split_response, ReasoningMode};
+
+/// Requests run at once unless [`ChatBatch::concurrency`] says otherwise.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// The only endpoint batch input lines may target.
+const CHAT_COMPLETIONS_URL: &str = "/v1/chat/completions";
+
+/// Progress of a [`ChatBatch`], reported after every finished request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl BatchProgress {
+    /// Requests that have finished, successfully or not.
+    pub fn finished(&self) -> usize {
+        self.succeeded + self.failed
+    }
+}
+
+type BatchProgressCallback<'a> = Box<dyn FnMut(BatchProgress) + Send + 'a>;
+
+struct BatchItem {
+    custom_id: Option<String>,
+    request: Result<Value>,
+}
+
+/// A batch of independent chat completion requests.
+///
+/// Created by [`ChatClient::complete_batch`] or
+/// [`ChatClient::batch_from_jsonl`].  Requests run with bounded concurrency
+/// and results come back in input order; a failed request does not affect
+/// the others.
+///
+/// ```ignore
+/// let cancel = Arc::new(AtomicBool::new(false));
+/// let results = client
+///     .complete_batch(conversations)
+///     .concurrency(2)
+///     .progress(|p| println!("{}/{}", p.finished(), p.total))
+///     .cancel(Arc::clone(&cancel))
+///     .run()
+///     .await;
+/// for result in results {
+///     match result {
+///         Ok(response) => println!("{:?}", response.choices[0].message.content),
+///         Err(e) => eprintln!("failed: {e}"),
+///     }
+/// }
+/// ```
+pub struct ChatBatch<'a> {
+    client: &'a ChatClient,
+    items: Vec<BatchItem>,
+    concurrency: usize,
+    progress: Option<BatchProgressCallback<'a>>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+}
+
+impl ChatClient {
+    /// Start a batch with one request per conversation in `requests`,
+    /// sent with this client's settings.
+    pub fn complete_batch(
+        &self,
+        requests: impl IntoIterator<Item = Vec<ChatCompletionRequestMessage>>,
+    ) -> ChatBatch<'_> {
+        let items = requests
+            .into_iter()
+            .map(|messages| BatchItem {
+                custom_id: None,
+                request: self.batch_request(&messages),
+            })
+            .collect();
+        ChatBatch::new(self, items)
+    }
+
+    /// Start a batch from OpenAI Batch API input lines.
+    ///
+    /// Each non-empty line is an object with a `custom_id`, an optional
+    /// `method` (`POST`) and `url` (`/v1/chat/completions`), and a chat
+    /// completion request `body`.  Body fields override this client's
+    /// settings; its `model` is replaced with this client's model.  Lines
+    /// that cannot be parsed fail on their own when the batch runs.
+    pub fn batch_from_jsonl(&self, jsonl: &str) -> ChatBatch<'_> {
+        let items = jsonl
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (custom_id, body) = parse_input_line(line);
+                BatchItem {
+                    custom_id,
+                    request: body.and_then(|body| self.batch_body_request(&body)),
+                }
+            })
+            .collect();
+        ChatBatch::new(self, items)
+    }
+}
+
+impl<'a> ChatBatch<'a> {
+    fn new(client: &'a ChatClient, items: Vec<BatchItem>) -> Self {
+        Self {
+            client,
+            items,
+            concurrency: DEFAULT_CONCURRENCY,
+            progress: None,
+            cancel_flag: None,
+        }
+    }
+
+    /// Number of requests in the batch.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the batch has no requests.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Run at most `n` requests at once.  Defaults to 4.
+    pub fn concurrency(mut self, n: usize) -> Self {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    /// Report progress after every finished request.
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(BatchProgress) + Send + 'a,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Stop starting requests when `cancel_flag` is set to `true`.
+    ///
+    /// Requests already running finish; the rest fail with an
+    /// "Operation cancelled" error.
+    pub fn cancel(mut self, cancel_flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// Run the batch, returning one result per request in input order.
+    ///
+    /// Thinking is split off as the client's
+    /// [`reasoning`](ChatClient::reasoning) mode says, and dropped.
+    pub async fn run(self) -> Vec<Result<CreateChatCompletionResponse>> {
+        let split = self.client.reasoning_setting() != ReasoningMode::Inline;
+        let template_opens = self.client.template_opens_thinking();
+        let (_, outcomes) = self.execute().await;
+        outcomes
+            .into_iter()
+            .map(|outcome| {
+                let raw = outcome.unwrap_or_else(|| Err(cancelled()))?;
+                let mut response = serde_json::from_str(&raw)?;
+                if split {
+                    split_response(&mut response, template_opens);
+                }
+                Ok(response)
+            })
+            .collect()
+    }
+
+    /// Run the batch, returning OpenAI Batch API output lines in input
+    /// order.
+    ///
+    /// Each line has the request's `custom_id` and either a `response`
+    /// with the completion as its `body`, or an `error` with a `code` of
+    /// `invalid_request`, `cancelled` or `server_error`.  Requests from
+    /// [`complete_batch`](ChatClient::complete_batch) get the custom ids
+    /// `request-0`, `request-1`, and so on.  Bodies are returned as the
+    /// model produced them, thinking included.
+    pub async fn run_jsonl(self) -> String {
+        let (custom_ids, outcomes) = self.execute().await;
+        custom_ids
+            .into_iter()
+            .zip(outcomes)
+            .enumerate()
+            .map(|(index, (custom_id, outcome))| {
+                let custom_id = custom_id.unwrap_or_else(|| format!("request-{index}"));
+                let outcome = outcome.map(|r| r.and_then(|raw| Ok(serde_json::from_str(&raw)?)));
+                format!("{}\n", output_line(index, &custom_id, outcome))
+            })
+            .collect()
+    }
+
+    /// Send the requests, keeping `concurrency` of them in flight.  `None`
+    /// marks requests skipped after cancellation.
+    async fn execute(self) -> (Vec<Option<String>>, Vec<Option<Result<String>>>) {
+        let Self {
+            client,
+            items,
+            concurrency,
+            mut progress,
+            cancel_flag,
+        } = self;
+        let total = items.len();
+        let (custom_ids, requests): (Vec<_>, VecDeque<_>) = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| (item.custom_id, (index, item.request)))
+            .unzip();
+
+        let queue = Arc::new(Mutex::new(requests));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        for _ in 0..concurrency.min(total) {
+            let queue = Arc::clone(&queue);
+            let tx = tx.clone();
+            let core = client.core();
+            let cancel_flag = cancel_flag.clone();
+            tokio::spawn(async move {
+                loop {
+                    if cancel_flag
+                        .as_ref()
+                        .is_some_and(|flag| flag.load(Ordering::Acquire))
+                    {
+                        break;
+                    }
+                    let next = queue
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .pop_front();
+                    let Some((index, request)) = next else { break };
+                    let result = match request {
+                        Ok(request) => match wrap_params(&request) {
+                            Ok(params) => {
+                                core.execute_command_async("chat_completions".into(), Some(params))
+                                    .await
+                            }
+                            Err(e) => Err(e),
+                        },
+                        Err(e) => Err(e),
+                    };
+                    if tx.send((index, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut outcomes: Vec<Option<Result<String>>> = (0..total).map(|_| None).collect();
+        let mut counts = BatchProgress {
+            total,
+            succeeded: 0,
+            failed: 0,
+        };
+        client.touch();
+        while let Some((index, result)) = rx.recv().await {
+            client.touch();
+            if result.is_ok() {
+                counts.succeeded += 1;
+            } else {
+                counts.failed += 1;
+            }
+            outcomes[index] = Some(result);
+            if let Some(callback) = progress.as_mut() {
+                callback(counts);
+            }
+        }
+        (custom_ids, outcomes)
+    }
+}
+
+fn wrap_params(request: &Value) -> Result<Value> {
+    Ok(json!({
+        "Params": {
+            "OpenAICreateRequest": serde_json::to_string(request)?
+        }
+    }))
+}
+
+fn cancelled() -> FoundryLocalError {
+    FoundryLocalError::CommandExecution {
+        reason: "Operation cancelled".into(),
+    }
+}
+
+/// The `custom_id` and request body of a batch input line.
+fn parse_input_line(line: &str) -> (Option<String>, Result<Value>) {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => return (None, Err(e.into())),
+    };
+    let custom_id = value
+        .get("custom_id")
+        .and_then(Value::as_str)
+        .map(str::to_owned);
+    let invalid = |reason: String| Err(FoundryLocalError::Validation { reason });
+
+    let body = match (
+        value.get("method").and_then(Value::as_str),
+        value.get("url").and_then(Value::as_str),
+        value.get("body"),
+    ) {
+        (Some(method), _, _) if !method.eq_ignore_ascii_case("POST") => {
+            invalid(format!("unsupported batch method '{method}'"))
+        }
+        (_, Some(url), _) if url != CHAT_COMPLETIONS_URL => invalid(format!(
+            "unsupported batch url '{url}'; only {CHAT_COMPLETIONS_URL} is supported"
+        )),
+        (_, _, Some(body)) if body.is_object() => Ok(body.clone()),
+        _ => invalid("batch line has no request body object".into()),
+    };
+    (custom_id, body)
+}
+
+fn error_code(error: &FoundryLocalError) -> &'static str {
+    match error {
+        FoundryLocalError::Validation { .. } | FoundryLocalError::Serialization(_) => {
+            "invalid_request"
+        }
+        _ => "server_error",
+    }
+}
+
+/// A batch output line for the request at `index`.
+fn output_line(index: usize, custom_id: &str, outcome: Option<Result<Value>>) -> Value {
+    let (response, error) = match outcome {
+        Some(Ok(body)) => (json!({ "status_code": 200, "body": body }), Value::Null),
+        Some(Err(e)) => (
+            Value::Null,
+            json!({ "code": error_code(&e), "message": e.to_string() }),
+        ),
+        None => (
+            Value::Null,
+            json!({ "code": "cancelled", "message": cancelled().to_string() }),
+        ),
+    };
+    json!({
+        "id": format!("batch_req_{index}"),
+        "custom_id": custom_id,
+        "response": response,
+        "error": error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_input_lines() {
+        let (id, body) = parse_input_line(
+            r#"{"custom_id": "a", "method": "POST", "url": "/v1/chat/completions",
+                "body": {"messages": [{"role": "user", "content": "hi"}]}}"#,
+        );
+        assert_eq!(id.as_deref(), Some("a"));
+        assert_eq!(
+            body.unwrap(),
+            json!({ "messages": [{ "role": "user", "content": "hi" }] })
+        );
+
+        let (id, body) =
+            parse_input_line(r#"{"custom_id": "b", "url": "/v1/embeddings", "body": {}}"#);
+        assert_eq!(id.as_deref(), Some("b"));
+        assert!(matches!(body, Err(FoundryLocalError::Validation { .. })));
+
+        let (id, body) = parse_input_line("{not json");
+        assert_eq!(id, None);
+        assert!(matches!(body, Err(FoundryLocalError::Serialization(_))));
+    }
+
+    #[test]
+    fn formats_output_lines() {
+        assert_eq!(
+            output_line(0, "a", Some(Ok(json!({ "id": "chatcmpl-1" })))),
+            json!({
+                "id": "batch_req_0",
+                "custom_id": "a",
+                "response": { "status_code": 200, "body": { "id": "chatcmpl-1" } },
+                "error": null
+            })
+        );
+
+        let invalid = FoundryLocalError::Validation {
+            reason: "no body".into(),
+        };
+        let line = output_line(1, "b", Some(Err(invalid)));
+        assert_eq!(line["response"], Value::Null);
+        assert_eq!(line["error"]["code"], "invalid_request");
+
+        assert_eq!(output_line(2, "c", None)["error"]["code"], "cancelled");
+    }
+}
@@ -1,19 +1,29 @@
 //! OpenAI-compatible chat completions client.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use async_openai::types::chat::{
-    ChatCompletionRequestMessage, ChatCompletionTools, CreateChatCompletionResponse,
-    CreateChatCompletionStreamResponse,
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage, ChatCompletionTools,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
 };
 use serde_json::{json, Value};
 
 use crate::detail::core_interop::CoreInterop;
+use crate::detail::UsageTracker;
 use crate::error::{FoundryLocalError, Result};
 use crate::types::{ChatResponseFormat, ChatToolChoice};
 
+use super::image_input::check_image_input;
 use super::json_stream::JsonStream;
+use super::reasoning::{
+    split_response, ReasoningCompletion, ReasoningFilter, ReasoningMode, ReasoningStream,
+};
+use super::tool_registry::{ToolRegistry, ToolRun};
+
+/// Largest `top_logprobs` the OpenAI API accepts.
+const MAX_TOP_LOGPROBS: u8 = 20;
 
 /// Tuning knobs for chat completion requests.
 ///
@@ -24,10 +34,14 @@ use super::json_stream::JsonStream;
 ///     .temperature(0.7)
 ///     .max_tokens(256);
 /// ```
+///
+/// The same setters on [`ChatRequest`] override a client's settings for a
+/// single request.
 #[derive(Debug, Clone, Default)]
 pub struct ChatClientSettings {
     frequency_penalty: Option<f64>,
     max_tokens: Option<u32>,
+    max_completion_tokens: Option<u32>,
     n: Option<u32>,
     temperature: Option<f64>,
     presence_penalty: Option<f64>,
@@ -36,10 +50,39 @@ pub struct ChatClientSettings {
     random_seed: Option<u64>,
     response_format: Option<ChatResponseFormat>,
     tool_choice: Option<ChatToolChoice>,
+    parallel_tool_calls: Option<bool>,
+    stop: Option<Vec<String>>,
+    logprobs: Option<bool>,
+    top_logprobs: Option<u8>,
+    logit_bias: BTreeMap<u32, i32>,
+    user: Option<String>,
+    include_stream_usage: Option<bool>,
+    metadata: BTreeMap<String, String>,
+    reasoning: ReasoningMode,
+    max_reasoning_chunks: Option<u32>,
 }
 
 impl ChatClientSettings {
-    fn serialize(&self) -> Value {
+    /// Check values the service would reject with a less helpful error.
+    fn validate(&self) -> Result<()> {
+        if let Some(n) = self.top_logprobs {
+            if n > MAX_TOP_LOGPROBS {
+                return Err(FoundryLocalError::Validation {
+                    reason: format!("top_logprobs must be at most {MAX_TOP_LOGPROBS}, got {n}"),
+                });
+            }
+        }
+        if let Some((token, bias)) = self.logit_bias.iter().find(|(_, b)| b.abs() > 100) {
+            return Err(FoundryLocalError::Validation {
+                reason: format!(
+                    "logit_bias for token {token} must be between -100 and 100, got {bias}"
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, stream: bool) -> Value {
         let mut map = serde_json::Map::new();
 
         if let Some(v) = self.frequency_penalty {
@@ -48,6 +91,9 @@ impl ChatClientSettings {
         if let Some(v) = self.max_tokens {
             map.insert("max_tokens".into(), json!(v));
         }
+        if let Some(v) = self.max_completion_tokens {
+            map.insert("max_completion_tokens".into(), json!(v));
+        }
         if let Some(v) = self.n {
             map.insert("n".into(), json!(v));
         }
@@ -60,6 +106,28 @@ impl ChatClientSettings {
         if let Some(v) = self.top_p {
             map.insert("top_p".into(), json!(v));
         }
+        if let Some(ref v) = self.stop {
+            map.insert("stop".into(), json!(v));
+        }
+        if let Some(v) = self.logprobs {
+            map.insert("logprobs".into(), json!(v));
+        }
+        if let Some(v) = self.top_logprobs {
+            map.insert("top_logprobs".into(), json!(v));
+        }
+        if !self.logit_bias.is_empty() {
+            map.insert("logit_bias".into(), json!(self.logit_bias));
+        }
+        if let Some(v) = self.parallel_tool_calls {
+            map.insert("parallel_tool_calls".into(), json!(v));
+        }
+        if let Some(ref v) = self.user {
+            map.insert("user".into(), json!(v));
+        }
+        // The service rejects stream_options on non-streaming requests.
+        if let (true, Some(v)) = (stream, self.include_stream_usage) {
+            map.insert("stream_options".into(), json!({ "include_usage": v }));
+        }
 
         if let Some(ref rf) = self.response_format {
             let mut rf_map = serde_json::Map::new();
@@ -104,7 +172,11 @@ impl ChatClientSettings {
 
         // Foundry-specific metadata for settings that don't map directly to
         // the OpenAI spec.
-        let mut metadata: HashMap<String, String> = HashMap::new();
+        let mut metadata: HashMap<String, String> = self
+            .metadata
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
         if let Some(k) = self.top_k {
             metadata.insert("top_k".into(), k.to_string());
         }
@@ -119,6 +191,146 @@ impl ChatClientSettings {
     }
 }
 
+/// Chainable setters for every [`ChatClientSettings`] field, shared by
+/// [`ChatClient`] and [`ChatRequest`].
+macro_rules! settings_setters {
+    () => {
+        /// Set the frequency penalty.
+        pub fn frequency_penalty(mut self, v: f64) -> Self {
+            self.settings.frequency_penalty = Some(v);
+            self
+        }
+
+        /// Set the maximum number of tokens to generate.
+        pub fn max_tokens(mut self, v: u32) -> Self {
+            self.settings.max_tokens = Some(v);
+            self
+        }
+
+        /// Set the maximum number of tokens to generate, the successor of
+        /// `max_tokens` in the OpenAI API.
+        pub fn max_completion_tokens(mut self, v: u32) -> Self {
+            self.settings.max_completion_tokens = Some(v);
+            self
+        }
+
+        /// Set the number of completions to generate.
+        pub fn n(mut self, v: u32) -> Self {
+            self.settings.n = Some(v);
+            self
+        }
+
+        /// Set the sampling temperature.
+        pub fn temperature(mut self, v: f64) -> Self {
+            self.settings.temperature = Some(v);
+            self
+        }
+
+        /// Set the presence penalty.
+        pub fn presence_penalty(mut self, v: f64) -> Self {
+            self.settings.presence_penalty = Some(v);
+            self
+        }
+
+        /// Set the nucleus sampling probability.
+        pub fn top_p(mut self, v: f64) -> Self {
+            self.settings.top_p = Some(v);
+            self
+        }
+
+        /// Set the top-k sampling parameter (Foundry extension).
+        pub fn top_k(mut self, v: u32) -> Self {
+            self.settings.top_k = Some(v);
+            self
+        }
+
+        /// Set the random seed for reproducible results (Foundry extension).
+        pub fn random_seed(mut self, v: u64) -> Self {
+            self.settings.random_seed = Some(v);
+            self
+        }
+
+        /// Set the desired response format.
+        pub fn response_format(mut self, v: ChatResponseFormat) -> Self {
+            self.settings.response_format = Some(v);
+            self
+        }
+
+        /// Set the tool choice strategy.
+        pub fn tool_choice(mut self, v: ChatToolChoice) -> Self {
+            self.settings.tool_choice = Some(v);
+            self
+        }
+
+        /// Allow or forbid several tool calls in one reply.
+        pub fn parallel_tool_calls(mut self, v: bool) -> Self {
+            self.settings.parallel_tool_calls = Some(v);
+            self
+        }
+
+        /// Set the sequences that end generation; they are not included in
+        /// the reply.
+        pub fn stop(mut self, sequences: impl IntoIterator<Item = impl Into<String>>) -> Self {
+            self.settings.stop = Some(sequences.into_iter().map(Into::into).collect());
+            self
+        }
+
+        /// Return the log probability of each generated token.
+        pub fn logprobs(mut self, v: bool) -> Self {
+            self.settings.logprobs = Some(v);
+            self
+        }
+
+        /// Also return the `v` most likely alternatives (at most 20) for each
+        /// token; requires [`logprobs`](Self::logprobs).
+        pub fn top_logprobs(mut self, v: u8) -> Self {
+            self.settings.top_logprobs = Some(v);
+            self
+        }
+
+        /// Add `bias` (-100 to 100) to the logit of `token_id`; -100 bans the
+        /// token and 100 forces it.
+        pub fn logit_bias(mut self, token_id: u32, bias: i32) -> Self {
+            self.settings.logit_bias.insert(token_id, bias);
+            self
+        }
+
+        /// Set the end-user identifier sent with requests.
+        pub fn user(mut self, v: impl Into<String>) -> Self {
+            self.settings.user = Some(v.into());
+            self
+        }
+
+        /// Ask streaming requests for a final chunk carrying token usage.
+        pub fn include_stream_usage(mut self, v: bool) -> Self {
+            self.settings.include_stream_usage = Some(v);
+            self
+        }
+
+        /// Add an entry to the request `metadata`, which also carries the
+        /// Foundry extensions `top_k` and `random_seed`.
+        pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+            self.settings.metadata.insert(key.into(), value.into());
+            self
+        }
+
+        /// Choose whether the `<think>` block of reasoning models stays in
+        /// `content`, is returned separately, or is hidden.
+        pub fn reasoning(mut self, mode: ReasoningMode) -> Self {
+            self.settings.reasoning = mode;
+            self
+        }
+
+        /// Stop generating once more than `v` streamed chunks carry
+        /// thinking.  The local service usually sends one chunk per token.
+        /// The reply then ends with finish reason `length` and no answer.
+        pub fn max_reasoning_chunks(mut self, v: u32) -> Self {
+            self.settings.max_reasoning_chunks = Some(v);
+            self
+        }
+    };
+}
+
 /// A stream of [`CreateChatCompletionStreamResponse`] chunks.
 ///
 /// Returned by [`ChatClient::complete_streaming_chat`].
@@ -129,6 +341,10 @@ pub struct ChatClient {
     model_id: String,
     core: Arc<CoreInterop>,
     settings: ChatClientSettings,
+    max_json_attempts: u32,
+    usage: Option<UsageTracker>,
+    input_modalities: Option<String>,
+    capabilities: Option<String>,
 }
 
 impl ChatClient {
@@ -137,67 +353,94 @@ impl ChatClient {
             model_id: model_id.to_owned(),
             core,
             settings: ChatClientSettings::default(),
+            max_json_attempts: 1,
+            usage: None,
+            input_modalities: None,
+            capabilities: None,
         }
     }
 
-    /// Set the frequency penalty.
-    pub fn frequency_penalty(mut self, v: f64) -> Self {
-        self.settings.frequency_penalty = Some(v);
-        self
+    /// The configured `max_tokens`, reserved for the reply by
+    /// [`ChatSession`](super::ChatSession).
+    pub(crate) fn max_tokens_setting(&self) -> Option<u32> {
+        self.settings
+            .max_completion_tokens
+            .or(self.settings.max_tokens)
     }
 
-    /// Set the maximum number of tokens to generate.
-    pub fn max_tokens(mut self, v: u32) -> Self {
-        self.settings.max_tokens = Some(v);
-        self
+    pub(super) fn response_format_setting(&self) -> Option<&ChatResponseFormat> {
+        self.settings.response_format.as_ref()
     }
 
-    /// Set the number of completions to generate.
-    pub fn n(mut self, v: u32) -> Self {
-        self.settings.n = Some(v);
-        self
+    pub(super) fn max_json_attempts_setting(&self) -> u32 {
+        self.max_json_attempts
     }
 
-    /// Set the sampling temperature.
-    pub fn temperature(mut self, v: f64) -> Self {
-        self.settings.temperature = Some(v);
-        self
+    pub(super) fn reasoning_setting(&self) -> ReasoningMode {
+        self.settings.reasoning
     }
 
-    /// Set the presence penalty.
-    pub fn presence_penalty(mut self, v: f64) -> Self {
-        self.settings.presence_penalty = Some(v);
-        self
+    /// Whether the model's prompt template may open the `<think>` block,
+    /// which is assumed for models with the `reasoning` capability.
+    pub(super) fn template_opens_thinking(&self) -> bool {
+        self.capabilities.as_deref().is_some_and(|c| {
+            c.split(',')
+                .any(|c| c.trim().eq_ignore_ascii_case("reasoning"))
+        })
     }
 
-    /// Set the nucleus sampling probability.
-    pub fn top_p(mut self, v: f64) -> Self {
-        self.settings.top_p = Some(v);
+    /// Count requests from this client as use of the model.
+    pub(crate) fn with_usage_tracker(mut self, tracker: UsageTracker) -> Self {
+        self.usage = Some(tracker);
         self
     }
 
-    /// Set the top-k sampling parameter (Foundry extension).
-    pub fn top_k(mut self, v: u32) -> Self {
-        self.settings.top_k = Some(v);
+    /// Reject image content unless `modalities` include `image`.
+    pub(crate) fn with_input_modalities(mut self, modalities: Option<String>) -> Self {
+        self.input_modalities = modalities;
         self
     }
 
-    /// Set the random seed for reproducible results (Foundry extension).
-    pub fn random_seed(mut self, v: u64) -> Self {
-        self.settings.random_seed = Some(v);
+    /// Treat replies as possibly starting inside a `<think>` block opened
+    /// by the template when `capabilities` include `reasoning`.
+    pub(crate) fn with_capabilities(mut self, capabilities: Option<String>) -> Self {
+        self.capabilities = capabilities;
         self
     }
 
-    /// Set the desired response format.
-    pub fn response_format(mut self, v: ChatResponseFormat) -> Self {
-        self.settings.response_format = Some(v);
+    settings_setters!();
+
+    /// Make at most `attempts` requests in
+    /// [`complete_json`](Self::complete_json),
+    /// [`complete_with_schema`](Self::complete_with_schema) and
+    /// `complete_structured`.
+    ///
+    /// A reply that is not valid JSON, does not match the schema or does not
+    /// deserialize is sent back to the model with the errors, asking for a
+    /// corrected reply.  Defaults to 1, which never retries.
+    pub fn max_json_attempts(mut self, attempts: u32) -> Self {
+        self.max_json_attempts = attempts.max(1);
         self
     }
 
-    /// Set the tool choice strategy.
-    pub fn tool_choice(mut self, v: ChatToolChoice) -> Self {
-        self.settings.tool_choice = Some(v);
-        self
+    /// Start a single request for `messages` whose settings can be
+    /// overridden without changing the client.
+    ///
+    /// ```ignore
+    /// let response = client
+    ///     .request(&messages)
+    ///     .temperature(0.0)
+    ///     .stop(["\n\n"])
+    ///     .send()
+    ///     .await?;
+    /// ```
+    pub fn request<'a>(&'a self, messages: &'a [ChatCompletionRequestMessage]) -> ChatRequest<'a> {
+        ChatRequest {
+            client: self,
+            messages,
+            tools: None,
+            settings: self.settings.clone(),
+        }
     }
 
     /// Perform a non-streaming chat completion.
@@ -205,6 +448,159 @@ impl ChatClient {
         &self,
         messages: &[ChatCompletionRequestMessage],
         tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<CreateChatCompletionResponse> {
+        self.complete_chat_with(&self.settings, messages, tools)
+            .await
+    }
+
+    /// Answer `messages`, calling the tools in `registry` until the model
+    /// replies without requesting any.
+    ///
+    /// Each round sends the conversation with the registry's tool
+    /// definitions, runs the requested calls concurrently and appends the
+    /// assistant message and the tool results.  Unknown tools, invalid
+    /// arguments, handler errors and timeouts are reported to the model as
+    /// tool results so it can recover.  A `Required` or `Function`
+    /// [`tool_choice`](Self::tool_choice) applies to the first request only;
+    /// later rounds use `Auto` so the model can finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::ModelOperation`] if the model still
+    /// requests tools after the registry's
+    /// [`max_iterations`](ToolRegistry::max_iterations) rounds.
+    pub async fn run_with_tools(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        registry: &ToolRegistry,
+    ) -> Result<ToolRun> {
+        let tools = registry.definitions();
+        let max_iterations = registry.max_iterations_setting();
+        let mut settings = self.settings.clone();
+        let mut transcript = messages.to_vec();
+        let mut iterations = 0;
+
+        loop {
+            let response = self
+                .complete_chat_with(&settings, &transcript, Some(&tools))
+                .await?;
+            let Some(choice) = response.choices.first() else {
+                return Ok(ToolRun {
+                    response,
+                    messages: transcript,
+                    iterations,
+                });
+            };
+            let calls = choice.message.tool_calls.clone().unwrap_or_default();
+            transcript.push(
+                ChatCompletionRequestAssistantMessage {
+                    content: choice.message.content.clone().map(Into::into),
+                    tool_calls: (!calls.is_empty()).then(|| calls.clone()),
+                    ..Default::default()
+                }
+                .into(),
+            );
+            if calls.is_empty() {
+                return Ok(ToolRun {
+                    response,
+                    messages: transcript,
+                    iterations,
+                });
+            }
+            if iterations == max_iterations {
+                return Err(FoundryLocalError::ModelOperation {
+                    reason: format!(
+                        "model '{}' still requested tools after {max_iterations} rounds",
+                        self.model_id
+                    ),
+                });
+            }
+
+            iterations += 1;
+            transcript.extend(registry.invoke(&calls).await.into_iter().map(Into::into));
+            if matches!(
+                settings.tool_choice,
+                Some(ChatToolChoice::Required | ChatToolChoice::Function(_))
+            ) {
+                settings.tool_choice = Some(ChatToolChoice::Auto);
+            }
+        }
+    }
+
+    /// Complete `messages` without tools, using `format` in place of the
+    /// configured response format.
+    pub(super) async fn complete_chat_with_format(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        format: ChatResponseFormat,
+    ) -> Result<CreateChatCompletionResponse> {
+        let mut settings = self.settings.clone();
+        settings.response_format = Some(format);
+        self.complete_chat_with(&settings, messages, None).await
+    }
+
+    async fn complete_chat_with(
+        &self,
+        settings: &ChatClientSettings,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<CreateChatCompletionResponse> {
+        if settings.max_reasoning_chunks.is_some() {
+            // The budget is enforced while streaming.
+            return self
+                .complete_streaming_chat_with(settings, messages, tools)
+                .await?
+                .collect()
+                .await;
+        }
+        let mut response = self.send_chat(settings, messages, tools).await?;
+        if settings.reasoning != ReasoningMode::Inline {
+            split_response(&mut response, self.template_opens_thinking());
+        }
+        Ok(response)
+    }
+
+    /// Answer `messages`, returning the thinking of a reasoning model
+    /// separately from the answer in `content`.
+    ///
+    /// The thinking is the `<think>…</think>` block at the start of the
+    /// reply; it is `None` for replies without one and with
+    /// [`ReasoningMode::Hide`].
+    pub async fn complete_with_reasoning(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<ReasoningCompletion> {
+        self.complete_with_reasoning_with(&self.settings, messages, tools)
+            .await
+    }
+
+    async fn complete_with_reasoning_with(
+        &self,
+        settings: &ChatClientSettings,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<ReasoningCompletion> {
+        if settings.max_reasoning_chunks.is_some() {
+            return self
+                .stream_with_reasoning_with(settings, messages, tools)
+                .await?
+                .collect()
+                .await;
+        }
+        let mut response = self.send_chat(settings, messages, tools).await?;
+        let reasoning = split_response(&mut response, self.template_opens_thinking());
+        Ok(ReasoningCompletion {
+            reasoning: reasoning.filter(|_| settings.reasoning != ReasoningMode::Hide),
+            response,
+        })
+    }
+
+    async fn send_chat(
+        &self,
+        settings: &ChatClientSettings,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
     ) -> Result<CreateChatCompletionResponse> {
         if messages.is_empty() {
             return Err(FoundryLocalError::Validation {
@@ -212,7 +608,8 @@ impl ChatClient {
             });
         }
 
-        let request = self.build_request(messages, tools, false)?;
+        let request = self.build_request(settings, messages, tools, false)?;
+        self.touch();
         let params = json!({
             "Params": {
                 "OpenAICreateRequest": serde_json::to_string(&request)?
@@ -235,6 +632,73 @@ impl ChatClient {
         &self,
         messages: &[ChatCompletionRequestMessage],
         tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<ChatCompletionStream> {
+        self.complete_streaming_chat_with(&self.settings, messages, tools)
+            .await
+    }
+
+    async fn complete_streaming_chat_with(
+        &self,
+        settings: &ChatClientSettings,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<ChatCompletionStream> {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let stream = self
+            .open_stream(settings, messages, tools, Arc::clone(&cancel_flag))
+            .await?;
+        if settings.reasoning == ReasoningMode::Inline && settings.max_reasoning_chunks.is_none() {
+            return Ok(stream);
+        }
+        let filter = ReasoningFilter::new(
+            settings.reasoning,
+            settings.max_reasoning_chunks,
+            self.template_opens_thinking(),
+            cancel_flag,
+        );
+        Ok(stream.with_filter(filter.into_stream_filter()))
+    }
+
+    /// Stream a chat completion with the thinking of a reasoning model and
+    /// the answer as separate [`ReasoningEvent`](super::ReasoningEvent)s.
+    pub async fn stream_with_reasoning(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<ReasoningStream> {
+        self.stream_with_reasoning_with(&self.settings, messages, tools)
+            .await
+    }
+
+    async fn stream_with_reasoning_with(
+        &self,
+        settings: &ChatClientSettings,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
+    ) -> Result<ReasoningStream> {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let stream = self
+            .open_stream(settings, messages, tools, Arc::clone(&cancel_flag))
+            .await?;
+        let mode = match settings.reasoning {
+            ReasoningMode::Inline => ReasoningMode::Separate,
+            mode => mode,
+        };
+        let filter = ReasoningFilter::new(
+            mode,
+            settings.max_reasoning_chunks,
+            self.template_opens_thinking(),
+            cancel_flag,
+        );
+        Ok(ReasoningStream::new(stream, filter))
+    }
+
+    async fn open_stream(
+        &self,
+        settings: &ChatClientSettings,
+        messages: &[ChatCompletionRequestMessage],
+        tools: Option<&[ChatCompletionTools]>,
+        cancel_flag: Arc<AtomicBool>,
     ) -> Result<ChatCompletionStream> {
         if messages.is_empty() {
             return Err(FoundryLocalError::Validation {
@@ -242,7 +706,8 @@ impl ChatClient {
             });
         }
 
-        let request = self.build_request(messages, tools, true)?;
+        let request = self.build_request(settings, messages, tools, true)?;
+        self.touch();
         let params = json!({
             "Params": {
                 "OpenAICreateRequest": serde_json::to_string(&request)?
@@ -251,35 +716,225 @@ impl ChatClient {
 
         let rx = self
             .core
-            .execute_command_streaming_channel("chat_completions".into(), Some(params))
+            .execute_command_streaming_channel_cancellable(
+                "chat_completions".into(),
+                Some(params),
+                cancel_flag,
+            )
             .await?;
 
         Ok(ChatCompletionStream::new(rx))
     }
 
+    /// The request for one conversation of a batch.
+    pub(super) fn batch_request(&self, messages: &[ChatCompletionRequestMessage]) -> Result<Value> {
+        if messages.is_empty() {
+            return Err(FoundryLocalError::Validation {
+                reason: "messages must be a non-empty array".into(),
+            });
+        }
+        self.build_request(&self.settings, messages, None, false)
+    }
+
+    /// The request for a batch input `body`, whose fields override this
+    /// client's settings.
+    pub(super) fn batch_body_request(&self, body: &Value) -> Result<Value> {
+        let has_messages = body
+            .get("messages")
+            .and_then(Value::as_array)
+            .is_some_and(|m| !m.is_empty());
+        if !has_messages {
+            return Err(FoundryLocalError::Validation {
+                reason: "messages must be a non-empty array".into(),
+            });
+        }
+        self.settings.validate()?;
+        let mut map = match self.settings.serialize(false) {
+            Value::Object(m) => m,
+            _ => serde_json::Map::new(),
+        };
+        if let Value::Object(fields) = body {
+            map.extend(fields.clone());
+        }
+        map.remove("stream");
+        map.insert("model".into(), json!(self.model_id));
+        let request = Value::Object(map);
+        check_image_input(&self.model_id, self.input_modalities.as_deref(), &request)?;
+        Ok(request)
+    }
+
     fn build_request(
         &self,
+        settings: &ChatClientSettings,
         messages: &[ChatCompletionRequestMessage],
         tools: Option<&[ChatCompletionTools]>,
         stream: bool,
     ) -> Result<Value> {
-        let settings_value = self.settings.serialize();
-        let mut map = match settings_value {
-            Value::Object(m) => m,
-            _ => serde_json::Map::new(),
-        };
+        let request = build_request(&self.model_id, settings, messages, tools, stream)?;
+        check_image_input(&self.model_id, self.input_modalities.as_deref(), &request)?;
+        Ok(request)
+    }
 
-        map.insert("model".into(), json!(self.model_id));
-        map.insert("messages".into(), serde_json::to_value(messages)?);
+    pub(super) fn core(&self) -> Arc<CoreInterop> {
+        Arc::clone(&self.core)
+    }
 
-        if stream {
-            map.insert("stream".into(), json!(true));
+    pub(super) fn touch(&self) {
+        if let Some(usage) = &self.usage {
+            usage.touch();
         }
+    }
+}
 
-        if let Some(t) = tools {
-            map.insert("tools".into(), serde_json::to_value(t)?);
-        }
+/// A single chat completion request with its own settings.
+///
+/// Created by [`ChatClient::request`] with a copy of the client's settings;
+/// the setters change this request only.
+pub struct ChatRequest<'a> {
+    client: &'a ChatClient,
+    messages: &'a [ChatCompletionRequestMessage],
+    tools: Option<&'a [ChatCompletionTools]>,
+    settings: ChatClientSettings,
+}
+
+impl<'a> ChatRequest<'a> {
+    settings_setters!();
+
+    /// Offer `tools` to the model.
+    pub fn tools(mut self, tools: &'a [ChatCompletionTools]) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Perform the request as a non-streaming chat completion.
+    pub async fn send(self) -> Result<CreateChatCompletionResponse> {
+        self.client
+            .complete_chat_with(&self.settings, self.messages, self.tools)
+            .await
+    }
 
-        Ok(Value::Object(map))
+    /// Perform the request as a streaming chat completion.
+    pub async fn stream(self) -> Result<ChatCompletionStream> {
+        self.client
+            .complete_streaming_chat_with(&self.settings, self.messages, self.tools)
+            .await
+    }
+
+    /// Perform the request, returning the thinking separately; see
+    /// [`ChatClient::complete_with_reasoning`].
+    pub async fn send_with_reasoning(self) -> Result<ReasoningCompletion> {
+        self.client
+            .complete_with_reasoning_with(&self.settings, self.messages, self.tools)
+            .await
+    }
+
+    /// Stream the request with thinking and answer as separate events; see
+    /// [`ChatClient::stream_with_reasoning`].
+    pub async fn stream_with_reasoning(self) -> Result<ReasoningStream> {
+        self.client
+            .stream_with_reasoning_with(&self.settings, self.messages, self.tools)
+            .await
+    }
+}
+
+fn build_request(
+    model_id: &str,
+    settings: &ChatClientSettings,
+    messages: &[ChatCompletionRequestMessage],
+    tools: Option<&[ChatCompletionTools]>,
+    stream: bool,
+) -> Result<Value> {
+    settings.validate()?;
+    let settings_value = settings.serialize(stream);
+    let mut map = match settings_value {
+        Value::Object(m) => m,
+        _ => serde_json::Map::new(),
+    };
+
+    map.insert("model".into(), json!(model_id));
+    map.insert("messages".into(), serde_json::to_value(messages)?);
+
+    if stream {
+        map.insert("stream".into(), json!(true));
+    }
+
+    if let Some(t) = tools {
+        map.insert("tools".into(), serde_json::to_value(t)?);
+    }
+
+    Ok(Value::Object(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_openai::types::chat::ChatCompletionRequestUserMessage;
+
+    use super::*;
+
+    fn request(settings: &ChatClientSettings, stream: bool) -> Result<Value> {
+        let messages = [ChatCompletionRequestUserMessage::from("hi").into()];
+        build_request("model", settings, &messages, None, stream)
+    }
+
+    #[test]
+    fn serializes_openai_parameters_and_foundry_metadata() {
+        let settings = ChatClientSettings {
+            max_completion_tokens: Some(64),
+            stop: Some(vec!["\n".into()]),
+            logprobs: Some(true),
+            top_logprobs: Some(3),
+            logit_bias: BTreeMap::from([(50256, -100)]),
+            parallel_tool_calls: Some(false),
+            user: Some("user-1".into()),
+            include_stream_usage: Some(true),
+            top_k: Some(40),
+            metadata: BTreeMap::from([("trace".into(), "abc".into())]),
+            ..Default::default()
+        };
+
+        let body = request(&settings, true).unwrap();
+        assert_eq!(body["max_completion_tokens"], json!(64));
+        assert_eq!(body["stop"], json!(["\n"]));
+        assert_eq!(body["logprobs"], json!(true));
+        assert_eq!(body["top_logprobs"], json!(3));
+        assert_eq!(body["logit_bias"], json!({ "50256": -100 }));
+        assert_eq!(body["parallel_tool_calls"], json!(false));
+        assert_eq!(body["user"], json!("user-1"));
+        assert_eq!(body["stream_options"], json!({ "include_usage": true }));
+        assert_eq!(body["metadata"], json!({ "trace": "abc", "top_k": "40" }));
+
+        let body = request(&settings, false).unwrap();
+        assert!(body.get("stream_options").is_none());
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let settings = ChatClientSettings {
+            top_logprobs: Some(21),
+            ..Default::default()
+        };
+        let err = request(&settings, false).unwrap_err();
+        assert!(
+            err.to_string().contains("top_logprobs must be at most 20"),
+            "{err}"
+        );
+
+        let settings = ChatClientSettings {
+            logit_bias: BTreeMap::from([(7, 101)]),
+            ..Default::default()
+        };
+        let err = request(&settings, false).unwrap_err();
+        assert!(err.to_string().contains("token 7"), "{err}");
+    }
+    #[test]
+    fn sends_lark_grammars_unchecked() {
+        let grammar = "start: _sep{WORD, \",\"} <|end|>\n_sep{x, sep}: x (sep x)*\n";
+        let settings = ChatClientSettings {
+            response_format: Some(ChatResponseFormat::LarkGrammar(grammar.into())),
+            ..Default::default()
+        };
+        let body = request(&settings, false).unwrap();
+        assert_eq!(body["response_format"]["larkGrammar"], json!(grammar));
     }
 }
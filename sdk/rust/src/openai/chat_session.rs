@@ -0,0 +1,770 @@
+//! Multi-turn chat session that owns its history and keeps it within the
+//! model's context window.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_openai::types::chat::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage, ChatCompletionTools,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
+};
+use serde_json::Value;
+
+use super::chat_client::{ChatClient, ChatCompletionStream};
+use super::stream_accumulator::ChatCompletionAccumulator;
+use crate::error::{FoundryLocalError, Result};
+
+/// Prefix of the system message that [`Summarize`] inserts.
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation: ";
+
+/// Counts the tokens a message occupies in the prompt.
+pub type TokenCounter = dyn Fn(&ChatCompletionRequestMessage) -> usize + Send + Sync;
+
+/// Future returned by [`TrimStrategy::trim`].
+pub type TrimFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// Default token estimate: a quarter of the message's JSON length, which
+/// overestimates for English text so the budget errs on the safe side.
+fn estimate_tokens(message: &ChatCompletionRequestMessage) -> usize {
+    serde_json::to_string(message).map_or(0, |s| (s.len() + 3) / 4)
+}
+
+/// A message in a [`ChatSession`]'s history.
+#[derive(Debug, Clone)]
+pub struct SessionMessage {
+    pub message: ChatCompletionRequestMessage,
+    /// Pinned messages are never removed by trimming.
+    pub pinned: bool,
+}
+
+/// Reduces a session's history until it fits the token budget.
+///
+/// Called before each request when the history is over budget.  Use the
+/// [`TrimContext`] helpers to remove or insert messages; if the history still
+/// does not fit afterwards the request fails.
+pub trait TrimStrategy: Send + Sync {
+    fn trim<'a>(&'a self, ctx: &'a mut TrimContext<'_>) -> TrimFuture<'a>;
+}
+
+/// The history being trimmed, handed to [`TrimStrategy::trim`].
+pub struct TrimContext<'a> {
+    messages: &'a mut Vec<SessionMessage>,
+    budget: usize,
+    client: &'a ChatClient,
+    counter: &'a TokenCounter,
+}
+
+impl TrimContext<'_> {
+    pub fn messages(&self) -> &[SessionMessage] {
+        self.messages
+    }
+
+    /// Tokens available for the prompt.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Tokens used by the whole history.
+    pub fn token_count(&self) -> usize {
+        total_tokens(self.messages, self.counter)
+    }
+
+    pub fn fits(&self) -> bool {
+        self.token_count() <= self.budget
+    }
+
+    pub fn count_tokens(&self, message: &ChatCompletionRequestMessage) -> usize {
+        (self.counter)(message)
+    }
+
+    /// The session's client, e.g. for summarizing.
+    pub fn client(&self) -> &ChatClient {
+        self.client
+    }
+
+    /// Whether `index` may be removed: it is not pinned, not a tool result
+    /// (those go with their tool call), and neither it nor only its tool
+    /// results are the newest messages.
+    pub fn is_removable(&self, index: usize) -> bool {
+        is_removable(self.messages, index)
+    }
+
+    /// Remove the message at `index` together with the tool results that
+    /// follow it.  Returns nothing when the message is not removable.
+    pub fn remove(&mut self, index: usize) -> Vec<ChatCompletionRequestMessage> {
+        remove_turn(self.messages, index)
+    }
+
+    pub fn insert(&mut self, index: usize, message: SessionMessage) {
+        self.messages.insert(index, message);
+    }
+}
+
+fn total_tokens(messages: &[SessionMessage], counter: &TokenCounter) -> usize {
+    messages.iter().map(|m| counter(&m.message)).sum()
+}
+
+fn is_removable(messages: &[SessionMessage], index: usize) -> bool {
+    index < messages.len()
+        && turn_end(messages, index) < messages.len()
+        && !messages[index].pinned
+        && !matches!(
+            messages[index].message,
+            ChatCompletionRequestMessage::Tool(_)
+        )
+}
+
+/// The index just past the message at `index` and the tool results that
+/// follow it.
+fn turn_end(messages: &[SessionMessage], index: usize) -> usize {
+    let mut end = index + 1;
+    while end < messages.len()
+        && matches!(messages[end].message, ChatCompletionRequestMessage::Tool(_))
+    {
+        end += 1;
+    }
+    end
+}
+
+fn remove_turn(
+    messages: &mut Vec<SessionMessage>,
+    index: usize,
+) -> Vec<ChatCompletionRequestMessage> {
+    if !is_removable(messages, index) {
+        return Vec::new();
+    }
+    let end = turn_end(messages, index);
+    messages.drain(index..end).map(|m| m.message).collect()
+}
+
+/// Remove the oldest removable messages not matched by `keep` until the
+/// history fits within `budget` tokens, returning what was removed.
+fn drop_oldest(
+    messages: &mut Vec<SessionMessage>,
+    counter: &TokenCounter,
+    budget: usize,
+    keep: impl Fn(&ChatCompletionRequestMessage) -> bool,
+) -> Vec<ChatCompletionRequestMessage> {
+    let mut removed = Vec::new();
+    let mut index = 0;
+    while total_tokens(messages, counter) > budget && index < messages.len() {
+        if is_removable(messages, index) && !keep(&messages[index].message) {
+            removed.extend(remove_turn(messages, index));
+        } else {
+            index += 1;
+        }
+    }
+    removed
+}
+
+fn is_system(message: &ChatCompletionRequestMessage) -> bool {
+    matches!(
+        message,
+        ChatCompletionRequestMessage::System(_) | ChatCompletionRequestMessage::Developer(_)
+    )
+}
+
+/// Drop the oldest unpinned messages, including system prompts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DropOldest;
+
+impl TrimStrategy for DropOldest {
+    fn trim<'a>(&'a self, ctx: &'a mut TrimContext<'_>) -> TrimFuture<'a> {
+        Box::pin(async move {
+            drop_oldest(ctx.messages, ctx.counter, ctx.budget, |_| false);
+            Ok(())
+        })
+    }
+}
+
+/// Drop the oldest unpinned messages but always keep system prompts.  The
+/// default strategy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepSystemPrompt;
+
+impl TrimStrategy for KeepSystemPrompt {
+    fn trim<'a>(&'a self, ctx: &'a mut TrimContext<'_>) -> TrimFuture<'a> {
+        Box::pin(async move {
+            drop_oldest(ctx.messages, ctx.counter, ctx.budget, is_system);
+            Ok(())
+        })
+    }
+}
+
+/// Replace the oldest messages with a model-written summary.
+///
+/// System prompts are kept.  An earlier summary is folded into the new one.
+/// If the summary itself does not fit, falls back to [`KeepSystemPrompt`].
+#[derive(Debug, Clone)]
+pub struct Summarize {
+    summary_tokens: usize,
+    instruction: String,
+}
+
+impl Default for Summarize {
+    fn default() -> Self {
+        Self {
+            summary_tokens: 256,
+            instruction: "Summarize the following conversation in a few sentences. Keep names, \
+                          facts, decisions and open questions."
+                .into(),
+        }
+    }
+}
+
+impl Summarize {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tokens reserved for the summary (default 256).
+    pub fn summary_tokens(mut self, tokens: usize) -> Self {
+        self.summary_tokens = tokens;
+        self
+    }
+
+    /// Instruction sent to the model with the messages to summarize.
+    pub fn instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.instruction = instruction.into();
+        self
+    }
+}
+
+impl TrimStrategy for Summarize {
+    fn trim<'a>(&'a self, ctx: &'a mut TrimContext<'_>) -> TrimFuture<'a> {
+        Box::pin(async move {
+            let target = ctx.budget.saturating_sub(self.summary_tokens);
+            let removed = drop_oldest(ctx.messages, ctx.counter, target, |m| {
+                is_system(m) && !is_summary(m)
+            });
+            if removed.is_empty() {
+                return Ok(());
+            }
+
+            let request: [ChatCompletionRequestMessage; 2] = [
+                ChatCompletionRequestSystemMessage::from(self.instruction.as_str()).into(),
+                ChatCompletionRequestUserMessage::from(transcript(&removed)).into(),
+            ];
+            let response = ctx.client.complete_chat(&request, None).await?;
+            let summary = response
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|c| c.message.content)
+                .unwrap_or_default();
+
+            if !summary.trim().is_empty() {
+                let at = ctx
+                    .messages
+                    .iter()
+                    .take_while(|m| is_system(&m.message))
+                    .count();
+                ctx.insert(
+                    at,
+                    SessionMessage {
+                        message: ChatCompletionRequestSystemMessage::from(format!(
+                            "{SUMMARY_PREFIX}{}",
+                            summary.trim()
+                        ))
+                        .into(),
+                        pinned: false,
+                    },
+                );
+            }
+            if !ctx.fits() {
+                drop_oldest(ctx.messages, ctx.counter, ctx.budget, is_system);
+            }
+            Ok(())
+        })
+    }
+}
+
+fn is_summary(message: &ChatCompletionRequestMessage) -> bool {
+    matches!(message, ChatCompletionRequestMessage::System(_))
+        && message_text(message).starts_with(SUMMARY_PREFIX)
+}
+
+/// Render messages as `role: text` lines for the summarizer.
+fn transcript(messages: &[ChatCompletionRequestMessage]) -> String {
+    messages
+        .iter()
+        .map(|m| {
+            let role = serde_json::to_value(m)
+                .ok()
+                .and_then(|v| v.get("role").and_then(Value::as_str).map(str::to_owned))
+                .unwrap_or_default();
+            format!("{role}: {}", message_text(m))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The text content of a message, joining the text parts of multi-part
+/// content.
+fn message_text(message: &ChatCompletionRequestMessage) -> String {
+    let Ok(value) = serde_json::to_value(message) else {
+        return String::new();
+    };
+    match value.get("content") {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    }
+}
+
+/// A conversation with a chat model that owns its message history.
+///
+/// Each [`send`](ChatSession::send) appends the user message, trims the
+/// history to fit the context window, and appends the assistant's reply.
+/// The token budget is the model's context length minus the client's
+/// `max_tokens`; without a known context length no trimming happens.
+///
+/// ```ignore
+/// let mut session = model
+///     .create_chat_session()
+///     .configure_client(|c| c.max_tokens(512))
+///     .system_prompt("You are a helpful assistant.");
+/// let reply = session.send("What is the capital of France?").await?;
+/// ```
+pub struct ChatSession {
+    client: ChatClient,
+    messages: Vec<SessionMessage>,
+    tools: Option<Vec<ChatCompletionTools>>,
+    context_length: Option<usize>,
+    strategy: Arc<dyn TrimStrategy>,
+    counter: Arc<TokenCounter>,
+}
+
+impl fmt::Debug for ChatSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChatSession")
+            .field("messages", &self.messages.len())
+            .field("context_length", &self.context_length)
+            .finish()
+    }
+}
+
+impl ChatSession {
+    /// Start an empty session on `client`.
+    pub fn new(client: ChatClient) -> Self {
+        Self {
+            client,
+            messages: Vec::new(),
+            tools: None,
+            context_length: None,
+            strategy: Arc::new(KeepSystemPrompt),
+            counter: Arc::new(estimate_tokens),
+        }
+    }
+
+    /// Adjust the client's settings, e.g. `max_tokens` or `temperature`.
+    pub fn configure_client(mut self, f: impl FnOnce(ChatClient) -> ChatClient) -> Self {
+        self.client = f(self.client);
+        self
+    }
+
+    /// Add a pinned system prompt.
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.push_pinned(ChatCompletionRequestSystemMessage::from(prompt.into()));
+        self
+    }
+
+    /// Set the model's context length in tokens.
+    pub fn context_length(mut self, tokens: u64) -> Self {
+        self.context_length = Some(usize::try_from(tokens).unwrap_or(usize::MAX));
+        self
+    }
+
+    /// Choose how history is trimmed (default [`KeepSystemPrompt`]).
+    pub fn trim_strategy(mut self, strategy: impl TrimStrategy + 'static) -> Self {
+        self.strategy = Arc::new(strategy);
+        self
+    }
+
+    /// Replace the default token estimate, e.g. with a real tokenizer.
+    pub fn token_counter(
+        mut self,
+        counter: impl Fn(&ChatCompletionRequestMessage) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.counter = Arc::new(counter);
+        self
+    }
+
+    /// Tools offered to the model on every request.
+    pub fn tools(mut self, tools: Vec<ChatCompletionTools>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn client(&self) -> &ChatClient {
+        &self.client
+    }
+
+    pub fn messages(&self) -> &[SessionMessage] {
+        &self.messages
+    }
+
+    /// Append a message without sending it.
+    pub fn push(&mut self, message: impl Into<ChatCompletionRequestMessage>) {
+        self.messages.push(SessionMessage {
+            message: message.into(),
+            pinned: false,
+        });
+    }
+
+    /// Append a message that trimming never removes.
+    pub fn push_pinned(&mut self, message: impl Into<ChatCompletionRequestMessage>) {
+        self.messages.push(SessionMessage {
+            message: message.into(),
+            pinned: true,
+        });
+    }
+
+    /// Pin or unpin the message at `index`.
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) -> Result<()> {
+        let len = self.messages.len();
+        let message =
+            self.messages
+                .get_mut(index)
+                .ok_or_else(|| FoundryLocalError::Validation {
+                    reason: format!("message index {index} out of range for {len} messages"),
+                })?;
+        message.pinned = pinned;
+        Ok(())
+    }
+
+    /// Remove every unpinned message.
+    pub fn clear(&mut self) {
+        self.messages.retain(|m| m.pinned);
+    }
+
+    /// Tokens the history currently occupies.
+    pub fn token_count(&self) -> usize {
+        total_tokens(&self.messages, self.counter.as_ref())
+    }
+
+    /// Tokens available for the prompt, if the context length is known.
+    pub fn token_budget(&self) -> Option<usize> {
+        let reserve = self.client.max_tokens_setting().unwrap_or(0) as usize;
+        self.context_length.map(|n| n.saturating_sub(reserve))
+    }
+
+    /// Send a user message and append the reply to the history.
+    pub async fn send(&mut self, text: &str) -> Result<CreateChatCompletionResponse> {
+        self.send_message(ChatCompletionRequestUserMessage::from(text))
+            .await
+    }
+
+    /// Send any message and append the reply to the history.  If the
+    /// request fails, the message is removed again.
+    pub async fn send_message(
+        &mut self,
+        message: impl Into<ChatCompletionRequestMessage>,
+    ) -> Result<CreateChatCompletionResponse> {
+        let message = message.into();
+        self.push(message.clone());
+        let result = self.complete().await;
+        if result.is_err() {
+            remove_last(&mut self.messages, &message);
+        }
+        result
+    }
+
+    /// Request a reply to the history as it stands, e.g. after pushing tool
+    /// results, and append it.
+    pub async fn complete(&mut self) -> Result<CreateChatCompletionResponse> {
+        let request = self.prepare().await?;
+        let response = self
+            .client
+            .complete_chat(&request, self.tools.as_deref())
+            .await?;
+        if let Some(reply) = assistant_reply(&response) {
+            self.push(reply);
+        }
+        Ok(response)
+    }
+
+    /// Send a user message and stream the reply.
+    ///
+    /// The streamed reply, with any tool calls, is appended to the history
+    /// when the stream ends or is dropped.  If the request or the stream
+    /// fails, nothing is appended and the user message is removed again.
+    pub async fn send_streaming(&mut self, text: &str) -> Result<ChatSessionStream<'_>> {
+        let prompt: ChatCompletionRequestMessage =
+            ChatCompletionRequestUserMessage::from(text).into();
+        self.push(prompt.clone());
+        let inner = match self.open_stream().await {
+            Ok(inner) => inner,
+            Err(e) => {
+                remove_last(&mut self.messages, &prompt);
+                return Err(e);
+            }
+        };
+        Ok(ChatSessionStream {
+            inner,
+            session: self,
+            prompt,
+            reply: ChatCompletionAccumulator::new(),
+            failed: false,
+        })
+    }
+
+    async fn open_stream(&mut self) -> Result<ChatCompletionStream> {
+        let request = self.prepare().await?;
+        self.client
+            .complete_streaming_chat(&request, self.tools.as_deref())
+            .await
+    }
+
+    /// Trim the history to the budget and return the messages to send.
+    async fn prepare(&mut self) -> Result<Vec<ChatCompletionRequestMessage>> {
+        if let Some(budget) = self.token_budget() {
+            if self.token_count() > budget {
+                let strategy = Arc::clone(&self.strategy);
+                let mut ctx = TrimContext {
+                    messages: &mut self.messages,
+                    budget,
+                    client: &self.client,
+                    counter: self.counter.as_ref(),
+                };
+                strategy.trim(&mut ctx).await?;
+                let used = ctx.token_count();
+                if used > budget {
+                    return Err(FoundryLocalError::Validation {
+                        reason: format!(
+                            "conversation needs {used} tokens but only {budget} fit in the context window"
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(self.messages.iter().map(|m| m.message.clone()).collect())
+    }
+}
+
+/// A streamed reply from [`ChatSession::send_streaming`].
+///
+/// Yields the same chunks as [`ChatCompletionStream`] and appends the
+/// accumulated reply to the session when the stream ends or is dropped.
+/// After a stream error the reply and the user message are discarded.
+pub struct ChatSessionStream<'a> {
+    inner: ChatCompletionStream,
+    session: &'a mut ChatSession,
+    prompt: ChatCompletionRequestMessage,
+    reply: ChatCompletionAccumulator,
+    failed: bool,
+}
+
+impl ChatSessionStream<'_> {
+    /// Append the reply, or forget the prompt if the stream failed.  Runs
+    /// once.
+    fn commit(&mut self) {
+        let reply = std::mem::take(&mut self.reply);
+        if self.failed {
+            remove_last(&mut self.session.messages, &self.prompt);
+            self.failed = false;
+        } else if let Some(reply) = assistant_reply(&reply.response()) {
+            self.session.push(reply);
+        }
+    }
+}
+
+impl futures_core::Stream for ChatSessionStream<'_> {
+    type Item = Result<CreateChatCompletionStreamResponse>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = futures_core::ready!(Pin::new(&mut self.inner).poll_next(cx));
+        match &item {
+            Some(Ok(chunk)) => self.reply.push(chunk),
+            Some(Err(_)) => self.failed = true,
+            None => self.commit(),
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for ChatSessionStream<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// The history entry for the first choice of `response`, if it has
+/// content or tool calls.
+fn assistant_reply(
+    response: &CreateChatCompletionResponse,
+) -> Option<ChatCompletionRequestAssistantMessage> {
+    let message = &response.choices.first()?.message;
+    let has_content = message.content.as_deref().is_some_and(|c| !c.is_empty());
+    let has_tool_calls = message.tool_calls.as_ref().is_some_and(|c| !c.is_empty());
+    if !has_content && !has_tool_calls {
+        return None;
+    }
+    Some(ChatCompletionRequestAssistantMessage {
+        content: message.content.clone().map(Into::into),
+        tool_calls: message.tool_calls.clone(),
+        ..Default::default()
+    })
+}
+
+/// Remove `message` if it is still the last one, undoing a push whose
+/// request failed.  Trimming only removes older messages.
+fn remove_last(messages: &mut Vec<SessionMessage>, message: &ChatCompletionRequestMessage) {
+    if messages.last().is_some_and(|m| &m.message == message) {
+        messages.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(text: &str) -> SessionMessage {
+        SessionMessage {
+            message: ChatCompletionRequestUserMessage::from(text).into(),
+            pinned: false,
+        }
+    }
+
+    fn system(text: &str) -> SessionMessage {
+        SessionMessage {
+            message: ChatCompletionRequestSystemMessage::from(text).into(),
+            pinned: false,
+        }
+    }
+
+    fn tool_result(text: &str) -> SessionMessage {
+        SessionMessage {
+            message: serde_json::from_value(
+                serde_json::json!({ "role": "tool", "content": text, "tool_call_id": "call_1" }),
+            )
+            .unwrap(),
+            pinned: false,
+        }
+    }
+
+    /// One token per message keeps budgets easy to reason about.
+    fn one(_: &ChatCompletionRequestMessage) -> usize {
+        1
+    }
+
+    fn texts(messages: &[SessionMessage]) -> Vec<String> {
+        messages.iter().map(|m| message_text(&m.message)).collect()
+    }
+
+    #[test]
+    fn keep_system_prompt_drops_oldest_turns() {
+        let mut messages = vec![system("sys"), user("a"), user("b"), user("c")];
+        let removed = drop_oldest(&mut messages, &one, 2, is_system);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(texts(&messages), vec!["sys", "c"]);
+
+        let mut messages = vec![system("sys"), user("a"), user("b")];
+        drop_oldest(&mut messages, &one, 1, |_| false);
+        assert_eq!(texts(&messages), vec!["b"]);
+    }
+
+    #[test]
+    fn pinned_and_newest_messages_are_kept() {
+        let mut messages = vec![user("a"), user("b"), user("c")];
+        messages[0].pinned = true;
+        drop_oldest(&mut messages, &one, 1, |_| false);
+        assert_eq!(texts(&messages), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn tool_results_are_removed_with_their_call() {
+        let mut messages = vec![user("a"), tool_result("r1"), tool_result("r2"), user("b")];
+        assert!(!is_removable(&messages, 1));
+        assert_eq!(remove_turn(&mut messages, 0).len(), 3);
+        assert_eq!(texts(&messages), vec!["b"]);
+    }
+
+    #[test]
+    fn trailing_tool_results_keep_their_call() {
+        let call = SessionMessage {
+            message: serde_json::from_value(serde_json::json!({
+                "role": "assistant",
+                "tool_calls": [{
+                    "id": "call_1", "type": "function",
+                    "function": { "name": "weather", "arguments": "{}" }
+                }]
+            }))
+            .unwrap(),
+            pinned: false,
+        };
+        let mut messages = vec![user("a"), call, tool_result("r1"), tool_result("r2")];
+        assert!(!is_removable(&messages, 1));
+        assert!(remove_turn(&mut messages, 1).is_empty());
+        drop_oldest(&mut messages, &one, 1, |_| false);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(texts(&messages[1..]), vec!["r1", "r2"]);
+    }
+
+    #[test]
+    fn transcript_and_summary_detection() {
+        let messages = [user("hello").message, system("be brief").message];
+        assert_eq!(transcript(&messages), "user: hello\nsystem: be brief");
+        assert!(is_summary(
+            &system(&format!("{SUMMARY_PREFIX}they said hi")).message
+        ));
+        assert!(!is_summary(&system("be brief").message));
+    }
+
+    #[test]
+    fn streamed_replies_keep_their_tool_calls() {
+        let mut reply = ChatCompletionAccumulator::new();
+        for delta in [
+            serde_json::json!({ "role": "assistant", "content": "Let me check." }),
+            serde_json::json!({ "tool_calls": [{
+                "index": 0, "id": "call_1", "type": "function",
+                "function": { "name": "weather", "arguments": "{\"city\":" }
+            }] }),
+            serde_json::json!({ "tool_calls": [{
+                "index": 0, "function": { "arguments": "\"Oslo\"}" }
+            }] }),
+        ] {
+            reply.push(
+                &serde_json::from_value(serde_json::json!({
+                    "id": "c1", "object": "chat.completion.chunk", "created": 1, "model": "m",
+                    "choices": [{ "index": 0, "delta": delta }]
+                }))
+                .unwrap(),
+            );
+        }
+
+        let message = assistant_reply(&reply.response()).unwrap();
+        let message = serde_json::to_value(ChatCompletionRequestMessage::from(message)).unwrap();
+        assert_eq!(message["content"], "Let me check.");
+        assert_eq!(message["tool_calls"][0]["id"], "call_1");
+        assert_eq!(
+            message["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Oslo\"}"
+        );
+        assert!(assistant_reply(&ChatCompletionAccumulator::new().response()).is_none());
+    }
+
+    #[test]
+    fn failed_requests_remove_their_message() {
+        let mut messages = vec![user("a"), user("b")];
+        remove_last(&mut messages, &user("a").message);
+        assert_eq!(texts(&messages), vec!["a", "b"]);
+        remove_last(&mut messages, &user("b").message);
+        assert_eq!(texts(&messages), vec!["a"]);
+    }
+
+    #[test]
+    fn estimate_is_proportional_to_length() {
+        let short = estimate_tokens(&user("hi").message);
+        let long = estimate_tokens(&user(&"word ".repeat(100)).message);
+        assert!(short > 0 && long > short + 100);
+    }
+}
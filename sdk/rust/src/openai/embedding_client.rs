@@ -6,12 +6,14 @@ use async_openai::types::embeddings::CreateEmbeddingResponse;
 use serde_json::{json, Value};
 
 use crate::detail::core_interop::CoreInterop;
+use crate::detail::UsageTracker;
 use crate::error::{FoundryLocalError, Result};
 
 /// Client for OpenAI-compatible embedding generation backed by a local model.
 pub struct EmbeddingClient {
     model_id: String,
     core: Arc<CoreInterop>,
+    usage: Option<UsageTracker>,
 }
 
 impl EmbeddingClient {
@@ -19,9 +21,16 @@ impl EmbeddingClient {
         Self {
             model_id: model_id.to_owned(),
             core,
+            usage: None,
         }
     }
 
+    /// Count requests from this client as use of the model.
+    pub(crate) fn with_usage_tracker(mut self, tracker: UsageTracker) -> Self {
+        self.usage = Some(tracker);
+        self
+    }
+
     /// Generate embeddings for a single input text.
     pub async fn generate_embedding(&self, input: &str) -> Result<CreateEmbeddingResponse> {
         Self::validate_input(input)?;
@@ -44,6 +53,9 @@ impl EmbeddingClient {
     }
 
     async fn execute_request(&self, request: Value) -> Result<CreateEmbeddingResponse> {
+        if let Some(usage) = &self.usage {
+            usage.touch();
+        }
         let params = json!({
             "Params": {
                 "OpenAICreateRequest": serde_json::to_string(&request)?
@@ -0,0 +1,314 @@
+//! Image content parts for vision models.
+
+use std::path::Path;
+
+use async_openai::types::chat::{
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartImage,
+    ChatCompletionRequestMessageContentPartText, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+    ImageDetail, ImageUrl,
+};
+use base64::Engine;
+use serde_json::Value;
+
+use crate::error::{FoundryLocalError, Result};
+
+/// Formats recognised by [`ImageInput::from_bytes`].
+const SUPPORTED_FORMATS: &str = "JPEG, PNG, GIF, WebP or BMP";
+
+#[derive(Debug, Clone)]
+enum ImageSource {
+    Url(String),
+    Data {
+        bytes: Vec<u8>,
+        media_type: &'static str,
+    },
+}
+
+/// An image for a vision model, sent as an `image_url` content part.
+///
+/// Files and bytes are embedded as base64 `data:` URLs.  Combine images with
+/// text using [`user_message_with_images`].
+#[derive(Debug, Clone)]
+pub struct ImageInput {
+    source: ImageSource,
+    detail: Option<ImageDetail>,
+}
+
+impl ImageInput {
+    /// Read an image file.  The MIME type comes from the file's contents,
+    /// or its extension if those are not recognised.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| FoundryLocalError::Internal {
+            reason: format!("failed to read '{}': {e}", path.display()),
+        })?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(media_type_for_extension);
+        let Some(media_type) = detect_media_type(&bytes).or(extension) else {
+            return Err(FoundryLocalError::Validation {
+                reason: format!("'{}' is not a {SUPPORTED_FORMATS} image", path.display()),
+            });
+        };
+        Ok(Self::data(bytes, media_type))
+    }
+
+    /// Use encoded image bytes, detecting the format from their contents.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Result<Self> {
+        let bytes = bytes.into();
+        let Some(media_type) = detect_media_type(&bytes) else {
+            return Err(FoundryLocalError::Validation {
+                reason: format!("image bytes are not {SUPPORTED_FORMATS}"),
+            });
+        };
+        Ok(Self::data(bytes, media_type))
+    }
+
+    /// Refer to an image by URL, either `http(s)://` or a `data:` URL.
+    pub fn from_url(url: impl Into<String>) -> Self {
+        Self {
+            source: ImageSource::Url(url.into()),
+            detail: None,
+        }
+    }
+
+    fn data(bytes: Vec<u8>, media_type: &'static str) -> Self {
+        Self {
+            source: ImageSource::Data { bytes, media_type },
+            detail: None,
+        }
+    }
+
+    /// Set how closely the model should look at the image.
+    pub fn detail(mut self, detail: ImageDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// The MIME type of a file or bytes image; `None` for URLs.
+    pub fn media_type(&self) -> Option<&'static str> {
+        match self.source {
+            ImageSource::Url(_) => None,
+            ImageSource::Data { media_type, .. } => Some(media_type),
+        }
+    }
+
+    /// Shrink the image so neither side exceeds `max_side` pixels, keeping
+    /// its aspect ratio.  Smaller images are left alone.
+    ///
+    /// JPEG images stay JPEG; others are re-encoded as PNG.  Fails with
+    /// `Validation` for URL images and images that cannot be decoded.
+    #[cfg(feature = "image")]
+    pub fn downscale(self, max_side: u32) -> Result<Self> {
+        use image::{DynamicImage, ImageFormat};
+
+        let ImageSource::Data { bytes, media_type } = &self.source else {
+            return Err(FoundryLocalError::Validation {
+                reason: "only image files and bytes can be downscaled".into(),
+            });
+        };
+        let invalid = |e: image::ImageError| FoundryLocalError::Validation {
+            reason: format!("cannot downscale {media_type} image: {e}"),
+        };
+        let decoded = image::load_from_memory(bytes).map_err(invalid)?;
+        let max_side = max_side.max(1);
+        if decoded.width() <= max_side && decoded.height() <= max_side {
+            return Ok(self);
+        }
+
+        let resized = decoded.resize(max_side, max_side, image::imageops::FilterType::Triangle);
+        let (resized, format, media_type) = if *media_type == "image/jpeg" {
+            let rgb = DynamicImage::ImageRgb8(resized.to_rgb8());
+            (rgb, ImageFormat::Jpeg, "image/jpeg")
+        } else {
+            (resized, ImageFormat::Png, "image/png")
+        };
+        let mut out = std::io::Cursor::new(Vec::new());
+        resized.write_to(&mut out, format).map_err(invalid)?;
+        Ok(Self {
+            source: ImageSource::Data {
+                bytes: out.into_inner(),
+                media_type,
+            },
+            detail: self.detail,
+        })
+    }
+
+    /// The URL sent to the model, a `data:` URL for files and bytes.
+    pub fn url(&self) -> String {
+        match &self.source {
+            ImageSource::Url(url) => url.clone(),
+            ImageSource::Data { bytes, media_type } => {
+                let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+                format!("data:{media_type};base64,{encoded}")
+            }
+        }
+    }
+}
+
+impl From<ImageInput> for ChatCompletionRequestUserMessageContentPart {
+    fn from(image: ImageInput) -> Self {
+        let image_url = ImageUrl {
+            url: image.url(),
+            detail: image.detail,
+        };
+        Self::ImageUrl(ChatCompletionRequestMessageContentPartImage { image_url })
+    }
+}
+
+/// A user message with `text` followed by `images`.
+///
+/// Sending it to a model whose `input_modalities` lack `image` fails with
+/// `Validation` before the request is made.
+pub fn user_message_with_images(
+    text: impl Into<String>,
+    images: impl IntoIterator<Item = ImageInput>,
+) -> ChatCompletionRequestMessage {
+    let text = ChatCompletionRequestMessageContentPartText { text: text.into() };
+    let parts = std::iter::once(ChatCompletionRequestUserMessageContentPart::Text(text))
+        .chain(images.into_iter().map(Into::into))
+        .collect();
+    ChatCompletionRequestUserMessage {
+        content: ChatCompletionRequestUserMessageContent::Array(parts),
+        name: None,
+    }
+    .into()
+}
+
+/// Reject a chat `request` with image parts when the model's comma-separated
+/// `input_modalities` are known and lack `image`.
+pub(crate) fn check_image_input(
+    model_id: &str,
+    input_modalities: Option<&str>,
+    request: &Value,
+) -> Result<()> {
+    let Some(modalities) = input_modalities else {
+        return Ok(());
+    };
+    if modalities
+        .split(',')
+        .any(|m| m.trim().eq_ignore_ascii_case("image"))
+    {
+        return Ok(());
+    }
+    let has_image = request
+        .get("messages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|message| message.get("content").and_then(Value::as_array))
+        .flatten()
+        .any(|part| part.get("type").and_then(Value::as_str) == Some("image_url"));
+    if has_image {
+        return Err(FoundryLocalError::Validation {
+            reason: format!(
+                "model '{model_id}' does not accept image input (input modalities: {modalities})"
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn detect_media_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        _ => None,
+    }
+}
+
+fn media_type_for_extension(extension: &str) -> Option<&'static str> {
+    match extension.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "bmp" => Some("image/bmp"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn detects_media_types() {
+        assert_eq!(detect_media_type(b"\xFF\xD8\xFF\xE0"), Some("image/jpeg"));
+        assert_eq!(detect_media_type(PNG_HEADER), Some("image/png"));
+        assert_eq!(detect_media_type(b"GIF89a"), Some("image/gif"));
+        assert_eq!(
+            detect_media_type(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(detect_media_type(b"RIFF\0\0\0\0WAVE"), None);
+        assert_eq!(media_type_for_extension("JPG"), Some("image/jpeg"));
+        assert!(matches!(
+            ImageInput::from_bytes(b"hello".to_vec()),
+            Err(FoundryLocalError::Validation { .. })
+        ));
+    }
+
+    #[test]
+    fn builds_data_url_parts() {
+        let image = ImageInput::from_bytes(PNG_HEADER).unwrap();
+        assert_eq!(image.media_type(), Some("image/png"));
+        let message = user_message_with_images("What is this?", [image.detail(ImageDetail::Low)]);
+        assert_eq!(
+            serde_json::to_value(message).unwrap(),
+            json!({
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "What is this?" },
+                    {
+                        "type": "image_url",
+                        "image_url": {
+                            "url": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUg==",
+                            "detail": "low"
+                        }
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_images_for_text_only_models() {
+        let image = ImageInput::from_url("https://example.com/cat.png");
+        let request = json!({ "messages": [user_message_with_images("Describe", [image])] });
+        let text_only = json!({ "messages": [{ "role": "user", "content": "Describe" }] });
+
+        assert!(matches!(
+            check_image_input("m", Some("text"), &request),
+            Err(FoundryLocalError::Validation { .. })
+        ));
+        assert!(check_image_input("m", Some("text, image"), &request).is_ok());
+        assert!(check_image_input("m", None, &request).is_ok());
+        assert!(check_image_input("m", Some("text"), &text_only).is_ok());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn downscales_large_images() {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgba8(64, 32)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let image = ImageInput::from_bytes(png.into_inner()).unwrap();
+
+        let ImageSource::Data { bytes, media_type } = image.downscale(16).unwrap().source else {
+            panic!("expected image data");
+        };
+        assert_eq!(media_type, "image/png");
+        let resized = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((resized.width(), resized.height()), (16, 8));
+    }
+}
@@ -8,11 +8,23 @@ use serde::de::DeserializeOwned;
 
 use crate::error::{FoundryLocalError, Result};
 
+/// What a [`JsonStream`] filter does with a parsed item.
+pub(crate) enum FilterStep<T> {
+    /// Yield the item and continue.
+    Yield(T),
+    /// Yield the item and end the stream.
+    Finish(T),
+}
+
+type ItemFilter<T> = Box<dyn FnMut(T) -> FilterStep<T> + Send + Sync>;
+
 /// A stream that deserializes each received string chunk into `T`.
 ///
 /// Empty chunks are silently skipped.
 pub struct JsonStream<T> {
     rx: tokio::sync::mpsc::UnboundedReceiver<Result<String>>,
+    filter: Option<ItemFilter<T>>,
+    done: bool,
     _marker: PhantomData<T>,
 }
 
@@ -20,9 +32,20 @@ impl<T> JsonStream<T> {
     pub(crate) fn new(rx: tokio::sync::mpsc::UnboundedReceiver<Result<String>>) -> Self {
         Self {
             rx,
+            filter: None,
+            done: false,
             _marker: PhantomData,
         }
     }
+
+    /// Pass every parsed item through `filter` before yielding it.
+    pub(crate) fn with_filter(
+        mut self,
+        filter: impl FnMut(T) -> FilterStep<T> + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
 }
 
 impl<T> Unpin for JsonStream<T> {}
@@ -31,6 +54,9 @@ impl<T: DeserializeOwned> futures_core::Stream for JsonStream<T> {
     type Item = Result<T>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
         loop {
             match self.rx.poll_recv(cx) {
                 Poll::Ready(Some(Ok(chunk))) => {
@@ -38,7 +64,18 @@ impl<T: DeserializeOwned> futures_core::Stream for JsonStream<T> {
                         continue;
                     }
                     let parsed = serde_json::from_str::<T>(&chunk).map_err(FoundryLocalError::from);
-                    return Poll::Ready(Some(parsed));
+                    let this = &mut *self;
+                    let item = match (parsed, this.filter.as_mut()) {
+                        (Ok(item), Some(filter)) => match filter(item) {
+                            FilterStep::Yield(item) => Ok(item),
+                            FilterStep::Finish(item) => {
+                                this.done = true;
+                                Ok(item)
+                            }
+                        },
+                        (parsed, _) => parsed,
+                    };
+                    return Poll::Ready(Some(item));
                 }
                 Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
                 Poll::Ready(None) => return Poll::Ready(None),
@@ -0,0 +1,415 @@
+//! Validation of JSON values against the subset of JSON Schema that
+//! constrained decoding supports.
+//!
+//! Covers `type`, `enum`, `const`, object `properties`, `required` and
+//! `additionalProperties`, array `items`, length and numeric bounds,
+//! `allOf`/`anyOf`/`oneOf` and local `$ref`s into `$defs` or
+//! `definitions`.  Other keywords are ignored.
+
+use serde_json::{Map, Value};
+
+/// Nesting depth after which `$ref` cycles are assumed.
+const MAX_DEPTH: usize = 64;
+
+/// Check `value` against `schema` and return one message per violation,
+/// each prefixed with the JSON pointer of the offending value.
+pub(crate) fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    Validator { root: schema }.check(schema, value, "", 0, &mut errors);
+    errors
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    fn check(
+        &self,
+        schema: &'a Value,
+        value: &Value,
+        path: &str,
+        depth: usize,
+        errors: &mut Vec<String>,
+    ) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return errors.push(format!("{}: no value is allowed", at(path))),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+        if depth > MAX_DEPTH {
+            return errors.push(format!("{}: schema nesting is too deep", at(path)));
+        }
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, value, path, depth + 1, errors),
+                None => errors.push(format!("{}: unresolved $ref '{reference}'", at(path))),
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            if !matches_type(expected, value) {
+                // The remaining keywords would only repeat the mismatch.
+                return errors.push(format!(
+                    "{}: expected {}, found {}",
+                    at(path),
+                    describe_type(expected),
+                    type_name(value)
+                ));
+            }
+        }
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                errors.push(format!(
+                    "{}: {value} is not one of {}",
+                    at(path),
+                    Value::Array(allowed.clone())
+                ));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                errors.push(format!("{}: expected {expected}, found {value}", at(path)));
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path, depth, errors),
+            Value::Array(items) => self.check_array(schema, items, path, depth, errors),
+            Value::String(s) => check_length(schema, s.chars().count(), "characters", path, errors),
+            Value::Number(n) => {
+                if let Some(n) = n.as_f64() {
+                    check_bounds(schema, n, path, errors);
+                }
+            }
+            _ => {}
+        }
+
+        self.check_combinators(schema, value, path, depth, errors);
+    }
+
+    fn check_object(
+        &self,
+        schema: &'a Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        depth: usize,
+        errors: &mut Vec<String>,
+    ) {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    errors.push(format!("{}: missing required property '{name}'", at(path)));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema.get("additionalProperties");
+        for (name, item) in object {
+            let item_path = format!("{path}/{}", escape(name));
+            match properties.and_then(|p| p.get(name)) {
+                Some(property) => self.check(property, item, &item_path, depth + 1, errors),
+                None => match additional {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{}: unexpected property '{name}'", at(path)))
+                    }
+                    Some(extra) => self.check(extra, item, &item_path, depth + 1, errors),
+                    None => {}
+                },
+            }
+        }
+    }
+
+    fn check_array(
+        &self,
+        schema: &'a Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        depth: usize,
+        errors: &mut Vec<String>,
+    ) {
+        let len = items.len();
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (len as u64) < min {
+                errors.push(format!(
+                    "{}: expected at least {min} items, found {len}",
+                    at(path)
+                ));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if len as u64 > max {
+                errors.push(format!(
+                    "{}: expected at most {max} items, found {len}",
+                    at(path)
+                ));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                self.check(
+                    item_schema,
+                    item,
+                    &format!("{path}/{index}"),
+                    depth + 1,
+                    errors,
+                );
+            }
+        }
+    }
+
+    fn check_combinators(
+        &self,
+        schema: &'a Map<String, Value>,
+        value: &Value,
+        path: &str,
+        depth: usize,
+        errors: &mut Vec<String>,
+    ) {
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for sub in all {
+                self.check(sub, value, path, depth + 1, errors);
+            }
+        }
+        let passing = |subs: &'a [Value]| {
+            subs.iter()
+                .filter(|sub| {
+                    let mut sub_errors = Vec::new();
+                    self.check(sub, value, path, depth + 1, &mut sub_errors);
+                    sub_errors.is_empty()
+                })
+                .count()
+        };
+        if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+            if passing(any) == 0 {
+                errors.push(format!("{}: does not match any allowed schema", at(path)));
+            }
+        }
+        if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+            let count = passing(one);
+            if count != 1 {
+                errors.push(format!(
+                    "{}: must match exactly one allowed schema, matches {count}",
+                    at(path)
+                ));
+            }
+        }
+    }
+
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+fn check_length(
+    schema: &Map<String, Value>,
+    len: usize,
+    unit: &str,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if (len as u64) < min {
+            errors.push(format!(
+                "{}: expected at least {min} {unit}, found {len}",
+                at(path)
+            ));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if len as u64 > max {
+            errors.push(format!(
+                "{}: expected at most {max} {unit}, found {len}",
+                at(path)
+            ));
+        }
+    }
+}
+
+fn check_bounds(schema: &Map<String, Value>, n: f64, path: &str, errors: &mut Vec<String>) {
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(min) = bound("minimum") {
+        if n < min {
+            errors.push(format!("{}: {n} is less than the minimum {min}", at(path)));
+        }
+    }
+    if let Some(max) = bound("maximum") {
+        if n > max {
+            errors.push(format!(
+                "{}: {n} is greater than the maximum {max}",
+                at(path)
+            ));
+        }
+    }
+    if let Some(min) = bound("exclusiveMinimum") {
+        if n <= min {
+            errors.push(format!("{}: {n} must be greater than {min}", at(path)));
+        }
+    }
+    if let Some(max) = bound("exclusiveMaximum") {
+        if n >= max {
+            errors.push(format!("{}: {n} must be less than {max}", at(path)));
+        }
+    }
+}
+
+fn matches_type(expected: &Value, value: &Value) -> bool {
+    match expected {
+        Value::String(name) => is_type(name, value),
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|name| is_type(name, value)),
+        _ => true,
+    }
+}
+
+fn is_type(name: &str, value: &Value) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" or "),
+        other => other.as_str().unwrap_or("any").to_owned(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// The location shown in messages; the root is written as `/`.
+fn at(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+/// Escape a property name for a JSON pointer.
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn invoice_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "number": { "type": "string", "minLength": 1 },
+                "status": { "enum": ["paid", "open"] },
+                "customer": { "$ref": "#/$defs/Customer" },
+                "lines": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "quantity": { "type": "integer", "minimum": 1 },
+                            "price": { "type": "number", "exclusiveMinimum": 0 }
+                        },
+                        "required": ["quantity", "price"]
+                    }
+                },
+                "note": { "type": ["string", "null"] }
+            },
+            "required": ["number", "lines"],
+            "additionalProperties": false,
+            "$defs": {
+                "Customer": {
+                    "type": "object",
+                    "properties": { "name": { "type": "string" } },
+                    "required": ["name"]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn accepts_a_conforming_value() {
+        let value = json!({
+            "number": "INV-7",
+            "status": "paid",
+            "customer": { "name": "Contoso" },
+            "lines": [{ "quantity": 2, "price": 9.5 }],
+            "note": null
+        });
+        assert!(validate(&invoice_schema(), &value).is_empty());
+    }
+
+    #[test]
+    fn reports_each_violation_with_its_path() {
+        let value = json!({
+            "number": "",
+            "status": "void",
+            "customer": {},
+            "lines": [{ "quantity": 0, "price": 9.5 }, { "quantity": 1.5 }],
+            "note": 3,
+            "total": 10
+        });
+        let errors = validate(&invoice_schema(), &value);
+        for expected in [
+            "/number: expected at least 1 characters, found 0",
+            "/status: \"void\" is not one of [\"paid\",\"open\"]",
+            "/customer: missing required property 'name'",
+            "/lines/0/quantity: 0 is less than the minimum 1",
+            "/lines/1: missing required property 'price'",
+            "/lines/1/quantity: expected integer, found number",
+            "/note: expected string or null, found integer",
+            "/: unexpected property 'total'",
+        ] {
+            assert!(
+                errors.iter().any(|e| e == expected),
+                "{expected} not in {errors:#?}"
+            );
+        }
+        assert_eq!(errors.len(), 8);
+    }
+
+    #[test]
+    fn combinators_and_root_type() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "type": "number" }] });
+        assert!(validate(&schema, &json!(1.5)).is_empty());
+        assert_eq!(
+            validate(&schema, &json!(2)),
+            ["/: must match exactly one allowed schema, matches 2"]
+        );
+        assert_eq!(
+            validate(&json!({ "type": "object" }), &json!([1])),
+            ["/: expected object, found array"]
+        );
+    }
+}
@@ -1,17 +1,49 @@
 mod audio_client;
+mod batch;
 mod chat_client;
+mod chat_session;
 mod embedding_client;
+mod image_input;
 mod json_stream;
+mod json_validation;
 mod live_audio_session;
+mod reasoning;
+mod responses_client;
+mod responses_types;
+mod stream_accumulator;
+mod structured_output;
+mod tool_registry;
 
 pub use self::audio_client::{
     AudioClient, AudioClientSettings, AudioTranscriptionResponse, AudioTranscriptionStream,
     TranscriptionSegment, TranscriptionWord,
 };
-pub use self::chat_client::{ChatClient, ChatClientSettings, ChatCompletionStream};
+pub use self::batch::{BatchProgress, ChatBatch};
+pub use self::chat_client::{ChatClient, ChatClientSettings, ChatCompletionStream, ChatRequest};
+pub use self::chat_session::{
+    ChatSession, ChatSessionStream, DropOldest, KeepSystemPrompt, SessionMessage, Summarize,
+    TokenCounter, TrimContext, TrimFuture, TrimStrategy,
+};
 pub use self::embedding_client::EmbeddingClient;
+pub use self::image_input::{user_message_with_images, ImageInput};
 pub use self::json_stream::JsonStream;
 pub use self::live_audio_session::{
     ContentPart, CoreErrorResponse, LiveAudioTranscriptionOptions, LiveAudioTranscriptionResponse,
     LiveAudioTranscriptionSession, LiveAudioTranscriptionStream,
 };
+pub use self::reasoning::{ReasoningCompletion, ReasoningEvent, ReasoningMode, ReasoningStream};
+pub use self::responses_client::{
+    ResponseStream, ResponsesClient, ResponsesClientSettings, ResponsesRequest,
+};
+pub use self::responses_types::{
+    Annotation, DeleteResponseResult, FunctionCallItem, FunctionCallOutputItem, IncompleteDetails,
+    InputItemsList, LogProb, MessageItem, MessageRole, ReasoningConfig, ReasoningItem,
+    ResponseContent, ResponseContentPart, ResponseError, ResponseInput, ResponseItem,
+    ResponseItemStatus, ResponseObject, ResponseStatus, ResponseStreamEvent, ResponseTool,
+    ResponseUsage, TextConfig, TextFormat, Truncation,
+};
+pub use self::stream_accumulator::{
+    AccumulatingStream, ChatCompletionAccumulator, StreamedToolCall,
+};
+pub use self::structured_output::{JsonCompletion, RejectedReply};
+pub use self::tool_registry::{ToolRegistry, ToolRun};
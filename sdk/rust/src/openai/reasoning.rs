@@ -0,0 +1,570 @@
+//! Separating the thinking of reasoning models from their answers.
+
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_openai::types::chat::{
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FinishReason,
+};
+
+use crate::error::Result;
+
+use super::chat_client::ChatCompletionStream;
+use super::json_stream::FilterStep;
+use super::stream_accumulator::ChatCompletionAccumulator;
+
+type StreamChunk = CreateChatCompletionStreamResponse;
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// What a [`ChatClient`](super::ChatClient) does with the `<think>…</think>`
+/// block reasoning models put before their answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReasoningMode {
+    /// Leave the thinking in `content`.  The default.
+    #[default]
+    Inline,
+    /// Remove the thinking from `content`, returning it separately from
+    /// [`complete_with_reasoning`](super::ChatClient::complete_with_reasoning)
+    /// and [`stream_with_reasoning`](super::ChatClient::stream_with_reasoning).
+    /// Methods returning a plain response, such as
+    /// [`complete_chat`](super::ChatClient::complete_chat), discard it.
+    ///
+    /// For models whose capabilities include `reasoning`, a reply that does
+    /// not open with `<think>` may have had its block opened by the prompt
+    /// template.  Streamed text of such a reply is held back until
+    /// `</think>` or the end of the reply shows which it was.
+    Separate,
+    /// Remove the thinking from `content` and discard it.
+    Hide,
+}
+
+/// Split a complete reply into its thinking and its answer.
+///
+/// With `template_opens`, for reasoning models, also handles replies whose
+/// opening `<think>` was part of the prompt, where the reply starts inside
+/// the thinking.  A reply cut off while thinking has no answer.
+pub(crate) fn split_reasoning(text: &str, template_opens: bool) -> (Option<String>, String) {
+    let trimmed = text.trim_start();
+    let (body, opened) = match trimmed.strip_prefix(OPEN_TAG) {
+        Some(body) => (body, true),
+        None => (text, false),
+    };
+    match body.find(CLOSE_TAG) {
+        Some(end) if opened || template_opens => (
+            Some(body[..end].trim().to_owned()),
+            body[end + CLOSE_TAG.len()..].trim_start().to_owned(),
+        ),
+        None if opened => (Some(body.trim().to_owned()), String::new()),
+        _ => (None, text.to_owned()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Phase {
+    /// Nothing but whitespace seen yet.
+    #[default]
+    Start,
+    /// No opening `<think>` from a model whose template may have opened the
+    /// block; holding the text back until a `</think>` shows it did, or the
+    /// reply ends.
+    Undecided,
+    Thinking,
+    /// After `</think>`, skipping the whitespace before the answer.
+    AfterThinking,
+    Answer,
+}
+
+/// Incremental version of [`split_reasoning`] for streamed deltas, which
+/// may split the tags.
+#[derive(Debug, Default)]
+struct ThinkSplitter {
+    /// Whether the prompt template may have opened the block.
+    template_opens: bool,
+    phase: Phase,
+    /// Text held back because it may be the start of a tag.
+    pending: String,
+}
+
+impl ThinkSplitter {
+    fn new(template_opens: bool) -> Self {
+        Self {
+            template_opens,
+            ..Self::default()
+        }
+    }
+
+    /// Feed `delta`, returning the thinking and answer text it completes.
+    fn push(&mut self, delta: &str) -> (String, String) {
+        let mut text = std::mem::take(&mut self.pending);
+        text.push_str(delta);
+        let mut reasoning = String::new();
+        let mut answer = String::new();
+
+        loop {
+            match self.phase {
+                Phase::Start => {
+                    let trimmed = text.trim_start();
+                    if let Some(rest) = trimmed.strip_prefix(OPEN_TAG) {
+                        text = rest.to_owned();
+                        self.phase = Phase::Thinking;
+                    } else if OPEN_TAG.starts_with(trimmed) {
+                        self.pending = text;
+                        break;
+                    } else if self.template_opens {
+                        self.phase = Phase::Undecided;
+                    } else {
+                        self.phase = Phase::Answer;
+                    }
+                }
+                Phase::Undecided => match text.find(CLOSE_TAG) {
+                    Some(end) => {
+                        reasoning.push_str(text[..end].trim_start());
+                        text = text[end + CLOSE_TAG.len()..].to_owned();
+                        self.phase = Phase::AfterThinking;
+                    }
+                    None => {
+                        self.pending = text;
+                        break;
+                    }
+                },
+                Phase::Thinking => match text.find(CLOSE_TAG) {
+                    Some(end) => {
+                        reasoning.push_str(&text[..end]);
+                        text = text[end + CLOSE_TAG.len()..].to_owned();
+                        self.phase = Phase::AfterThinking;
+                    }
+                    None => {
+                        let keep = partial_tag_len(&text, CLOSE_TAG);
+                        reasoning.push_str(&text[..text.len() - keep]);
+                        self.pending = text[text.len() - keep..].to_owned();
+                        break;
+                    }
+                },
+                Phase::AfterThinking => {
+                    let rest = text.trim_start();
+                    if rest.is_empty() {
+                        break;
+                    }
+                    text = rest.to_owned();
+                    self.phase = Phase::Answer;
+                }
+                Phase::Answer => {
+                    answer.push_str(&text);
+                    break;
+                }
+            }
+        }
+        (reasoning, answer)
+    }
+
+    /// Release held-back text at the end of the reply.
+    fn finish(&mut self) -> (String, String) {
+        let pending = std::mem::take(&mut self.pending);
+        match self.phase {
+            Phase::Thinking => (pending, String::new()),
+            _ => (String::new(), pending),
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that starts `tag`.
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len().min(text.len() + 1))
+        .rev()
+        .find(|&n| {
+            text.is_char_boundary(text.len() - n) && tag.starts_with(&text[text.len() - n..])
+        })
+        .unwrap_or(0)
+}
+
+/// Splits thinking out of streamed chunks and enforces the reasoning
+/// budget.
+pub(super) struct ReasoningFilter {
+    mode: ReasoningMode,
+    budget: Option<u32>,
+    template_opens: bool,
+    used: u32,
+    splitters: HashMap<u32, ThinkSplitter>,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+/// Thinking deltas taken from a chunk, by choice index.
+type ReasoningDeltas = Vec<(u32, String)>;
+
+impl ReasoningFilter {
+    /// `template_opens` is whether the model's prompt template may open
+    /// the `<think>` block; `cancel_flag` stops the request once the budget
+    /// is spent.
+    pub(super) fn new(
+        mode: ReasoningMode,
+        budget: Option<u32>,
+        template_opens: bool,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            mode,
+            budget,
+            template_opens,
+            used: 0,
+            splitters: HashMap::new(),
+            cancel_flag,
+        }
+    }
+
+    /// Move thinking out of `chunk`.  Returns `true` once the budget is
+    /// spent, after marking every choice as finished for `length`.
+    ///
+    /// The budget counts chunks carrying thinking.  Text held back while it
+    /// is unclear whether the template opened a `<think>` block does not
+    /// count until a `</think>` shows it was thinking.
+    fn split(&mut self, chunk: &mut CreateChatCompletionStreamResponse) -> (ReasoningDeltas, bool) {
+        let mut deltas = Vec::new();
+        let template_opens = self.template_opens;
+        for choice in &mut chunk.choices {
+            let splitter = self
+                .splitters
+                .entry(choice.index)
+                .or_insert_with(|| ThinkSplitter::new(template_opens));
+            let original = choice.delta.content.take();
+            let (mut reasoning, mut answer) =
+                splitter.push(original.as_deref().unwrap_or_default());
+            if choice.finish_reason.is_some() {
+                let (r, a) = splitter.finish();
+                reasoning.push_str(&r);
+                answer.push_str(&a);
+            }
+            choice.delta.content = match self.mode {
+                ReasoningMode::Inline => original,
+                _ => (!answer.is_empty()).then_some(answer),
+            };
+            if !reasoning.is_empty() {
+                self.used += 1;
+            }
+            if !reasoning.is_empty() {
+                deltas.push((choice.index, reasoning));
+            }
+        }
+
+        let exhausted = self.budget.is_some_and(|budget| self.used > budget);
+        if exhausted {
+            self.cancel_flag.store(true, Ordering::Release);
+            for choice in &mut chunk.choices {
+                choice.finish_reason = Some(FinishReason::Length);
+                let (reasoning, answer) = self
+                    .splitters
+                    .get_mut(&choice.index)
+                    .map(ThinkSplitter::finish)
+                    .unwrap_or_default();
+                if !reasoning.is_empty() {
+                    deltas.push((choice.index, reasoning));
+                }
+                if !answer.is_empty() && self.mode != ReasoningMode::Inline {
+                    choice
+                        .delta
+                        .content
+                        .get_or_insert_with(String::new)
+                        .push_str(&answer);
+                }
+            }
+        }
+        (deltas, exhausted)
+    }
+
+    /// Filter for a plain [`ChatCompletionStream`], dropping the thinking.
+    pub(super) fn into_stream_filter(
+        mut self,
+    ) -> impl FnMut(StreamChunk) -> FilterStep<StreamChunk> {
+        move |mut chunk| match self.split(&mut chunk) {
+            (_, true) => FilterStep::Finish(chunk),
+            (_, false) => FilterStep::Yield(chunk),
+        }
+    }
+}
+
+/// An item of a [`ReasoningStream`].
+#[derive(Debug, Clone)]
+pub enum ReasoningEvent {
+    /// Thinking text of the choice at `index`.
+    Reasoning { index: u32, text: String },
+    /// A chunk whose `content` deltas hold only the answer.
+    Chunk(CreateChatCompletionStreamResponse),
+}
+
+/// A chat completion stream with thinking and answer as separate events.
+///
+/// Returned by [`ChatClient::stream_with_reasoning`](super::ChatClient::stream_with_reasoning).
+/// Render [`ReasoningEvent::Reasoning`] in a collapsible "thinking" panel
+/// and the `content` of [`ReasoningEvent::Chunk`] as the reply.  With
+/// [`ReasoningMode::Hide`] no reasoning events are produced.
+pub struct ReasoningStream {
+    inner: ChatCompletionStream,
+    filter: ReasoningFilter,
+    queue: VecDeque<ReasoningEvent>,
+    done: bool,
+}
+
+impl ReasoningStream {
+    pub(super) fn new(inner: ChatCompletionStream, filter: ReasoningFilter) -> Self {
+        Self {
+            inner,
+            filter,
+            queue: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Drain the stream into the complete response and the first choice's
+    /// thinking.
+    pub async fn collect(mut self) -> Result<ReasoningCompletion> {
+        use tokio_stream::StreamExt;
+
+        let mut accumulator = ChatCompletionAccumulator::new();
+        let mut reasoning: Option<String> = None;
+        while let Some(event) = self.next().await {
+            match event? {
+                ReasoningEvent::Reasoning { index: 0, text } => {
+                    reasoning.get_or_insert_with(String::new).push_str(&text);
+                }
+                ReasoningEvent::Reasoning { .. } => {}
+                ReasoningEvent::Chunk(chunk) => accumulator.push(&chunk),
+            }
+        }
+        Ok(ReasoningCompletion {
+            reasoning: reasoning.map(|r| r.trim().to_owned()),
+            response: accumulator.response(),
+        })
+    }
+}
+
+impl futures_core::Stream for ReasoningStream {
+    type Item = Result<ReasoningEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(event) = this.queue.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let mut chunk = match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => chunk,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            let (deltas, exhausted) = this.filter.split(&mut chunk);
+            if this.filter.mode != ReasoningMode::Hide {
+                this.queue.extend(
+                    deltas
+                        .into_iter()
+                        .map(|(index, text)| ReasoningEvent::Reasoning { index, text }),
+                );
+            }
+            this.queue.push_back(ReasoningEvent::Chunk(chunk));
+            this.done = exhausted;
+        }
+    }
+}
+
+/// A chat completion with the first choice's thinking separated from its
+/// answer.
+///
+/// Returned by [`ChatClient::complete_with_reasoning`](super::ChatClient::complete_with_reasoning).
+#[derive(Debug, Clone)]
+pub struct ReasoningCompletion {
+    /// The thinking, or `None` if the model did not think or it was hidden.
+    pub reasoning: Option<String>,
+    /// The response, whose `content` holds only the answer.  A spent
+    /// reasoning budget ends it with finish reason `length`.
+    pub response: CreateChatCompletionResponse,
+}
+
+impl ReasoningCompletion {
+    /// The first choice's answer.
+    pub fn content(&self) -> Option<&str> {
+        self.response
+            .choices
+            .first()
+            .and_then(|c| c.message.content.as_deref())
+    }
+}
+
+/// Remove the thinking from every choice of `response`, returning the
+/// first choice's.  See [`split_reasoning`] for `template_opens`.
+pub(super) fn split_response(
+    response: &mut CreateChatCompletionResponse,
+    template_opens: bool,
+) -> Option<String> {
+    let mut first = None;
+    for (i, choice) in response.choices.iter_mut().enumerate() {
+        let Some(content) = choice.message.content.take() else {
+            continue;
+        };
+        let (reasoning, answer) = split_reasoning(&content, template_opens);
+        choice.message.content = Some(answer);
+        if i == 0 {
+            first = reasoning;
+        }
+    }
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stream_split(deltas: &[&str], template_opens: bool) -> (String, String) {
+        let mut splitter = ThinkSplitter::new(template_opens);
+        let (mut reasoning, mut answer) = (String::new(), String::new());
+        for delta in deltas {
+            let (r, a) = splitter.push(delta);
+            reasoning.push_str(&r);
+            answer.push_str(&a);
+        }
+        let (r, a) = splitter.finish();
+        (reasoning + &r, answer + &a)
+    }
+
+    #[test]
+    fn splits_complete_replies() {
+        assert_eq!(
+            split_reasoning("<think>\nAdd them.\n</think>\n\n42", false),
+            (Some("Add them.".into()), "42".into())
+        );
+        // The template of a reasoning model opened the block.
+        assert_eq!(
+            split_reasoning("Add them.</think>42", true),
+            (Some("Add them.".into()), "42".into())
+        );
+        assert_eq!(
+            split_reasoning("Add them.</think>42", false),
+            (None, "Add them.</think>42".into())
+        );
+        assert_eq!(
+            split_reasoning("<think>Still going", false),
+            (Some("Still going".into()), String::new())
+        );
+        assert_eq!(split_reasoning("Just 42", true), (None, "Just 42".into()));
+    }
+
+    #[test]
+    fn splits_tags_across_deltas() {
+        assert_eq!(
+            stream_split(
+                &["<th", "ink>", "Add", " them.</", "think", ">\n", "\n42"],
+                false
+            ),
+            ("Add them.".into(), "42".into())
+        );
+        assert_eq!(
+            stream_split(&["<", "b>bold</b>"], true),
+            (String::new(), "<b>bold</b>".into())
+        );
+        assert_eq!(
+            stream_split(&["<thi"], false),
+            (String::new(), "<thi".into())
+        );
+    }
+
+    #[test]
+    fn streams_agree_with_complete_replies() {
+        let replies = [
+            "<think>\nAdd them.\n</think>\n\n42",
+            "Add them.</think>42",
+            "  Add them.\n</think>\n42",
+            "<think>Still going",
+            "Just 42",
+            "  <b>bold</b>",
+        ];
+        for (reply, template_opens) in replies.iter().flat_map(|r| [(r, false), (r, true)]) {
+            let (reasoning, answer) = split_reasoning(reply, template_opens);
+            let deltas: Vec<String> = reply.chars().map(String::from).collect();
+            let deltas: Vec<&str> = deltas.iter().map(String::as_str).collect();
+            let (streamed_reasoning, streamed_answer) = stream_split(&deltas, template_opens);
+            assert_eq!(
+                (reasoning.unwrap_or_default(), answer),
+                (streamed_reasoning.trim().to_owned(), streamed_answer),
+                "{reply:?} {template_opens}"
+            );
+        }
+    }
+
+    fn chunk(content: &str) -> CreateChatCompletionStreamResponse {
+        serde_json::from_value(json!({
+            "id": "c", "object": "chat.completion.chunk", "created": 0, "model": "m",
+            "choices": [{ "index": 0, "delta": { "content": content } }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn stops_when_the_budget_is_spent() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut filter =
+            ReasoningFilter::new(ReasoningMode::Separate, Some(2), false, Arc::clone(&cancel));
+
+        let mut first = chunk("<think>a");
+        assert_eq!(filter.split(&mut first), (vec![(0, "a".into())], false));
+        assert_eq!(first.choices[0].delta.content, None);
+
+        let mut second = chunk("b");
+        assert!(!filter.split(&mut second).1);
+        let mut third = chunk("c");
+        assert!(filter.split(&mut third).1);
+        assert_eq!(third.choices[0].finish_reason, Some(FinishReason::Length));
+        assert!(cancel.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn answers_without_thinking_stream_past_the_budget() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut filter =
+            ReasoningFilter::new(ReasoningMode::Separate, Some(1), false, Arc::clone(&cancel));
+
+        for text in ["Just", " 42", " and", " more"] {
+            let mut c = chunk(text);
+            assert_eq!(filter.split(&mut c), (vec![], false));
+            assert_eq!(c.choices[0].delta.content.as_deref(), Some(text));
+        }
+        assert!(!cancel.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn held_back_text_does_not_count_until_it_is_thinking() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut filter =
+            ReasoningFilter::new(ReasoningMode::Separate, Some(1), true, Arc::clone(&cancel));
+
+        for text in ["Maybe", " more", " text"] {
+            let mut c = chunk(text);
+            assert_eq!(filter.split(&mut c), (vec![], false));
+            assert_eq!(c.choices[0].delta.content, None);
+        }
+        let mut end = chunk("</think>42");
+        assert_eq!(
+            filter.split(&mut end),
+            (vec![(0, "Maybe more text".into())], false)
+        );
+        assert_eq!(end.choices[0].delta.content.as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn inline_mode_keeps_content() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut filter = ReasoningFilter::new(ReasoningMode::Inline, None, false, cancel);
+        let mut c = chunk("<think>a</think>b");
+        assert_eq!(filter.split(&mut c).0, vec![(0, "a".into())]);
+        assert_eq!(
+            c.choices[0].delta.content.as_deref(),
+            Some("<think>a</think>b")
+        );
+    }
+}
@@ -0,0 +1,782 @@
+//! Client for the OpenAI Responses API of the local web service.
+
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::detail::UsageTracker;
+use crate::error::{FoundryLocalError, Result};
+use crate::types::ChatToolChoice;
+
+use super::json_stream::JsonStream;
+use super::responses_types::{
+    DeleteResponseResult, InputItemsList, ReasoningConfig, ResponseInput, ResponseItem,
+    ResponseObject, ResponseStreamEvent, ResponseTool, TextConfig, Truncation,
+};
+
+/// Longest response id accepted by [`ResponsesClient::get`] and friends.
+const MAX_ID_LEN: usize = 1024;
+
+/// A stream of [`ResponseStreamEvent`]s.
+///
+/// Returned by [`ResponsesClient::create_streaming`].
+pub type ResponseStream = JsonStream<ResponseStreamEvent>;
+
+/// Tuning knobs for Responses API requests.
+///
+/// The same setters on [`ResponsesRequest`] override a client's settings
+/// for a single request.
+#[derive(Debug, Clone, Default)]
+pub struct ResponsesClientSettings {
+    instructions: Option<String>,
+    temperature: Option<f64>,
+    top_p: Option<f64>,
+    max_output_tokens: Option<u32>,
+    frequency_penalty: Option<f64>,
+    presence_penalty: Option<f64>,
+    tool_choice: Option<ChatToolChoice>,
+    truncation: Option<Truncation>,
+    parallel_tool_calls: Option<bool>,
+    store: Option<bool>,
+    metadata: BTreeMap<String, String>,
+    reasoning: Option<ReasoningConfig>,
+    text: Option<TextConfig>,
+    seed: Option<u64>,
+    user: Option<String>,
+}
+
+impl ResponsesClientSettings {
+    fn serialize(&self) -> serde_json::Map<String, Value> {
+        let mut map = serde_json::Map::new();
+        let mut insert = |key: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                map.insert(key.into(), value);
+            }
+        };
+
+        insert("instructions", self.instructions.as_ref().map(|v| json!(v)));
+        insert("temperature", self.temperature.map(|v| json!(v)));
+        insert("top_p", self.top_p.map(|v| json!(v)));
+        insert(
+            "max_output_tokens",
+            self.max_output_tokens.map(|v| json!(v)),
+        );
+        insert(
+            "frequency_penalty",
+            self.frequency_penalty.map(|v| json!(v)),
+        );
+        insert("presence_penalty", self.presence_penalty.map(|v| json!(v)));
+        insert(
+            "tool_choice",
+            self.tool_choice.as_ref().map(|tc| match tc {
+                ChatToolChoice::None => json!("none"),
+                ChatToolChoice::Auto => json!("auto"),
+                ChatToolChoice::Required => json!("required"),
+                ChatToolChoice::Function(name) => json!({ "type": "function", "name": name }),
+            }),
+        );
+        insert("truncation", self.truncation.map(|v| json!(v)));
+        insert(
+            "parallel_tool_calls",
+            self.parallel_tool_calls.map(|v| json!(v)),
+        );
+        insert("store", self.store.map(|v| json!(v)));
+        insert(
+            "metadata",
+            (!self.metadata.is_empty()).then(|| json!(self.metadata)),
+        );
+        insert("reasoning", self.reasoning.as_ref().map(|v| json!(v)));
+        insert("text", self.text.as_ref().map(|v| json!(v)));
+        insert("seed", self.seed.map(|v| json!(v)));
+        insert("user", self.user.as_ref().map(|v| json!(v)));
+        map
+    }
+}
+
+/// Setters shared by [`ResponsesClient`] and [`ResponsesRequest`], which
+/// both keep their settings in `self.settings`.
+macro_rules! responses_setters {
+    () => {
+        /// System-level instructions for the model.
+        pub fn instructions(mut self, v: impl Into<String>) -> Self {
+            self.settings.instructions = Some(v.into());
+            self
+        }
+
+        /// Set the sampling temperature.
+        pub fn temperature(mut self, v: f64) -> Self {
+            self.settings.temperature = Some(v);
+            self
+        }
+
+        /// Set the nucleus sampling probability.
+        pub fn top_p(mut self, v: f64) -> Self {
+            self.settings.top_p = Some(v);
+            self
+        }
+
+        /// Set the maximum number of output tokens, reasoning included.
+        pub fn max_output_tokens(mut self, v: u32) -> Self {
+            self.settings.max_output_tokens = Some(v);
+            self
+        }
+
+        /// Set the frequency penalty.
+        pub fn frequency_penalty(mut self, v: f64) -> Self {
+            self.settings.frequency_penalty = Some(v);
+            self
+        }
+
+        /// Set the presence penalty.
+        pub fn presence_penalty(mut self, v: f64) -> Self {
+            self.settings.presence_penalty = Some(v);
+            self
+        }
+
+        /// Set the tool choice strategy.
+        pub fn tool_choice(mut self, v: ChatToolChoice) -> Self {
+            self.settings.tool_choice = Some(v);
+            self
+        }
+
+        /// Set how input exceeding the context window is shortened.
+        pub fn truncation(mut self, v: Truncation) -> Self {
+            self.settings.truncation = Some(v);
+            self
+        }
+
+        /// Allow the model to request several tool calls in one reply.
+        pub fn parallel_tool_calls(mut self, v: bool) -> Self {
+            self.settings.parallel_tool_calls = Some(v);
+            self
+        }
+
+        /// Store responses so they can be fetched, continued and deleted.
+        pub fn store(mut self, v: bool) -> Self {
+            self.settings.store = Some(v);
+            self
+        }
+
+        /// Add an entry to the request `metadata`.
+        pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+            self.settings.metadata.insert(key.into(), value.into());
+            self
+        }
+
+        /// Set the reasoning options of reasoning models.
+        pub fn reasoning(mut self, v: ReasoningConfig) -> Self {
+            self.settings.reasoning = Some(v);
+            self
+        }
+
+        /// Set the output text options.
+        pub fn text(mut self, v: TextConfig) -> Self {
+            self.settings.text = Some(v);
+            self
+        }
+
+        /// Set the random seed.
+        pub fn seed(mut self, v: u64) -> Self {
+            self.settings.seed = Some(v);
+            self
+        }
+
+        /// Set an identifier for the end user.
+        pub fn user(mut self, v: impl Into<String>) -> Self {
+            self.settings.user = Some(v.into());
+            self
+        }
+    };
+}
+
+/// Client for the OpenAI Responses API served by the local web service.
+///
+/// Unlike [`ChatClient`](super::ChatClient), which calls the native core
+/// directly, this client talks HTTP to the URL of
+/// [`FoundryLocalManager::start_web_service`](crate::FoundryLocalManager::start_web_service).
+pub struct ResponsesClient {
+    base_url: String,
+    model_id: Option<String>,
+    http: reqwest::Client,
+    settings: ResponsesClientSettings,
+    usage: Option<UsageTracker>,
+}
+
+impl ResponsesClient {
+    /// Create a client for the web service at `base_url`, for example
+    /// `http://127.0.0.1:5273`.  `model_id` is the default model of
+    /// requests.
+    ///
+    /// The client uses default HTTP settings; clients created by
+    /// [`FoundryLocalManager`](crate::FoundryLocalManager) or a model use
+    /// the configured [`HttpSettings`](crate::HttpSettings).
+    pub fn new(base_url: &str, model_id: Option<&str>) -> Result<Self> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if base_url.is_empty() {
+            return Err(FoundryLocalError::Validation {
+                reason: "base_url must be a non-empty string".into(),
+            });
+        }
+        Ok(Self {
+            base_url: base_url.to_owned(),
+            model_id: model_id.map(str::to_owned),
+            http: reqwest::Client::new(),
+            settings: ResponsesClientSettings::default(),
+            usage: None,
+        })
+    }
+
+    /// Send requests with `http`, built from the manager's
+    /// [`HttpSettings`](crate::HttpSettings).
+    pub(crate) fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Count requests from this client as use of the model.
+    pub(crate) fn with_usage_tracker(mut self, tracker: UsageTracker) -> Self {
+        self.usage = Some(tracker);
+        self
+    }
+
+    responses_setters!();
+
+    /// Start a request whose settings can be overridden for it alone.
+    pub fn request(&self, input: impl Into<ResponseInput>) -> ResponsesRequest<'_> {
+        ResponsesRequest {
+            client: self,
+            input: input.into(),
+            model: None,
+            tools: Vec::new(),
+            previous_response_id: None,
+            settings: self.settings.clone(),
+        }
+    }
+
+    /// Create a response to `input`, a prompt or a list of
+    /// [`ResponseItem`]s.
+    pub async fn create(&self, input: impl Into<ResponseInput>) -> Result<ResponseObject> {
+        self.request(input).send().await
+    }
+
+    /// Create a response to `input`, streaming its events.
+    pub async fn create_streaming(
+        &self,
+        input: impl Into<ResponseInput>,
+    ) -> Result<ResponseStream> {
+        self.request(input).stream().await
+    }
+
+    /// Fetch the stored response `response_id`.
+    pub async fn get(&self, response_id: &str) -> Result<ResponseObject> {
+        let path = self.response_path(response_id, "")?;
+        self.send_json(self.http.get(path)).await
+    }
+
+    /// Delete the stored response `response_id`.
+    pub async fn delete(&self, response_id: &str) -> Result<DeleteResponseResult> {
+        let path = self.response_path(response_id, "")?;
+        self.send_json(self.http.delete(path)).await
+    }
+
+    /// Cancel the in-progress response `response_id`.
+    pub async fn cancel(&self, response_id: &str) -> Result<ResponseObject> {
+        let path = self.response_path(response_id, "/cancel")?;
+        self.send_json(self.http.post(path)).await
+    }
+
+    /// The input items of the stored response `response_id`.
+    pub async fn input_items(&self, response_id: &str) -> Result<InputItemsList> {
+        let path = self.response_path(response_id, "/input_items")?;
+        self.send_json(self.http.get(path)).await
+    }
+
+    fn response_path(&self, response_id: &str, suffix: &str) -> Result<String> {
+        if response_id.trim().is_empty() {
+            return Err(FoundryLocalError::Validation {
+                reason: "response_id must be a non-empty string".into(),
+            });
+        }
+        if response_id.len() > MAX_ID_LEN {
+            return Err(FoundryLocalError::Validation {
+                reason: format!("response_id exceeds the maximum length ({MAX_ID_LEN})"),
+            });
+        }
+        let id = urlencoding::encode(response_id);
+        Ok(format!("{}/v1/responses/{id}{suffix}", self.base_url))
+    }
+
+    fn create_url(&self) -> String {
+        format!("{}/v1/responses", self.base_url)
+    }
+
+    async fn send_json<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+        let text = send(request).await?.text().await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    fn touch(&self) {
+        if let Some(usage) = &self.usage {
+            usage.touch();
+        }
+    }
+}
+
+/// A single Responses API request with its own settings.
+///
+/// Created by [`ResponsesClient::request`] with a copy of the client's
+/// settings; the setters change this request only.
+pub struct ResponsesRequest<'a> {
+    client: &'a ResponsesClient,
+    input: ResponseInput,
+    model: Option<String>,
+    tools: Vec<ResponseTool>,
+    previous_response_id: Option<String>,
+    settings: ResponsesClientSettings,
+}
+
+impl ResponsesRequest<'_> {
+    responses_setters!();
+
+    /// Use `model` instead of the client's model.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Offer `tools` to the model.
+    pub fn tools(mut self, tools: impl IntoIterator<Item = ResponseTool>) -> Self {
+        self.tools.extend(tools);
+        self
+    }
+
+    /// Continue the conversation of the stored response `id`.
+    pub fn previous_response_id(mut self, id: impl Into<String>) -> Self {
+        self.previous_response_id = Some(id.into());
+        self
+    }
+
+    /// Perform the request, returning the complete response.
+    pub async fn send(self) -> Result<ResponseObject> {
+        let client = self.client;
+        let body = self.into_body(false)?;
+        client.touch();
+        client
+            .send_json(client.http.post(client.create_url()).json(&body))
+            .await
+    }
+
+    /// Perform the request, streaming its events.
+    pub async fn stream(self) -> Result<ResponseStream> {
+        let client = self.client;
+        let body = self.into_body(true)?;
+        client.touch();
+        let request = client
+            .http
+            .post(client.create_url())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .json(&body);
+        let response = send(request).await?;
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(forward_events(response, tx));
+        Ok(ResponseStream::new(rx))
+    }
+
+    fn into_body(self, stream: bool) -> Result<Value> {
+        validate_input(&self.input)?;
+        if let Some(name) = self.tools.iter().find_map(|tool| match tool {
+            ResponseTool::Function { name, .. } => name.trim().is_empty().then_some(name),
+        }) {
+            return Err(FoundryLocalError::Validation {
+                reason: format!("tool name must be a non-empty string, got '{name}'"),
+            });
+        }
+        let Some(model) = self.model.or_else(|| self.client.model_id.clone()) else {
+            return Err(FoundryLocalError::Validation {
+                reason: "no model given to the ResponsesClient or the request".into(),
+            });
+        };
+
+        let mut map = self.settings.serialize();
+        map.insert("model".into(), json!(model));
+        map.insert("input".into(), serde_json::to_value(&self.input)?);
+        if !self.tools.is_empty() {
+            map.insert("tools".into(), serde_json::to_value(&self.tools)?);
+        }
+        if let Some(id) = self.previous_response_id {
+            map.insert("previous_response_id".into(), json!(id));
+        }
+        map.insert("stream".into(), json!(stream));
+        Ok(Value::Object(map))
+    }
+}
+
+impl ResponseStream {
+    /// Drain the stream and return the final response of its
+    /// `response.completed`, `response.failed` or `response.incomplete`
+    /// event.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error the stream yields, or a `ModelOperation`
+    /// error for an `error` event or a stream without a final response.
+    pub async fn collect(mut self) -> Result<ResponseObject> {
+        use tokio_stream::StreamExt;
+
+        let mut last = None;
+        while let Some(event) = self.next().await {
+            match event? {
+                ResponseStreamEvent::Completed { response, .. }
+                | ResponseStreamEvent::Failed { response, .. }
+                | ResponseStreamEvent::Incomplete { response, .. } => last = Some(*response),
+                ResponseStreamEvent::Error { code, message, .. } => {
+                    return Err(FoundryLocalError::ModelOperation {
+                        reason: format!(
+                            "response stream error {}: {}",
+                            code.as_deref().unwrap_or("unknown"),
+                            message.as_deref().unwrap_or("")
+                        ),
+                    });
+                }
+                _ => {}
+            }
+        }
+        last.ok_or_else(|| FoundryLocalError::ModelOperation {
+            reason: "response stream ended without a final response".into(),
+        })
+    }
+}
+
+fn validate_input(input: &ResponseInput) -> Result<()> {
+    let reason = match input {
+        ResponseInput::Text(text) if text.trim().is_empty() => "input text must not be empty",
+        ResponseInput::Items(items) if items.is_empty() => "input items must not be empty",
+        ResponseInput::Items(items) if items.contains(&ResponseItem::Unknown) => {
+            "input items must have a known type"
+        }
+        _ => return Ok(()),
+    };
+    Err(FoundryLocalError::Validation {
+        reason: reason.into(),
+    })
+}
+
+/// Send `request`, turning error statuses into errors carrying the body.
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(FoundryLocalError::ModelOperation {
+        reason: format!("Responses API error ({status}): {body}"),
+    })
+}
+
+/// Forward the `data` of each server-sent event until `[DONE]`.
+async fn forward_events(
+    mut response: reqwest::Response,
+    tx: tokio::sync::mpsc::UnboundedSender<Result<String>>,
+) {
+    let mut parser = SseParser::default();
+    loop {
+        let (events, ended) = match response.chunk().await {
+            Ok(Some(chunk)) => (parser.push(&chunk), false),
+            Ok(None) => (parser.finish().into_iter().collect(), true),
+            Err(e) => {
+                let _ = tx.send(Err(e.into()));
+                return;
+            }
+        };
+        for data in events {
+            if data == "[DONE]" || tx.send(Ok(data)).is_err() {
+                return;
+            }
+        }
+        if ended {
+            return;
+        }
+    }
+}
+
+/// Splits a server-sent event stream into the `data` of each event.
+///
+/// Multiple `data:` lines of one event are joined with `\n`; other fields
+/// are ignored, since Responses events carry their type in the JSON.
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    /// Add received bytes, returning the data of every completed event.
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some((end, len)) = find_event_end(&self.buffer) {
+            let block: Vec<u8> = self.buffer.drain(..end + len).take(end).collect();
+            events.extend(event_data(&String::from_utf8_lossy(&block)));
+        }
+        events
+    }
+
+    /// End the stream, returning the data of a final unterminated event.
+    fn finish(&mut self) -> Option<String> {
+        let block = std::mem::take(&mut self.buffer);
+        event_data(&String::from_utf8_lossy(&block))
+    }
+}
+
+/// Position and length of the first blank line ending an event.
+fn find_event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    (0..buffer.len()).find_map(|i| {
+        let rest = &buffer[i..];
+        if rest.starts_with(b"\r\n\r\n") {
+            Some((i, 4))
+        } else if rest.starts_with(b"\n\n") || rest.starts_with(b"\r\r") {
+            Some((i, 2))
+        } else {
+            None
+        }
+    })
+}
+
+fn event_data(block: &str) -> Option<String> {
+    let lines: Vec<&str> = block
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai::responses_types::{MessageRole, ResponseStatus};
+
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// A recorded request: method, path and body.
+    type Recorded = Arc<Mutex<Vec<(String, String, String)>>>;
+
+    /// Serve one canned `(status, content type, body)` reply per request
+    /// on a local port, recording the requests.
+    fn mock_server(replies: Vec<(u16, &'static str, String)>) -> (String, Recorded) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let recorded = Recorded::default();
+        let log = Arc::clone(&recorded);
+        std::thread::spawn(move || {
+            for (status, content_type, body) in replies {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    let header = header.to_ascii_lowercase();
+                    if let Some(v) = header.strip_prefix("content-length:") {
+                        length = v.trim().parse().unwrap();
+                    }
+                }
+                let mut request_body = vec![0; length];
+                reader.read_exact(&mut request_body).unwrap();
+                let mut parts = request_line.split_whitespace();
+                log.lock().unwrap().push((
+                    parts.next().unwrap().to_owned(),
+                    parts.next().unwrap().to_owned(),
+                    String::from_utf8(request_body).unwrap(),
+                ));
+
+                let mut stream = reader.into_inner();
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Type: {content_type}\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (url, recorded)
+    }
+
+    fn response_json(status: &str, text: &str) -> String {
+        json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": status,
+            "model": "m",
+            "output": [{
+                "type": "message", "role": "assistant",
+                "content": [{ "type": "output_text", "text": text }]
+            }]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn parses_sse_events_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"event: a\ndata: {\"x\":").is_empty());
+        assert_eq!(
+            parser.push(b"1}\n\ndata: one\r\ndata: two\r\n\r\ndata: [DO"),
+            ["{\"x\":1}", "one\ntwo"]
+        );
+        assert_eq!(parser.push(b"NE]\n\n"), ["[DONE]"]);
+        assert_eq!(parser.push(b"data:tail"), Vec::<String>::new());
+        assert_eq!(parser.finish().as_deref(), Some("tail"));
+    }
+
+    #[tokio::test]
+    async fn creates_responses_with_merged_settings() {
+        let (url, recorded) = mock_server(vec![(
+            200,
+            "application/json",
+            response_json("completed", "Hi there"),
+        )]);
+        let client = ResponsesClient::new(&format!("{url}/"), Some("m"))
+            .unwrap()
+            .instructions("Be brief.")
+            .temperature(0.5)
+            .tool_choice(ChatToolChoice::Function("add".into()));
+
+        let response = client
+            .request(vec![ResponseItem::message(MessageRole::User, "Hello")])
+            .temperature(0.0)
+            .previous_response_id("resp_0")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status, ResponseStatus::Completed);
+        assert_eq!(response.output_text(), "Hi there");
+
+        let (method, path, body) = recorded.lock().unwrap().remove(0);
+        assert_eq!((method.as_str(), path.as_str()), ("POST", "/v1/responses"));
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "model": "m",
+                "input": [{ "type": "message", "role": "user", "content": "Hello" }],
+                "instructions": "Be brief.",
+                "temperature": 0.0,
+                "tool_choice": { "type": "function", "name": "add" },
+                "previous_response_id": "resp_0",
+                "stream": false
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_events_and_collects_the_final_response() {
+        let sse = format!(
+            "event: response.created\ndata: {{\"type\":\"response.created\",\"response\":{}}}\n\n\
+             data: {{\"type\":\"response.output_text.delta\",\"item_id\":\"m1\",\
+             \"output_index\":0,\"content_index\":0,\"delta\":\"Hi\"}}\n\n\
+             data: {{\"type\":\"response.completed\",\"response\":{}}}\n\n\
+             data: [DONE]\n\n",
+            response_json("in_progress", ""),
+            response_json("completed", "Hi")
+        );
+        let (url, _) = mock_server(vec![
+            (200, "text/event-stream", sse.clone()),
+            (200, "text/event-stream", sse),
+        ]);
+        let client = ResponsesClient::new(&url, Some("m")).unwrap();
+
+        let mut stream = client.create_streaming("Say hi").await.unwrap();
+        let mut deltas = String::new();
+        while let Some(event) = tokio_stream::StreamExt::next(&mut stream).await {
+            if let ResponseStreamEvent::OutputTextDelta { delta, .. } = event.unwrap() {
+                deltas.push_str(&delta);
+            }
+        }
+        assert_eq!(deltas, "Hi");
+
+        let response = client
+            .create_streaming("Say hi")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(response.output_text(), "Hi");
+    }
+
+    #[tokio::test]
+    async fn manages_stored_responses() {
+        let (url, recorded) = mock_server(vec![
+            (200, "application/json", response_json("completed", "a")),
+            (200, "application/json", response_json("cancelled", "")),
+            (
+                200,
+                "application/json",
+                json!({ "object": "list", "data": [
+                    { "type": "message", "role": "user", "content": "hi" }
+                ]})
+                .to_string(),
+            ),
+            (
+                200,
+                "application/json",
+                json!({ "id": "resp 1", "object": "response.deleted", "deleted": true })
+                    .to_string(),
+            ),
+            (404, "text/plain", "no such response".into()),
+        ]);
+        let client = ResponsesClient::new(&url, None).unwrap();
+
+        assert_eq!(client.get("resp 1").await.unwrap().output_text(), "a");
+        let cancelled = client.cancel("resp 1").await.unwrap();
+        assert_eq!(cancelled.status, ResponseStatus::Cancelled);
+        assert_eq!(client.input_items("resp 1").await.unwrap().data.len(), 1);
+        assert!(client.delete("resp 1").await.unwrap().deleted);
+        let err = client.get("missing").await.unwrap_err();
+        assert!(err.to_string().contains("404"), "{err}");
+        assert!(err.to_string().contains("no such response"), "{err}");
+
+        let requests: Vec<_> = recorded
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, path, _)| format!("{method} {path}"))
+            .collect();
+        assert_eq!(
+            requests,
+            [
+                "GET /v1/responses/resp%201",
+                "POST /v1/responses/resp%201/cancel",
+                "GET /v1/responses/resp%201/input_items",
+                "DELETE /v1/responses/resp%201",
+                "GET /v1/responses/missing"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_requests_before_sending() {
+        let client = ResponsesClient::new("http://127.0.0.1:9", None).unwrap();
+        for result in [
+            client.create("hi").await,
+            client.request("  ").model("m").send().await,
+            client.request(Vec::new()).model("m").send().await,
+            client.get("").await,
+        ] {
+            assert!(matches!(result, Err(FoundryLocalError::Validation { .. })));
+        }
+        assert!(ResponsesClient::new(" / ", None).is_err());
+    }
+}
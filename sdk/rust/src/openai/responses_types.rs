@@ -0,0 +1,721 @@
+//! Request and response types of the OpenAI Responses API, as served by the
+//! Foundry Local web service.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Status of a [`ResponseObject`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Failed,
+    Incomplete,
+    Cancelled,
+}
+
+/// Status of an individual [`ResponseItem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseItemStatus {
+    InProgress,
+    Completed,
+    Incomplete,
+}
+
+/// Author of a [`MessageItem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    System,
+    User,
+    Assistant,
+    Developer,
+}
+
+/// How the service shortens input that exceeds the context window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Truncation {
+    Auto,
+    Disabled,
+}
+
+/// A part of a message's content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseContentPart {
+    /// Text written by the user.
+    InputText { text: String },
+    /// An image for vision models, as base64 `image_data` with its
+    /// `media_type`, or as an `image_url`.
+    InputImage {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image_data: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        media_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image_url: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+    /// Text written by the model.
+    OutputText {
+        text: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<Annotation>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        logprobs: Option<Vec<LogProb>>,
+    },
+    /// The model declined to answer.
+    Refusal { refusal: String },
+    /// A part type this SDK does not know.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ResponseContentPart {
+    /// Input text part.
+    pub fn input_text(text: impl Into<String>) -> Self {
+        Self::InputText { text: text.into() }
+    }
+
+    /// Base64 image part in the Foundry `image_data` form.
+    pub fn input_image(image_data: impl Into<String>, media_type: impl Into<String>) -> Self {
+        Self::InputImage {
+            image_data: Some(image_data.into()),
+            media_type: Some(media_type.into()),
+            image_url: None,
+            detail: None,
+        }
+    }
+
+    /// The text of an input text, output text or refusal part.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Self::InputText { text } | Self::OutputText { text, .. } => Some(text),
+            Self::Refusal { refusal } => Some(refusal),
+            _ => None,
+        }
+    }
+}
+
+/// A citation or other span annotation of output text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub start_index: u32,
+    #[serde(default)]
+    pub end_index: u32,
+    /// Cited URL of a `url_citation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Title of a `url_citation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Log probability of an output token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogProb {
+    pub token: String,
+    pub logprob: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<Vec<u8>>,
+}
+
+/// Content of a message or tool output: plain text or typed parts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResponseContent {
+    Text(String),
+    Parts(Vec<ResponseContentPart>),
+}
+
+impl ResponseContent {
+    /// The text, with the text of all parts concatenated.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Parts(parts) => parts.iter().filter_map(ResponseContentPart::text).collect(),
+        }
+    }
+}
+
+impl From<&str> for ResponseContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+impl From<String> for ResponseContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Vec<ResponseContentPart>> for ResponseContent {
+    fn from(parts: Vec<ResponseContentPart>) -> Self {
+        Self::Parts(parts)
+    }
+}
+
+/// A message in the input or output of a response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub role: MessageRole,
+    pub content: ResponseContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ResponseItemStatus>,
+}
+
+/// A function call requested by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCallItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub call_id: String,
+    pub name: String,
+    /// The arguments as a JSON string.
+    pub arguments: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ResponseItemStatus>,
+}
+
+/// The result of a [`FunctionCallItem`], sent back as input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCallOutputItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub call_id: String,
+    pub output: ResponseContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ResponseItemStatus>,
+}
+
+/// Thinking of a reasoning model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReasoningItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<Vec<ResponseContentPart>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_content: Option<String>,
+    /// A summary string, or an array of summary parts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ResponseItemStatus>,
+}
+
+/// An input or output item of a response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseItem {
+    Message(MessageItem),
+    FunctionCall(FunctionCallItem),
+    FunctionCallOutput(FunctionCallOutputItem),
+    /// A reference to an item of a stored response.
+    ItemReference {
+        id: String,
+    },
+    Reasoning(ReasoningItem),
+    /// An item type this SDK does not know.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ResponseItem {
+    /// A message input item.
+    pub fn message(role: MessageRole, content: impl Into<ResponseContent>) -> Self {
+        Self::Message(MessageItem {
+            id: None,
+            role,
+            content: content.into(),
+            status: None,
+        })
+    }
+
+    /// The output of the function call `call_id`.
+    pub fn function_call_output(call_id: impl Into<String>, output: impl Into<String>) -> Self {
+        Self::FunctionCallOutput(FunctionCallOutputItem {
+            id: None,
+            call_id: call_id.into(),
+            output: ResponseContent::Text(output.into()),
+            status: None,
+        })
+    }
+}
+
+/// The `input` of a response: a prompt or a list of items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResponseInput {
+    Text(String),
+    Items(Vec<ResponseItem>),
+}
+
+impl From<&str> for ResponseInput {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+impl From<String> for ResponseInput {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Vec<ResponseItem>> for ResponseInput {
+    fn from(items: Vec<ResponseItem>) -> Self {
+        Self::Items(items)
+    }
+}
+
+/// A tool the model may call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseTool {
+    Function {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// JSON schema of the arguments.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parameters: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+}
+
+impl ResponseTool {
+    /// A function tool taking arguments described by the JSON schema
+    /// `parameters`.
+    pub fn function(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+    ) -> Self {
+        Self::Function {
+            name: name.into(),
+            description: Some(description.into()),
+            parameters: Some(parameters),
+            strict: None,
+        }
+    }
+}
+
+/// Reasoning options for reasoning models.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReasoningConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// Format of the output text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextFormat {
+    /// `text`, `json_object` or `json_schema`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Output text options.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<TextFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<String>,
+}
+
+/// Token usage of a response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub total_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens_details: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens_details: Option<Value>,
+}
+
+/// Why a response failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Why a response is incomplete.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncompleteDetails {
+    pub reason: String,
+}
+
+/// A model response.
+///
+/// The service answers model-level failures with HTTP 200 too, so check
+/// [`status`](Self::status) and [`error`](Self::error).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseObject {
+    pub id: String,
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub completed_at: Option<u64>,
+    #[serde(default)]
+    pub failed_at: Option<u64>,
+    #[serde(default)]
+    pub cancelled_at: Option<u64>,
+    pub status: ResponseStatus,
+    #[serde(default)]
+    pub incomplete_details: Option<IncompleteDetails>,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub previous_response_id: Option<String>,
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub output: Vec<ResponseItem>,
+    #[serde(default)]
+    pub error: Option<ResponseError>,
+    #[serde(default)]
+    pub tools: Vec<ResponseTool>,
+    /// `none`, `auto`, `required` or `{"type": "function", "name": ...}`.
+    #[serde(default)]
+    pub tool_choice: Option<Value>,
+    #[serde(default)]
+    pub truncation: Option<Truncation>,
+    #[serde(default)]
+    pub parallel_tool_calls: Option<bool>,
+    #[serde(default)]
+    pub text: Option<TextConfig>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub presence_penalty: Option<f64>,
+    #[serde(default)]
+    pub frequency_penalty: Option<f64>,
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    #[serde(default)]
+    pub reasoning: Option<ReasoningConfig>,
+    #[serde(default)]
+    pub store: Option<bool>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub usage: Option<ResponseUsage>,
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+impl ResponseObject {
+    /// The `output_text` parts of all assistant messages, concatenated;
+    /// refusals are left out.
+    pub fn output_text(&self) -> String {
+        let mut out = String::new();
+        for item in &self.output {
+            let ResponseItem::Message(m) = item else {
+                continue;
+            };
+            if m.role != MessageRole::Assistant {
+                continue;
+            }
+            match &m.content {
+                ResponseContent::Text(text) => out.push_str(text),
+                ResponseContent::Parts(parts) => {
+                    for part in parts {
+                        if let ResponseContentPart::OutputText { text, .. } = part {
+                            out.push_str(text);
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Function calls requested by the model.
+    pub fn function_calls(&self) -> impl Iterator<Item = &FunctionCallItem> {
+        self.output.iter().filter_map(|item| match item {
+            ResponseItem::FunctionCall(call) => Some(call),
+            _ => None,
+        })
+    }
+}
+
+/// Input items of a stored response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputItemsList {
+    pub data: Vec<ResponseItem>,
+}
+
+/// Result of deleting a stored response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeleteResponseResult {
+    pub id: String,
+    pub deleted: bool,
+}
+
+/// A server-sent event of a streaming response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseStreamEvent {
+    #[serde(rename = "response.created")]
+    Created {
+        response: Box<ResponseObject>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.queued")]
+    Queued {
+        response: Box<ResponseObject>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.in_progress")]
+    InProgress {
+        response: Box<ResponseObject>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.completed")]
+    Completed {
+        response: Box<ResponseObject>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.failed")]
+    Failed {
+        response: Box<ResponseObject>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.incomplete")]
+    Incomplete {
+        response: Box<ResponseObject>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded {
+        #[serde(default)]
+        item_id: Option<String>,
+        output_index: u32,
+        item: ResponseItem,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone {
+        #[serde(default)]
+        item_id: Option<String>,
+        output_index: u32,
+        item: ResponseItem,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.content_part.added")]
+    ContentPartAdded {
+        item_id: String,
+        content_index: u32,
+        part: ResponseContentPart,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.content_part.done")]
+    ContentPartDone {
+        item_id: String,
+        content_index: u32,
+        part: ResponseContentPart,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        item_id: String,
+        #[serde(default)]
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_text.done")]
+    OutputTextDone {
+        item_id: String,
+        #[serde(default)]
+        output_index: u32,
+        content_index: u32,
+        text: String,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.refusal.delta")]
+    RefusalDelta {
+        item_id: String,
+        content_index: u32,
+        delta: String,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.refusal.done")]
+    RefusalDone {
+        item_id: String,
+        content_index: u32,
+        refusal: String,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: u32,
+        delta: String,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        item_id: String,
+        output_index: u32,
+        arguments: String,
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    /// An error reported in the stream.
+    #[serde(rename = "error")]
+    Error {
+        #[serde(default)]
+        code: Option<String>,
+        #[serde(default)]
+        message: Option<String>,
+        #[serde(default)]
+        param: Option<String>,
+        #[serde(default)]
+        sequence_number: u64,
+    },
+    /// An event type this SDK does not know.
+    #[serde(other)]
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_output_items_and_text() {
+        let response: ResponseObject = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": "completed",
+            "model": "m",
+            "output": [
+                { "type": "reasoning", "id": "rs_1", "summary": [] },
+                {
+                    "type": "function_call", "call_id": "c1",
+                    "name": "add", "arguments": "{\"a\":1}"
+                },
+                {
+                    "type": "message", "role": "assistant", "status": "completed",
+                    "content": [
+                        { "type": "output_text", "text": "Hello, ", "annotations": [] },
+                        { "type": "output_text", "text": "world" }
+                    ]
+                },
+                { "type": "web_search_call", "id": "ws_1" },
+                {
+                    "type": "message", "role": "assistant", "status": "completed",
+                    "content": [
+                        { "type": "refusal", "refusal": "No." },
+                        { "type": "output_text", "text": "!" }
+                    ]
+                }
+            ],
+            "tool_choice": "auto",
+            "usage": { "input_tokens": 3, "output_tokens": 2, "total_tokens": 5 }
+        }))
+        .unwrap();
+
+        assert_eq!(response.output_text(), "Hello, world!");
+        assert_eq!(response.function_calls().next().unwrap().name, "add");
+        assert_eq!(response.output[3], ResponseItem::Unknown);
+        assert_eq!(response.usage.unwrap().total_tokens, 5);
+    }
+
+    #[test]
+    fn serializes_input_items() {
+        let input = ResponseInput::from(vec![
+            ResponseItem::message(
+                MessageRole::User,
+                vec![
+                    ResponseContentPart::input_text("Describe this image."),
+                    ResponseContentPart::input_image("AAAA", "image/png"),
+                ],
+            ),
+            ResponseItem::function_call_output("c1", "2"),
+        ]);
+        assert_eq!(
+            serde_json::to_value(input).unwrap(),
+            json!([
+                {
+                    "type": "message",
+                    "role": "user",
+                    "content": [
+                        { "type": "input_text", "text": "Describe this image." },
+                        { "type": "input_image", "image_data": "AAAA", "media_type": "image/png" }
+                    ]
+                },
+                { "type": "function_call_output", "call_id": "c1", "output": "2" }
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_stream_events() {
+        let delta: ResponseStreamEvent = serde_json::from_value(json!({
+            "type": "response.output_text.delta", "item_id": "msg_1",
+            "output_index": 0, "content_index": 0, "delta": "Hi", "sequence_number": 4
+        }))
+        .unwrap();
+        assert!(
+            matches!(delta, ResponseStreamEvent::OutputTextDelta { ref delta, .. } if delta == "Hi")
+        );
+
+        let unknown: ResponseStreamEvent =
+            serde_json::from_value(json!({ "type": "response.reasoning.delta", "delta": "x" }))
+                .unwrap();
+        assert_eq!(unknown, ResponseStreamEvent::Unknown);
+    }
+}
@@ -0,0 +1,446 @@
+//! Reassembly of streamed chat completion chunks into a complete response.
+
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_openai::types::chat::{
+    ChatChoice, ChatChoiceLogprobs, ChatCompletionMessageToolCall, ChatCompletionMessageToolCalls,
+    ChatCompletionResponseMessage, ChatCompletionStreamResponseDelta, CompletionUsage,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FinishReason, FunctionCall,
+    Role, ServiceTier,
+};
+use serde_json::Value;
+use tokio_stream::StreamExt;
+
+use super::chat_client::ChatCompletionStream;
+use crate::error::{FoundryLocalError, Result};
+
+/// A tool call from a streamed reply, with its arguments parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedToolCall {
+    pub id: String,
+    pub name: String,
+    /// The arguments object; an empty argument string becomes `{}`.
+    pub arguments: Value,
+}
+
+#[derive(Debug, Default)]
+struct ToolCallState {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+#[derive(Debug, Default)]
+struct ChoiceState {
+    role: Option<Role>,
+    content: Option<String>,
+    refusal: Option<String>,
+    tool_calls: BTreeMap<u32, ToolCallState>,
+    finish_reason: Option<FinishReason>,
+    logprobs: Option<ChatChoiceLogprobs>,
+}
+
+impl ChoiceState {
+    fn push(&mut self, delta: &ChatCompletionStreamResponseDelta) {
+        if delta.role.is_some() {
+            self.role = delta.role;
+        }
+        append(&mut self.content, delta.content.as_deref());
+        append(&mut self.refusal, delta.refusal.as_deref());
+        for chunk in delta.tool_calls.iter().flatten() {
+            let call = self.tool_calls.entry(chunk.index).or_default();
+            if let Some(id) = chunk.id.as_deref().filter(|id| !id.is_empty()) {
+                call.id = id.to_owned();
+            }
+            if let Some(function) = &chunk.function {
+                call.name
+                    .push_str(function.name.as_deref().unwrap_or_default());
+                call.arguments
+                    .push_str(function.arguments.as_deref().unwrap_or_default());
+            }
+        }
+    }
+
+    fn push_logprobs(&mut self, logprobs: &ChatChoiceLogprobs) {
+        let merged = self.logprobs.get_or_insert(ChatChoiceLogprobs {
+            content: None,
+            refusal: None,
+        });
+        if let Some(content) = &logprobs.content {
+            merged
+                .content
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(content);
+        }
+        if let Some(refusal) = &logprobs.refusal {
+            merged
+                .refusal
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(refusal);
+        }
+    }
+
+    fn message(&self) -> ChatCompletionResponseMessage {
+        let tool_calls = (!self.tool_calls.is_empty()).then(|| {
+            self.tool_calls
+                .values()
+                .map(|call| {
+                    ChatCompletionMessageToolCalls::Function(ChatCompletionMessageToolCall {
+                        id: call.id.clone(),
+                        function: FunctionCall {
+                            name: call.name.clone(),
+                            arguments: call.arguments.clone(),
+                        },
+                    })
+                })
+                .collect()
+        });
+        ChatCompletionResponseMessage {
+            content: self.content.clone(),
+            refusal: self.refusal.clone(),
+            tool_calls,
+            annotations: None,
+            role: self.role.unwrap_or(Role::Assistant),
+            #[allow(deprecated)]
+            function_call: None,
+            audio: None,
+        }
+    }
+}
+
+fn append(target: &mut Option<String>, fragment: Option<&str>) {
+    if let Some(fragment) = fragment {
+        target.get_or_insert_with(String::new).push_str(fragment);
+    }
+}
+
+/// Stitches [`CreateChatCompletionStreamResponse`] chunks back into the
+/// [`CreateChatCompletionResponse`] a non-streaming request would return.
+///
+/// Content, refusals and tool-call argument fragments are concatenated per
+/// choice, tool calls are matched by their `index`, and the last finish
+/// reason and usage seen win.
+#[derive(Debug, Default)]
+pub struct ChatCompletionAccumulator {
+    id: String,
+    created: u32,
+    model: String,
+    service_tier: Option<ServiceTier>,
+    system_fingerprint: Option<String>,
+    usage: Option<CompletionUsage>,
+    choices: BTreeMap<u32, ChoiceState>,
+}
+
+impl ChatCompletionAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one chunk.
+    pub fn push(&mut self, chunk: &CreateChatCompletionStreamResponse) {
+        if self.id.is_empty() {
+            self.id.clone_from(&chunk.id);
+        }
+        if self.created == 0 {
+            self.created = chunk.created;
+        }
+        if self.model.is_empty() {
+            self.model.clone_from(&chunk.model);
+        }
+        if chunk.service_tier.is_some() {
+            self.service_tier.clone_from(&chunk.service_tier);
+        }
+        #[allow(deprecated)]
+        if chunk.system_fingerprint.is_some() {
+            self.system_fingerprint
+                .clone_from(&chunk.system_fingerprint);
+        }
+        if chunk.usage.is_some() {
+            self.usage.clone_from(&chunk.usage);
+        }
+        for choice in &chunk.choices {
+            let state = self.choices.entry(choice.index).or_default();
+            state.push(&choice.delta);
+            if let Some(logprobs) = &choice.logprobs {
+                state.push_logprobs(logprobs);
+            }
+            if choice.finish_reason.is_some() {
+                state.finish_reason = choice.finish_reason;
+            }
+        }
+    }
+
+    /// Text of the first choice received so far.
+    pub fn content(&self) -> &str {
+        self.choices
+            .get(&0)
+            .and_then(|c| c.content.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Finish reason of the first choice, once it has arrived.
+    pub fn finish_reason(&self) -> Option<FinishReason> {
+        self.choices.get(&0).and_then(|c| c.finish_reason)
+    }
+
+    /// Tool calls of the first choice with their arguments parsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::Validation`] when a call's arguments are
+    /// not valid JSON, e.g. because the stream ended early.
+    pub fn tool_calls(&self) -> Result<Vec<StreamedToolCall>> {
+        let Some(choice) = self.choices.get(&0) else {
+            return Ok(Vec::new());
+        };
+        choice
+            .tool_calls
+            .values()
+            .map(|call| {
+                let arguments = if call.arguments.trim().is_empty() {
+                    Value::Object(Default::default())
+                } else {
+                    serde_json::from_str(&call.arguments).map_err(|e| {
+                        FoundryLocalError::Validation {
+                            reason: format!(
+                                "arguments of tool call '{}' are not valid JSON: {e}",
+                                call.name
+                            ),
+                        }
+                    })?
+                };
+                Ok(StreamedToolCall {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                    arguments,
+                })
+            })
+            .collect()
+    }
+
+    /// The response assembled from the chunks pushed so far.
+    pub fn response(&self) -> CreateChatCompletionResponse {
+        CreateChatCompletionResponse {
+            id: self.id.clone(),
+            choices: self
+                .choices
+                .iter()
+                .map(|(&index, choice)| ChatChoice {
+                    index,
+                    message: choice.message(),
+                    finish_reason: choice.finish_reason,
+                    logprobs: choice.logprobs.clone(),
+                })
+                .collect(),
+            created: self.created,
+            model: self.model.clone(),
+            service_tier: self.service_tier.clone(),
+            #[allow(deprecated)]
+            system_fingerprint: self.system_fingerprint.clone(),
+            object: "chat.completion".into(),
+            usage: self.usage.clone(),
+        }
+    }
+}
+
+/// A [`ChatCompletionStream`] that yields the first choice's text deltas
+/// as they arrive and accumulates every chunk into a complete response.
+///
+/// ```ignore
+/// let mut stream = client.complete_streaming_chat(&messages, Some(&tools)).await?.accumulate();
+/// while let Some(text) = stream.next().await {
+///     print!("{}", text?);
+/// }
+/// for call in stream.accumulator().tool_calls()? {
+///     println!("{}({})", call.name, call.arguments);
+/// }
+/// ```
+pub struct AccumulatingStream {
+    inner: ChatCompletionStream,
+    accumulator: ChatCompletionAccumulator,
+}
+
+impl AccumulatingStream {
+    /// Everything received so far.
+    pub fn accumulator(&self) -> &ChatCompletionAccumulator {
+        &self.accumulator
+    }
+
+    /// Drain the rest of the stream and return the complete response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error the stream yields.
+    pub async fn collect(mut self) -> Result<CreateChatCompletionResponse> {
+        while let Some(text) = self.next().await {
+            text?;
+        }
+        Ok(self.accumulator.response())
+    }
+}
+
+impl futures_core::Stream for AccumulatingStream {
+    type Item = Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let chunk = match futures_core::ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            };
+            self.accumulator.push(&chunk);
+            let text = chunk
+                .choices
+                .iter()
+                .find(|c| c.index == 0)
+                .and_then(|c| c.delta.content.as_deref())
+                .filter(|text| !text.is_empty());
+            if let Some(text) = text {
+                return Poll::Ready(Some(Ok(text.to_owned())));
+            }
+        }
+    }
+}
+
+impl ChatCompletionStream {
+    /// Yield text deltas while accumulating the complete response.
+    pub fn accumulate(self) -> AccumulatingStream {
+        AccumulatingStream {
+            inner: self,
+            accumulator: ChatCompletionAccumulator::new(),
+        }
+    }
+
+    /// Drain the stream and return the complete response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error the stream yields.
+    pub async fn collect(self) -> Result<CreateChatCompletionResponse> {
+        self.accumulate().collect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn chunk(choices: Value) -> CreateChatCompletionStreamResponse {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 7,
+            "model": "test",
+            "choices": choices
+        }))
+        .unwrap()
+    }
+
+    fn tool_delta(index: u32, id: Option<&str>, name: Option<&str>, arguments: &str) -> Value {
+        json!([{
+            "index": 0,
+            "delta": {
+                "tool_calls": [{
+                    "index": index,
+                    "id": id,
+                    "type": id.map(|_| "function"),
+                    "function": { "name": name, "arguments": arguments }
+                }]
+            }
+        }])
+    }
+
+    #[test]
+    fn concatenates_content_per_choice() {
+        let mut acc = ChatCompletionAccumulator::new();
+        acc.push(&chunk(json!([
+            { "index": 0, "delta": { "role": "assistant", "content": "The answer" } },
+            { "index": 1, "delta": { "role": "assistant", "content": "It is" } }
+        ])));
+        acc.push(&chunk(
+            json!([{ "index": 0, "delta": { "content": " is 42." } }]),
+        ));
+        acc.push(&chunk(
+            json!([{ "index": 1, "delta": { "content": " 42." } }]),
+        ));
+        acc.push(&chunk(json!([
+            { "index": 0, "delta": {}, "finish_reason": "stop" },
+            { "index": 1, "delta": {}, "finish_reason": "length" }
+        ])));
+
+        assert_eq!(acc.content(), "The answer is 42.");
+        assert_eq!(acc.finish_reason(), Some(FinishReason::Stop));
+        let response = acc.response();
+        assert_eq!(response.id, "chatcmpl-1");
+        assert_eq!(response.created, 7);
+        assert_eq!(response.object, "chat.completion");
+        assert_eq!(
+            response.choices[1].message.content.as_deref(),
+            Some("It is 42.")
+        );
+        assert_eq!(
+            response.choices[1].finish_reason,
+            Some(FinishReason::Length)
+        );
+        assert!(response.choices[0].message.tool_calls.is_none());
+    }
+
+    #[test]
+    fn assembles_tool_calls_from_fragments() {
+        let mut acc = ChatCompletionAccumulator::new();
+        acc.push(&chunk(tool_delta(0, Some("call_1"), Some("multiply"), "")));
+        acc.push(&chunk(tool_delta(0, None, None, "{\"a\": 6, ")));
+        acc.push(&chunk(tool_delta(1, Some("call_2"), Some("now"), "")));
+        acc.push(&chunk(tool_delta(0, None, None, "\"b\": 7}")));
+        acc.push(&chunk(
+            json!([{ "index": 0, "delta": {}, "finish_reason": "tool_calls" }]),
+        ));
+
+        let calls = acc.tool_calls().unwrap();
+        assert_eq!(
+            calls,
+            [
+                StreamedToolCall {
+                    id: "call_1".into(),
+                    name: "multiply".into(),
+                    arguments: json!({ "a": 6, "b": 7 }),
+                },
+                StreamedToolCall {
+                    id: "call_2".into(),
+                    name: "now".into(),
+                    arguments: json!({}),
+                },
+            ]
+        );
+
+        let message = &acc.response().choices[0].message;
+        assert_eq!(message.content, None);
+        match &message.tool_calls.as_deref().unwrap()[0] {
+            ChatCompletionMessageToolCalls::Function(call) => {
+                assert_eq!(call.function.arguments, "{\"a\": 6, \"b\": 7}")
+            }
+            other => panic!("unexpected tool call {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reports_truncated_arguments() {
+        let mut acc = ChatCompletionAccumulator::new();
+        acc.push(&chunk(tool_delta(
+            0,
+            Some("call_1"),
+            Some("multiply"),
+            "{\"a\": 6",
+        )));
+        let err = acc.tool_calls().unwrap_err();
+        assert!(
+            err.to_string().contains("'multiply' are not valid JSON"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,433 @@
+//! Chat completions validated as JSON and deserialized into Rust types.
+
+use async_openai::types::chat::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestUserMessage, CreateChatCompletionResponse, FinishReason,
+};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::chat_client::ChatClient;
+use super::json_validation::validate;
+use crate::error::{FoundryLocalError, Result};
+use crate::types::ChatResponseFormat;
+
+/// A JSON reply that passed validation.
+#[derive(Debug, Clone)]
+pub struct JsonCompletion<T = Value> {
+    /// The deserialized reply.
+    pub value: T,
+    /// The response that produced `value`.
+    pub response: CreateChatCompletionResponse,
+    /// Requests made, including the successful one.
+    pub attempts: u32,
+    /// The replies rejected before `value`, oldest first.
+    pub rejected: Vec<RejectedReply>,
+}
+
+/// A reply that failed validation and was sent back for repair.
+#[derive(Debug, Clone)]
+pub struct RejectedReply {
+    /// The model's text.
+    pub raw: String,
+    /// Why the reply was rejected.
+    pub errors: Vec<String>,
+}
+
+impl ChatClient {
+    /// Complete `messages` with the configured JSON response format and
+    /// validate the reply.
+    ///
+    /// With [`ChatResponseFormat::JsonSchema`] the reply must match the
+    /// schema; with [`ChatResponseFormat::JsonObject`] it must be a JSON
+    /// object.  Invalid replies are repaired up to
+    /// [`max_json_attempts`](Self::max_json_attempts).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::Validation`] when no JSON response format
+    /// is configured and [`FoundryLocalError::StructuredOutput`] when the
+    /// last attempt is still invalid.
+    pub async fn complete_json(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<JsonCompletion> {
+        let format = self.response_format_setting().cloned();
+        let schema = match &format {
+            Some(ChatResponseFormat::JsonSchema(schema)) => {
+                Some(serde_json::from_str::<Value>(schema).map_err(|e| {
+                    FoundryLocalError::Validation {
+                        reason: format!("the JSON schema response format is not valid JSON: {e}"),
+                    }
+                })?)
+            }
+            Some(ChatResponseFormat::JsonObject) => None,
+            _ => {
+                return Err(FoundryLocalError::Validation {
+                    reason: "complete_json requires a JsonObject or JsonSchema response format"
+                        .into(),
+                })
+            }
+        };
+        let format = format.unwrap_or(ChatResponseFormat::JsonObject);
+        self.complete_validated(messages, format, schema.as_ref())
+            .await
+    }
+
+    /// Complete `messages` with the output constrained to the JSON schema
+    /// `schema` and deserialize it into `T`, returned as the completion's
+    /// `value`.
+    ///
+    /// The configured response format is ignored for this request.  Replies
+    /// that do not match the schema are repaired up to
+    /// [`max_json_attempts`](Self::max_json_attempts).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::StructuredOutput`], carrying the model's
+    /// text, when the last attempt cannot be deserialized into `T`.
+    pub async fn complete_with_schema<T: DeserializeOwned>(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        schema: &Value,
+    ) -> Result<JsonCompletion<T>> {
+        let format = ChatResponseFormat::JsonSchema(schema.to_string());
+        self.complete_validated(messages, format, Some(schema))
+            .await
+    }
+
+    /// Complete `messages` with the output constrained to the JSON schema
+    /// derived from `T` and deserialize it into `T`, returned as the
+    /// completion's `value`.
+    ///
+    /// ```ignore
+    /// #[derive(Deserialize, JsonSchema)]
+    /// struct Invoice { number: String, total: f64 }
+    ///
+    /// let invoice: Invoice = client.complete_structured(&messages).await?.value;
+    /// ```
+    ///
+    /// Requires the `schemars` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FoundryLocalError::StructuredOutput`], carrying the model's
+    /// text, when the last attempt cannot be deserialized into `T`.
+    #[cfg(feature = "schemars")]
+    pub async fn complete_structured<T>(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<JsonCompletion<T>>
+    where
+        T: schemars::JsonSchema + DeserializeOwned,
+    {
+        self.complete_with_schema(messages, &schema_for::<T>()?)
+            .await
+    }
+
+    /// Request, check and re-prompt until a reply passes or the attempts
+    /// run out.
+    async fn complete_validated<T: DeserializeOwned>(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        format: ChatResponseFormat,
+        schema: Option<&Value>,
+    ) -> Result<JsonCompletion<T>> {
+        let mut repair = Repair::new(messages, schema, self.max_json_attempts_setting());
+        loop {
+            let response = self
+                .complete_chat_with_format(&repair.conversation, format.clone())
+                .await?;
+            if let Some(completion) = repair.accept(response)? {
+                return Ok(completion);
+            }
+        }
+    }
+}
+
+/// The state of a validated completion between requests.
+struct Repair<'a, T> {
+    schema: Option<&'a Value>,
+    max_attempts: u32,
+    /// The messages for the next request.
+    conversation: Vec<ChatCompletionRequestMessage>,
+    rejected: Vec<RejectedReply>,
+    _value: PhantomData<T>,
+}
+
+impl<'a, T: DeserializeOwned> Repair<'a, T> {
+    fn new(
+        messages: &[ChatCompletionRequestMessage],
+        schema: Option<&'a Value>,
+        max_attempts: u32,
+    ) -> Self {
+        Self {
+            schema,
+            max_attempts,
+            conversation: messages.to_vec(),
+            rejected: Vec::new(),
+            _value: PhantomData,
+        }
+    }
+
+    /// Check `response`: the completion when it passes, `None` when the
+    /// conversation now asks for a repair, or an error when the attempts
+    /// have run out.
+    fn accept(
+        &mut self,
+        response: CreateChatCompletionResponse,
+    ) -> Result<Option<JsonCompletion<T>>> {
+        let attempts = self.rejected.len() as u32 + 1;
+        let errors = match check::<T>(&response, self.schema) {
+            Ok(value) => {
+                return Ok(Some(JsonCompletion {
+                    value,
+                    response,
+                    attempts,
+                    rejected: std::mem::take(&mut self.rejected),
+                }))
+            }
+            Err(errors) => errors,
+        };
+
+        let raw = reply_text(&response).to_owned();
+        if attempts >= self.max_attempts {
+            return Err(FoundryLocalError::StructuredOutput {
+                type_name: std::any::type_name::<T>().to_owned(),
+                reason: errors.join("; "),
+                raw,
+                attempts,
+            });
+        }
+        self.conversation.push(
+            ChatCompletionRequestAssistantMessage {
+                content: Some(raw.clone().into()),
+                ..Default::default()
+            }
+            .into(),
+        );
+        self.conversation.push(
+            ChatCompletionRequestUserMessage::from(repair_prompt(&errors, self.schema)).into(),
+        );
+        self.rejected.push(RejectedReply { raw, errors });
+        Ok(None)
+    }
+}
+
+/// The JSON schema derived from `T`.
+#[cfg(feature = "schemars")]
+pub(crate) fn schema_for<T: schemars::JsonSchema>() -> Result<Value> {
+    Ok(serde_json::to_value(schemars::schema_for!(T))?)
+}
+
+/// Parse the first choice of `response`, validate it against `schema` (or
+/// require an object without one) and deserialize it into `T`.
+fn check<T: DeserializeOwned>(
+    response: &CreateChatCompletionResponse,
+    schema: Option<&Value>,
+) -> std::result::Result<T, Vec<String>> {
+    let raw = reply_text(response);
+    if raw.trim().is_empty() {
+        return Err(vec!["the reply is empty".into()]);
+    }
+    let value: Value = serde_json::from_str(json_body(raw)).map_err(|e| {
+        let truncated = response
+            .choices
+            .first()
+            .is_some_and(|c| c.finish_reason == Some(FinishReason::Length));
+        let mut errors = vec![format!("the reply is not valid JSON: {e}")];
+        if truncated {
+            errors.push("the reply was cut off by the max_tokens limit".into());
+        }
+        errors
+    })?;
+
+    let errors = match schema {
+        Some(schema) => validate(schema, &value),
+        None if !value.is_object() => vec!["expected a JSON object".into()],
+        None => Vec::new(),
+    };
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    serde_json::from_value(value).map_err(|e| vec![e.to_string()])
+}
+
+fn reply_text(response: &CreateChatCompletionResponse) -> &str {
+    response
+        .choices
+        .first()
+        .and_then(|c| c.message.content.as_deref())
+        .unwrap_or_default()
+}
+
+fn repair_prompt(errors: &[String], schema: Option<&Value>) -> String {
+    let mut prompt = String::from("Your previous reply was rejected:\n");
+    for error in errors {
+        prompt.push_str("- ");
+        prompt.push_str(error);
+        prompt.push('\n');
+    }
+    match schema {
+        Some(schema) => prompt.push_str(&format!(
+            "Reply again with only the corrected JSON, matching this schema:\n{schema}"
+        )),
+        None => prompt.push_str("Reply again with only the corrected JSON object."),
+    }
+    prompt
+}
+
+/// The JSON in `text`, without the Markdown code fence some models add.
+fn json_body(text: &str) -> &str {
+    let text = text.trim();
+    let Some(fenced) = text.strip_prefix("```") else {
+        return text;
+    };
+    let Some(body) = fenced.strip_suffix("```") else {
+        return text;
+    };
+    // Drop the info string, e.g. "json".
+    match body.find('\n') {
+        Some(newline) => body[newline + 1..].trim(),
+        None => body.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    fn response(content: Option<&str>, finish_reason: &str) -> CreateChatCompletionResponse {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "test",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": finish_reason
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["name", "age"]
+        })
+    }
+
+    #[test]
+    fn parses_plain_and_fenced_json() {
+        let ada = Person {
+            name: "Ada".into(),
+            age: 36,
+        };
+        let plain = r#" {"name": "Ada", "age": 36} "#;
+        let fenced = "```json\n{\"name\": \"Ada\", \"age\": 36}\n```";
+        let schema = person_schema();
+        assert_eq!(
+            check::<Person>(&response(Some(plain), "stop"), Some(&schema)).unwrap(),
+            ada
+        );
+        assert_eq!(
+            check::<Person>(&response(Some(fenced), "stop"), None).unwrap(),
+            ada
+        );
+    }
+
+    #[test]
+    fn reports_schema_and_syntax_errors() {
+        let schema = person_schema();
+        let errors = check::<Person>(&response(Some(r#"{"name": "Ada"}"#), "stop"), Some(&schema))
+            .unwrap_err();
+        assert_eq!(errors, ["/: missing required property 'age'"]);
+
+        let errors =
+            check::<Value>(&response(Some(r#"{"name": "Ad"#), "length"), None).unwrap_err();
+        assert!(errors[0].starts_with("the reply is not valid JSON"));
+        assert!(errors[1].contains("max_tokens"));
+
+        assert_eq!(
+            check::<Value>(&response(Some("[1, 2]"), "stop"), None).unwrap_err(),
+            ["expected a JSON object"]
+        );
+        assert_eq!(
+            check::<Value>(&response(None, "stop"), None).unwrap_err(),
+            ["the reply is empty"]
+        );
+    }
+
+    #[test]
+    fn repair_prompt_lists_errors_and_schema() {
+        let schema = person_schema();
+        let prompt = repair_prompt(
+            &["/: missing required property 'age'".into()],
+            Some(&schema),
+        );
+        assert!(prompt.contains("- /: missing required property 'age'\n"));
+        assert!(prompt.ends_with(&schema.to_string()));
+    }
+
+    #[test]
+    fn repairs_until_a_reply_passes() {
+        let schema = person_schema();
+        let messages = [ChatCompletionRequestUserMessage::from("Who?").into()];
+        let mut repair = Repair::<Person>::new(&messages, Some(&schema), 3);
+
+        let first = repair.accept(response(Some(r#"{"name": "Ada"}"#), "stop"));
+        assert!(first.unwrap().is_none());
+        assert_eq!(repair.conversation.len(), 3);
+
+        let completion = repair
+            .accept(response(Some(r#"{"name": "Ada", "age": 36}"#), "stop"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(completion.value.age, 36);
+        assert_eq!(completion.attempts, 2);
+        assert_eq!(completion.rejected[0].raw, r#"{"name": "Ada"}"#);
+    }
+
+    #[test]
+    fn mismatch_keeps_the_raw_text() {
+        let schema = person_schema();
+        let mut repair = Repair::<Person>::new(&[], Some(&schema), 2);
+        let raw = r#"{"name": "Ada", "age": -1}"#;
+        assert!(repair
+            .accept(response(Some("{}"), "stop"))
+            .unwrap()
+            .is_none());
+
+        match repair.accept(response(Some(raw), "stop")) {
+            Err(FoundryLocalError::StructuredOutput {
+                type_name,
+                reason,
+                raw: text,
+                attempts,
+            }) => {
+                assert!(type_name.ends_with("Person"));
+                assert!(reason.contains("age"));
+                assert_eq!(text, raw);
+                assert_eq!(attempts, 2);
+            }
+            other => panic!("expected StructuredOutput, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Rust functions exposed to the model as tools, driven by
+//! [`ChatClient::run_with_tools`](super::ChatClient::run_with_tools).
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_openai::types::chat::{
+    ChatCompletionMessageToolCalls, ChatCompletionRequestMessage, ChatCompletionRequestToolMessage,
+    ChatCompletionTool, ChatCompletionTools, CreateChatCompletionResponse, FunctionObject,
+};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+/// Rounds of tool calls allowed by default before giving up.
+const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+type ToolFuture = Pin<Box<dyn Future<Output = std::result::Result<String, String>> + Send>>;
+type ToolHandler = dyn Fn(Value) -> ToolFuture + Send + Sync;
+
+struct RegisteredTool {
+    definition: FunctionObject,
+    timeout: Option<Duration>,
+    handler: Arc<ToolHandler>,
+}
+
+/// A set of async Rust functions the model may call.
+///
+/// Each tool has a name, a description and a JSON schema for its
+/// arguments, and is offered to the model by
+/// [`ChatClient::run_with_tools`](super::ChatClient::run_with_tools):
+///
+/// ```ignore
+/// let tools = ToolRegistry::new()
+///     .register(
+///         "multiply",
+///         "Multiply two numbers together.",
+///         json!({
+///             "type": "object",
+///             "properties": { "a": { "type": "number" }, "b": { "type": "number" } },
+///             "required": ["a", "b"]
+///         }),
+///         |args| async move {
+///             let a = args["a"].as_f64().ok_or("a must be a number")?;
+///             let b = args["b"].as_f64().ok_or("b must be a number")?;
+///             Ok::<_, &str>(a * b)
+///         },
+///     )
+///     .timeout(Duration::from_secs(10));
+/// ```
+pub struct ToolRegistry {
+    tools: Vec<RegisteredTool>,
+    timeout: Option<Duration>,
+    max_iterations: usize,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self {
+            tools: Vec::new(),
+            timeout: None,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        }
+    }
+}
+
+impl fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.names().collect::<Vec<_>>())
+            .field("timeout", &self.timeout)
+            .field("max_iterations", &self.max_iterations)
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `handler` as the tool `name`.
+    ///
+    /// `parameters` is the JSON schema of the arguments object the handler
+    /// receives.  The handler's output is sent to the model as-is when it
+    /// serializes to a JSON string and as JSON text otherwise; its error is
+    /// reported to the model so it can correct the call.  Registering a name
+    /// again replaces the earlier tool.
+    pub fn register<F, Fut, T, E>(
+        self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<T, E>> + Send + 'static,
+        T: Serialize,
+        E: fmt::Display,
+    {
+        self.add(name.into(), description.into(), parameters, None, handler)
+    }
+
+    /// Like [`register`](Self::register), with a timeout for this tool that
+    /// overrides the registry-wide [`timeout`](Self::timeout).
+    pub fn register_with_timeout<F, Fut, T, E>(
+        self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+        timeout: Duration,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<T, E>> + Send + 'static,
+        T: Serialize,
+        E: fmt::Display,
+    {
+        self.add(
+            name.into(),
+            description.into(),
+            parameters,
+            Some(timeout),
+            handler,
+        )
+    }
+
+    /// Time allowed for each tool call without its own timeout.  A call that
+    /// runs longer is abandoned and reported to the model as failed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Rounds of tool calls allowed before
+    /// [`run_with_tools`](super::ChatClient::run_with_tools) gives up.
+    /// Defaults to 10.
+    pub fn max_iterations(mut self, rounds: usize) -> Self {
+        self.max_iterations = rounds;
+        self
+    }
+
+    /// Names of the registered tools, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools.iter().map(|t| t.definition.name.as_str())
+    }
+
+    /// The tool definitions to send with a chat request.
+    pub fn definitions(&self) -> Vec<ChatCompletionTools> {
+        self.tools
+            .iter()
+            .map(|t| {
+                ChatCompletionTools::Function(ChatCompletionTool {
+                    function: t.definition.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Run `calls` concurrently and return one tool message per call, in
+    /// the order of `calls`.  Failures become error messages for the model.
+    pub async fn invoke(
+        &self,
+        calls: &[ChatCompletionMessageToolCalls],
+    ) -> Vec<ChatCompletionRequestToolMessage> {
+        let mut outputs: Vec<Option<String>> = vec![None; calls.len()];
+        let mut tasks = JoinSet::new();
+
+        for (index, call) in calls.iter().enumerate() {
+            let (name, arguments) = match call {
+                ChatCompletionMessageToolCalls::Function(c) => {
+                    (c.function.name.as_str(), c.function.arguments.as_str())
+                }
+                ChatCompletionMessageToolCalls::Custom(c) => {
+                    outputs[index] = Some(error_output(&format!(
+                        "'{}' is a custom tool; only function tools are available",
+                        c.custom_tool.name
+                    )));
+                    continue;
+                }
+            };
+            let Some(tool) = self.find(name) else {
+                outputs[index] = Some(error_output(&format!(
+                    "unknown tool '{name}'; available tools: {}",
+                    self.names().collect::<Vec<_>>().join(", ")
+                )));
+                continue;
+            };
+            let args = match parse_arguments(arguments) {
+                Ok(args) => args,
+                Err(e) => {
+                    outputs[index] = Some(error_output(&e));
+                    continue;
+                }
+            };
+
+            let handler = Arc::clone(&tool.handler);
+            let timeout = tool.timeout.or(self.timeout);
+            let name = name.to_owned();
+            tasks.spawn(async move {
+                let call = handler(args);
+                let result = match timeout {
+                    Some(limit) => match tokio::time::timeout(limit, call).await {
+                        Ok(result) => result,
+                        Err(_) => Err(format!("'{name}' timed out after {limit:?}")),
+                    },
+                    None => call.await,
+                };
+                (index, result.unwrap_or_else(|e| error_output(&e)))
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            // A panicking tool leaves its slot empty and is reported below.
+            if let Ok((index, output)) = joined {
+                outputs[index] = Some(output);
+            }
+        }
+
+        calls
+            .iter()
+            .zip(outputs)
+            .map(|(call, output)| ChatCompletionRequestToolMessage {
+                content: output
+                    .unwrap_or_else(|| error_output("the tool panicked"))
+                    .into(),
+                tool_call_id: call_id(call).to_owned(),
+            })
+            .collect()
+    }
+
+    fn add<F, Fut, T, E>(
+        mut self,
+        name: String,
+        description: String,
+        parameters: Value,
+        timeout: Option<Duration>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<T, E>> + Send + 'static,
+        T: Serialize,
+        E: fmt::Display,
+    {
+        let handler: Arc<ToolHandler> = Arc::new(move |args| {
+            let call = handler(args);
+            Box::pin(async move {
+                let value = call.await.map_err(|e| e.to_string())?;
+                format_output(&value)
+            })
+        });
+        self.tools.retain(|t| t.definition.name != name);
+        self.tools.push(RegisteredTool {
+            definition: FunctionObject {
+                name,
+                description: Some(description),
+                parameters: Some(parameters),
+                strict: None,
+            },
+            timeout,
+            handler,
+        });
+        self
+    }
+
+    fn find(&self, name: &str) -> Option<&RegisteredTool> {
+        self.tools.iter().find(|t| t.definition.name == name)
+    }
+
+    pub(crate) fn max_iterations_setting(&self) -> usize {
+        self.max_iterations
+    }
+}
+
+/// Result of [`ChatClient::run_with_tools`](super::ChatClient::run_with_tools).
+#[derive(Debug, Clone)]
+pub struct ToolRun {
+    /// The final response, which calls no tools.
+    pub response: CreateChatCompletionResponse,
+    /// The input messages followed by every assistant message and tool
+    /// result of the run, including the final reply.
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    /// Rounds of tool calls that were executed.
+    pub iterations: usize,
+}
+
+impl ToolRun {
+    /// Text of the final reply.
+    pub fn content(&self) -> Option<&str> {
+        self.response
+            .choices
+            .first()
+            .and_then(|c| c.message.content.as_deref())
+    }
+}
+
+fn call_id(call: &ChatCompletionMessageToolCalls) -> &str {
+    match call {
+        ChatCompletionMessageToolCalls::Function(c) => &c.id,
+        ChatCompletionMessageToolCalls::Custom(c) => &c.id,
+    }
+}
+
+/// Models send `""` for tools without parameters.
+fn parse_arguments(arguments: &str) -> std::result::Result<Value, String> {
+    if arguments.trim().is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+    serde_json::from_str(arguments).map_err(|e| format!("arguments are not valid JSON: {e}"))
+}
+
+fn format_output<T: Serialize>(value: &T) -> std::result::Result<String, String> {
+    match serde_json::to_value(value) {
+        Ok(Value::String(s)) => Ok(s),
+        Ok(other) => Ok(other.to_string()),
+        Err(e) => Err(format!("could not serialize the result: {e}")),
+    }
+}
+
+fn error_output(reason: &str) -> String {
+    format!("Error: {reason}")
+}
+
+#[cfg(test)]
+mod tests {
+    use async_openai::types::chat::ChatCompletionRequestToolMessageContent;
+    use async_openai::types::chat::{ChatCompletionMessageToolCall, FunctionCall};
+    use serde_json::json;
+
+    use super::*;
+
+    fn call(id: &str, name: &str, arguments: &str) -> ChatCompletionMessageToolCalls {
+        ChatCompletionMessageToolCalls::Function(ChatCompletionMessageToolCall {
+            id: id.into(),
+            function: FunctionCall {
+                name: name.into(),
+                arguments: arguments.into(),
+            },
+        })
+    }
+
+    fn text(message: &ChatCompletionRequestToolMessage) -> &str {
+        match &message.content {
+            ChatCompletionRequestToolMessageContent::Text(t) => t,
+            other => panic!("unexpected content {other:?}"),
+        }
+    }
+
+    fn registry() -> ToolRegistry {
+        ToolRegistry::new()
+            .register("multiply", "Multiply.", json!({}), |args| async move {
+                let a = args["a"].as_f64().ok_or("a must be a number")?;
+                let b = args["b"].as_f64().ok_or("b must be a number")?;
+                Ok::<_, &str>(a * b)
+            })
+            .register("greet", "Greet.", json!({}), |_| async {
+                Ok::<_, String>("hello")
+            })
+            .register_with_timeout(
+                "slow",
+                "Never finishes in time.",
+                json!({}),
+                Duration::from_millis(20),
+                |_| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok::<_, String>(json!({ "done": true }))
+                },
+            )
+    }
+
+    #[test]
+    fn definitions_follow_registration_order() {
+        let tools = registry()
+            .register("greet", "Greet again.", json!({}), |_| async {
+                Ok::<_, String>("hi")
+            })
+            .definitions();
+        let names: Vec<_> = tools
+            .iter()
+            .map(|t| match t {
+                ChatCompletionTools::Function(f) => f.function.name.clone(),
+                ChatCompletionTools::Custom(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(names, ["multiply", "slow", "greet"]);
+    }
+
+    #[tokio::test]
+    async fn results_keep_call_order_and_report_errors() {
+        let calls = [
+            call("1", "slow", ""),
+            call("2", "multiply", r#"{"a": 6, "b": 7}"#),
+            call("3", "greet", ""),
+            call("4", "multiply", r#"{"a": "six"}"#),
+            call("5", "divide", "{}"),
+            call("6", "multiply", "{not json"),
+        ];
+        let results = registry().invoke(&calls).await;
+
+        let ids: Vec<_> = results.iter().map(|r| r.tool_call_id.as_str()).collect();
+        assert_eq!(ids, ["1", "2", "3", "4", "5", "6"]);
+        assert!(text(&results[0]).contains("timed out"));
+        assert_eq!(text(&results[1]), "42.0");
+        assert_eq!(text(&results[2]), "hello");
+        assert_eq!(text(&results[3]), "Error: a must be a number");
+        assert!(text(&results[4]).contains("unknown tool 'divide'"));
+        assert!(text(&results[5]).contains("not valid JSON"));
+    }
+}
@@ -0,0 +1,214 @@
+//! The byte-pair encoding model of a Hugging Face `tokenizer.json`.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use serde_json::Value;
+
+use crate::error::{FoundryLocalError, Result};
+
+/// Candidate merges as (rank, position, left id, right id, merged id),
+/// lowest rank and leftmost first.
+type MergeQueue = BinaryHeap<Reverse<(u32, usize, u32, u32, u32)>>;
+
+#[derive(Debug)]
+pub(super) struct Bpe {
+    vocab: HashMap<String, u32>,
+    /// Rank and merged id of each mergeable pair.
+    merges: HashMap<(u32, u32), (u32, u32)>,
+    unk: Option<u32>,
+    fuse_unk: bool,
+    byte_fallback: bool,
+    ignore_merges: bool,
+}
+
+impl Bpe {
+    pub(super) fn from_json(model: &Value) -> Result<Self> {
+        let kind = model.get("type").and_then(Value::as_str).unwrap_or("BPE");
+        if kind != "BPE" {
+            return Err(FoundryLocalError::Validation {
+                reason: format!("unsupported tokenizer model '{kind}'; only BPE is supported"),
+            });
+        }
+        let vocab: HashMap<String, u32> =
+            serde_json::from_value(model.get("vocab").cloned().unwrap_or(Value::Null))?;
+
+        let mut merges = HashMap::new();
+        let entries = model.get("merges").and_then(Value::as_array);
+        for (rank, entry) in entries.into_iter().flatten().enumerate() {
+            let pair = match entry {
+                Value::String(s) => s.split_once(' '),
+                Value::Array(pair) => match (pair.first(), pair.get(1)) {
+                    (Some(Value::String(a)), Some(Value::String(b))) => {
+                        Some((a.as_str(), b.as_str()))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            let Some((left, right)) = pair else {
+                return Err(FoundryLocalError::Validation {
+                    reason: format!("invalid tokenizer merge {entry}"),
+                });
+            };
+            let ids = (
+                vocab.get(left),
+                vocab.get(right),
+                vocab.get(&format!("{left}{right}")),
+            );
+            if let (Some(&a), Some(&b), Some(&merged)) = ids {
+                merges.entry((a, b)).or_insert((rank as u32, merged));
+            }
+        }
+
+        let flag = |name: &str| model.get(name).and_then(Value::as_bool).unwrap_or(false);
+        let unk = model
+            .get("unk_token")
+            .and_then(Value::as_str)
+            .and_then(|t| vocab.get(t).copied());
+        Ok(Self {
+            unk,
+            fuse_unk: flag("fuse_unk"),
+            byte_fallback: flag("byte_fallback"),
+            ignore_merges: flag("ignore_merges"),
+            vocab,
+            merges,
+        })
+    }
+
+    pub(super) fn vocab(&self) -> &HashMap<String, u32> {
+        &self.vocab
+    }
+
+    /// Append the ids of `word` to `out`.
+    pub(super) fn tokenize(&self, word: &str, out: &mut Vec<u32>) {
+        if self.ignore_merges {
+            if let Some(&id) = self.vocab.get(word) {
+                out.push(id);
+                return;
+            }
+        }
+
+        let mut symbols: Vec<u32> = Vec::new();
+        let mut last_unknown = false;
+        for c in word.chars() {
+            let mut buf = [0u8; 4];
+            let s = c.encode_utf8(&mut buf);
+            if let Some(&id) = self.vocab.get(&*s) {
+                symbols.push(id);
+                last_unknown = false;
+                continue;
+            }
+            let bytes: Option<Vec<u32>> = if self.byte_fallback {
+                s.bytes()
+                    .map(|b| self.vocab.get(&format!("<0x{b:02X}>")).copied())
+                    .collect()
+            } else {
+                None
+            };
+            match (bytes, self.unk) {
+                (Some(bytes), _) => {
+                    symbols.extend(bytes);
+                    last_unknown = false;
+                }
+                (None, Some(unk)) => {
+                    if !(self.fuse_unk && last_unknown) {
+                        symbols.push(unk);
+                    }
+                    last_unknown = true;
+                }
+                (None, None) => {}
+            }
+        }
+
+        out.extend(self.merge(symbols));
+    }
+
+    /// Apply merges lowest rank first until none are left.
+    fn merge(&self, ids: Vec<u32>) -> Vec<u32> {
+        if ids.len() < 2 {
+            return ids;
+        }
+        // Symbols form a linked list; merged-away symbols get `None`.
+        let mut symbols: Vec<Option<u32>> = ids.into_iter().map(Some).collect();
+        let mut prev: Vec<Option<usize>> = (0..symbols.len()).map(|i| i.checked_sub(1)).collect();
+        let mut next: Vec<Option<usize>> = (1..=symbols.len())
+            .map(|i| (i < symbols.len()).then_some(i))
+            .collect();
+
+        let mut queue = BinaryHeap::new();
+        for i in 0..symbols.len() - 1 {
+            self.queue_pair(&mut queue, &symbols, i, i + 1);
+        }
+
+        while let Some(Reverse((_, at, a, b, merged))) = queue.pop() {
+            // Skip pairs that changed since they were queued.
+            let Some(right) = next[at] else { continue };
+            if symbols[at] != Some(a) || symbols[right] != Some(b) {
+                continue;
+            }
+            symbols[at] = Some(merged);
+            symbols[right] = None;
+            next[at] = next[right];
+            if let Some(after) = next[right] {
+                prev[after] = Some(at);
+                self.queue_pair(&mut queue, &symbols, at, after);
+            }
+            if let Some(before) = prev[at] {
+                self.queue_pair(&mut queue, &symbols, before, at);
+            }
+        }
+
+        symbols.into_iter().flatten().collect()
+    }
+
+    fn queue_pair(&self, queue: &mut MergeQueue, symbols: &[Option<u32>], at: usize, right: usize) {
+        if let (Some(a), Some(b)) = (symbols[at], symbols[right]) {
+            if let Some(&(rank, merged)) = self.merges.get(&(a, b)) {
+                queue.push(Reverse((rank, at, a, b, merged)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn model() -> Bpe {
+        Bpe::from_json(&json!({
+            "type": "BPE",
+            "vocab": {
+                "<unk>": 0, "<0xC3>": 1, "<0xA9>": 2,
+                "a": 3, "b": 4, "c": 5, "ab": 6, "bc": 7, "abc": 8
+            },
+            "merges": ["b c", ["a", "b"], "ab c"],
+            "unk_token": "<unk>",
+            "byte_fallback": true
+        }))
+        .unwrap()
+    }
+
+    fn tokenize(bpe: &Bpe, word: &str) -> Vec<u32> {
+        let mut out = Vec::new();
+        bpe.tokenize(word, &mut out);
+        out
+    }
+
+    #[test]
+    fn merges_by_rank() {
+        let bpe = model();
+        // "b c" outranks "a b", so "abc" becomes a + bc, which has no merge.
+        assert_eq!(tokenize(&bpe, "abc"), [3, 7]);
+        assert_eq!(tokenize(&bpe, "ab"), [6]);
+        assert_eq!(tokenize(&bpe, "cab"), [5, 6]);
+    }
+
+    #[test]
+    fn falls_back_to_bytes_then_unknown() {
+        let bpe = model();
+        assert_eq!(tokenize(&bpe, "é"), [1, 2]);
+        assert_eq!(tokenize(&bpe, "xa"), [0, 3]);
+    }
+}
@@ -0,0 +1,557 @@
+//! Tokenization with a model's own vocabulary, read from the Hugging Face
+//! `tokenizer.json` in its cache directory.
+
+mod bpe;
+mod pre_tokenizer;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use async_openai::types::chat::ChatCompletionRequestMessage;
+use serde_json::Value;
+
+use crate::error::{FoundryLocalError, Result};
+use crate::types::PromptTemplate;
+
+use self::bpe::Bpe;
+use self::pre_tokenizer::{byte_to_char, Normalizer, PreTokenizer};
+
+/// Tokens every message costs on top of its content when the model has no
+/// prompt template, and the tokens that prime the reply.
+const MESSAGE_OVERHEAD: usize = 4;
+const REPLY_OVERHEAD: usize = 3;
+
+/// Tokens matched verbatim before normalization, such as `<|end|>`.
+#[derive(Debug, Default)]
+struct AddedTokens {
+    /// Tokens grouped by first character, longest first.
+    by_first_char: HashMap<char, Vec<(String, u32)>>,
+}
+
+impl AddedTokens {
+    fn from_json(value: Option<&Value>) -> Self {
+        let mut by_first_char: HashMap<char, Vec<(String, u32)>> = HashMap::new();
+        for token in value.and_then(Value::as_array).into_iter().flatten() {
+            let content = token.get("content").and_then(Value::as_str);
+            let id = token.get("id").and_then(Value::as_u64);
+            if let (Some(content), Some(id)) = (content, id) {
+                if let Some(first) = content.chars().next() {
+                    by_first_char
+                        .entry(first)
+                        .or_default()
+                        .push((content.to_owned(), id as u32));
+                }
+            }
+        }
+        for tokens in by_first_char.values_mut() {
+            tokens.sort_by_key(|(content, _)| Reverse(content.len()));
+        }
+        Self { by_first_char }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &(String, u32)> {
+        self.by_first_char.values().flatten()
+    }
+
+    /// Split `text` into runs of plain text and added token ids.
+    fn split<'a>(&self, text: &'a str) -> Vec<Segment<'a>> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for (at, c) in text.char_indices() {
+            if at < start {
+                continue;
+            }
+            let found = self.by_first_char.get(&c).and_then(|tokens| {
+                tokens
+                    .iter()
+                    .find(|(content, _)| text[at..].starts_with(content.as_str()))
+            });
+            if let Some((content, id)) = found {
+                if at > start {
+                    segments.push(Segment::Text(&text[start..at]));
+                }
+                segments.push(Segment::Added(*id));
+                start = at + content.len();
+            }
+        }
+        if start < text.len() {
+            segments.push(Segment::Text(&text[start..]));
+        }
+        segments
+    }
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Added(u32),
+}
+
+/// How ids are turned back into text.
+#[derive(Debug, Default)]
+struct Decoder {
+    byte_level: bool,
+    byte_fallback: bool,
+    replace: Vec<(String, String)>,
+    strip_leading_space: bool,
+}
+
+impl Decoder {
+    fn from_json(value: &Value) -> Result<Self> {
+        let mut decoder = Self::default();
+        decoder.add(value)?;
+        Ok(decoder)
+    }
+
+    fn add(&mut self, value: &Value) -> Result<()> {
+        if value.is_null() {
+            return Ok(());
+        }
+        match value
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+        {
+            "ByteLevel" => self.byte_level = true,
+            "ByteFallback" => self.byte_fallback = true,
+            "Fuse" => {}
+            "Replace" => {
+                let from = value.pointer("/pattern/String").and_then(Value::as_str);
+                let to = value.get("content").and_then(Value::as_str);
+                if let (Some(from), Some(to)) = (from, to) {
+                    self.replace.push((from.to_owned(), to.to_owned()));
+                }
+            }
+            "Strip" => {
+                let start = value.get("start").and_then(Value::as_u64).unwrap_or(0);
+                self.strip_leading_space |= start > 0;
+            }
+            "Metaspace" => {
+                let replacement = value
+                    .get("replacement")
+                    .and_then(Value::as_str)
+                    .unwrap_or("\u{2581}");
+                self.replace.push((replacement.to_owned(), " ".to_owned()));
+                self.strip_leading_space |=
+                    value.get("prepend_scheme").and_then(Value::as_str) != Some("never");
+            }
+            "Sequence" => {
+                for step in value
+                    .get("decoders")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    self.add(step)?;
+                }
+            }
+            other => {
+                return Err(FoundryLocalError::Validation {
+                    reason: format!("unsupported tokenizer decoder '{other}'"),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `<0xAB>` byte token.
+fn byte_token(token: &str) -> Option<u8> {
+    let hex = token.strip_prefix("<0x")?.strip_suffix('>')?;
+    if hex.len() != 2 {
+        return None;
+    }
+    u8::from_str_radix(hex, 16).ok()
+}
+
+/// A model's tokenizer, for counting and inspecting tokens without loading
+/// the model.
+///
+/// Obtain one with [`Model::tokenizer`](crate::Model::tokenizer), or load a
+/// `tokenizer.json` directly with [`Tokenizer::from_file`].  Byte-pair
+/// encoding tokenizers are supported, which covers the GPT-2, Llama,
+/// Mistral, Qwen and Phi families:
+///
+/// ```ignore
+/// let tokenizer = Arc::new(model.tokenizer().await?);
+/// let ids = tokenizer.encode("Hello, world!");
+/// assert_eq!(tokenizer.decode(&ids), "Hello, world!");
+///
+/// let prompt_tokens = tokenizer.count_chat_tokens(&messages);
+/// let counter = Arc::clone(&tokenizer);
+/// let session = model
+///     .create_chat_session()
+///     .token_counter(move |m| counter.count_message_tokens(m));
+/// ```
+///
+/// Chat messages are rendered with the model's [`PromptTemplate`] before
+/// counting.  Special tokens a post-processor would add, such as a
+/// beginning-of-sequence token, are not included.
+pub struct Tokenizer {
+    added: AddedTokens,
+    normalizer: Option<Normalizer>,
+    pre_tokenizer: Option<PreTokenizer>,
+    byte_level: bool,
+    model: Bpe,
+    decoder: Decoder,
+    tokens: HashMap<u32, String>,
+    template: Option<PromptTemplate>,
+}
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokenizer")
+            .field("vocab_size", &self.vocab_size())
+            .field("template", &self.template)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tokenizer {
+    /// Load a Hugging Face `tokenizer.json` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| FoundryLocalError::Internal {
+            reason: format!("failed to read '{}': {e}", path.display()),
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Parse the contents of a Hugging Face `tokenizer.json` file.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json)?;
+        let model = Bpe::from_json(value.get("model").unwrap_or(&Value::Null))?;
+        let pre_tokenizer = PreTokenizer::from_json(&value["pre_tokenizer"])?;
+        let added = AddedTokens::from_json(value.get("added_tokens"));
+
+        let mut tokens: HashMap<u32, String> = model
+            .vocab()
+            .iter()
+            .map(|(token, &id)| (id, token.clone()))
+            .collect();
+        for (content, id) in added.iter() {
+            tokens.insert(*id, content.clone());
+        }
+
+        Ok(Self {
+            normalizer: Normalizer::from_json(&value["normalizer"])?,
+            byte_level: pre_tokenizer
+                .as_ref()
+                .is_some_and(PreTokenizer::is_byte_level),
+            pre_tokenizer,
+            decoder: Decoder::from_json(&value["decoder"])?,
+            added,
+            model,
+            tokens,
+            template: None,
+        })
+    }
+
+    /// Render chat messages with `template` when counting them.
+    pub fn prompt_template(mut self, template: PromptTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// The template chat messages are rendered with, if any.
+    pub fn template(&self) -> Option<&PromptTemplate> {
+        self.template.as_ref()
+    }
+
+    /// Number of distinct tokens, including added tokens.
+    pub fn vocab_size(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// The id of `token`, as spelled in the vocabulary.
+    pub fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.added
+            .iter()
+            .find(|(content, _)| content == token)
+            .map(|(_, id)| *id)
+            .or_else(|| self.model.vocab().get(token).copied())
+    }
+
+    /// The vocabulary entry for `id`.
+    pub fn id_to_token(&self, id: u32) -> Option<&str> {
+        self.tokens.get(&id).map(String::as_str)
+    }
+
+    /// Token ids of `text`.  Added tokens such as `<|end|>` in the text
+    /// are matched as single tokens.
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        let mut ids = Vec::new();
+        for (index, segment) in self.added.split(text).into_iter().enumerate() {
+            let text = match segment {
+                Segment::Added(id) => {
+                    ids.push(id);
+                    continue;
+                }
+                Segment::Text(text) => text,
+            };
+            let text = match &self.normalizer {
+                Some(normalizer) => normalizer.apply(text),
+                None => text.to_owned(),
+            };
+            let words = match &self.pre_tokenizer {
+                Some(pre_tokenizer) => pre_tokenizer.apply(vec![text], index == 0),
+                None => vec![text],
+            };
+            for word in &words {
+                self.model.tokenize(word, &mut ids);
+            }
+        }
+        ids
+    }
+
+    /// The text of `ids`.  Unknown ids are skipped.
+    pub fn decode(&self, ids: &[u32]) -> String {
+        let char_to_byte: HashMap<char, u8> = if self.decoder.byte_level || self.byte_level {
+            (0u8..=255)
+                .zip(byte_to_char().iter())
+                .map(|(b, &c)| (c, b))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let is_added = |id: &u32| self.added.iter().any(|(_, added)| added == id);
+
+        let mut bytes = Vec::new();
+        for id in ids {
+            let Some(token) = self.tokens.get(id) else {
+                continue;
+            };
+            if is_added(id) {
+                bytes.extend_from_slice(token.as_bytes());
+            } else if let Some(byte) = byte_token(token).filter(|_| self.decoder.byte_fallback) {
+                bytes.push(byte);
+            } else if !char_to_byte.is_empty() {
+                for c in token.chars() {
+                    match char_to_byte.get(&c) {
+                        Some(&b) => bytes.push(b),
+                        None => bytes.extend_from_slice(c.to_string().as_bytes()),
+                    }
+                }
+            } else {
+                let token = self
+                    .decoder
+                    .replace
+                    .iter()
+                    .fold(token.clone(), |t, (from, to)| t.replace(from.as_str(), to));
+                bytes.extend_from_slice(token.as_bytes());
+            }
+        }
+
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+        match text.strip_prefix(' ') {
+            Some(rest) if self.decoder.strip_leading_space => rest.to_owned(),
+            _ => text,
+        }
+    }
+
+    /// Number of tokens in `text`.
+    pub fn count_tokens(&self, text: &str) -> usize {
+        self.encode(text).len()
+    }
+
+    /// Number of tokens `message` takes up in a prompt.
+    pub fn count_message_tokens(&self, message: &ChatCompletionRequestMessage) -> usize {
+        let (role, text) = message_parts(message);
+        match &self.template {
+            Some(template) => self.count_tokens(&template.render_message(&role, &text)),
+            None => self.count_tokens(&text) + MESSAGE_OVERHEAD,
+        }
+    }
+
+    /// Number of prompt tokens a chat request with `messages` uses,
+    /// including the start of the assistant's reply.
+    ///
+    /// Messages are rendered with the [`PromptTemplate`]; without one each
+    /// message is counted as its text plus a fixed overhead.
+    pub fn count_chat_tokens(&self, messages: &[ChatCompletionRequestMessage]) -> usize {
+        let Some(template) = &self.template else {
+            return messages
+                .iter()
+                .map(|m| self.count_message_tokens(m))
+                .sum::<usize>()
+                + REPLY_OVERHEAD;
+        };
+        let mut prompt: String = messages
+            .iter()
+            .map(|m| {
+                let (role, text) = message_parts(m);
+                template.render_message(&role, &text)
+            })
+            .collect();
+        prompt.push_str(template.generation_prompt());
+        self.count_tokens(&prompt)
+    }
+}
+
+/// The role and prompt text of a message: its text content, followed by
+/// any tool calls as JSON.
+fn message_parts(message: &ChatCompletionRequestMessage) -> (String, String) {
+    let Ok(value) = serde_json::to_value(message) else {
+        return (String::new(), String::new());
+    };
+    let role = value
+        .get("role")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned();
+    let mut text = match value.get("content") {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    };
+    if let Some(calls) = value.get("tool_calls") {
+        text.push_str(&calls.to_string());
+    }
+    (role, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A byte-level tokenizer whose vocabulary covers "hello world".
+    fn byte_level() -> Tokenizer {
+        let json = json!({
+            "added_tokens": [{ "id": 100, "content": "<|end|>", "special": true }],
+            "normalizer": null,
+            "pre_tokenizer": {
+                "type": "Sequence",
+                "pretokenizers": [
+                    {
+                        "type": "Split",
+                        "pattern": { "Regex": concat!(
+                            r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}",
+                            r"| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+"
+                        ) },
+                        "behavior": "Isolated"
+                    },
+                    { "type": "ByteLevel", "add_prefix_space": false, "use_regex": false }
+                ]
+            },
+            "decoder": { "type": "ByteLevel" },
+            "model": {
+                "type": "BPE",
+                "vocab": {
+                    "h": 0, "e": 1, "l": 2, "o": 3, "\u{120}": 4, "w": 5, "r": 6, "d": 7,
+                    "he": 8, "ll": 9, "hell": 10, "hello": 11, "\u{120}w": 12, "or": 13,
+                    "\u{120}wor": 14, "\u{120}world": 15, "ld": 16, "\u{10A}": 17
+                },
+                "merges": [
+                    "h e", "l l", "he ll", "hell o", "\u{120} w", "o r", "\u{120}w or", "l d",
+                    "\u{120}wor ld"
+                ]
+            }
+        });
+        Tokenizer::from_json(&json.to_string()).unwrap()
+    }
+
+    /// A sentencepiece-style tokenizer with byte fallback.
+    fn metaspace() -> Tokenizer {
+        let json = json!({
+            "normalizer": {
+                "type": "Sequence",
+                "normalizers": [
+                    { "type": "Prepend", "prepend": "\u{2581}" },
+                    { "type": "Replace", "pattern": { "String": " " }, "content": "\u{2581}" }
+                ]
+            },
+            "pre_tokenizer": null,
+            "decoder": {
+                "type": "Sequence",
+                "decoders": [
+                    { "type": "Replace", "pattern": { "String": "\u{2581}" }, "content": " " },
+                    { "type": "ByteFallback" },
+                    { "type": "Fuse" },
+                    { "type": "Strip", "content": " ", "start": 1, "stop": 0 }
+                ]
+            },
+            "model": {
+                "type": "BPE",
+                "vocab": {
+                    "<unk>": 0, "<0x21>": 1, "\u{2581}": 2, "h": 3, "i": 4,
+                    "\u{2581}h": 5, "\u{2581}hi": 6
+                },
+                "merges": ["\u{2581} h", "\u{2581}h i"],
+                "unk_token": "<unk>",
+                "byte_fallback": true
+            }
+        });
+        Tokenizer::from_json(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn byte_level_round_trip() {
+        let tokenizer = byte_level();
+        assert_eq!(tokenizer.encode("hello world"), [11, 15]);
+        assert_eq!(tokenizer.encode("hello<|end|>\n"), [11, 100, 17]);
+        assert_eq!(tokenizer.decode(&[11, 15, 100]), "hello world<|end|>");
+        assert_eq!(tokenizer.token_to_id("<|end|>"), Some(100));
+        assert_eq!(tokenizer.vocab_size(), 19);
+    }
+
+    #[test]
+    fn metaspace_round_trip_with_byte_fallback() {
+        let tokenizer = metaspace();
+        assert_eq!(tokenizer.encode("hi hi!"), [6, 6, 1]);
+        assert_eq!(tokenizer.decode(&[6, 6, 1]), "hi hi!");
+    }
+
+    #[test]
+    fn counts_chat_with_template() {
+        let messages: Vec<ChatCompletionRequestMessage> = serde_json::from_value(json!([
+            { "role": "user", "content": "hello" }
+        ]))
+        .unwrap();
+
+        let tokenizer = byte_level();
+        assert_eq!(tokenizer.count_chat_tokens(&messages), 1 + 4 + 3);
+
+        let tokenizer = tokenizer.prompt_template(PromptTemplate {
+            system: None,
+            user: Some("{Content}<|end|>\n".into()),
+            assistant: Some("hello{Content}<|end|>\n".into()),
+            prompt: None,
+        });
+        // hello <|end|> \n, then the "hello" that opens the reply.
+        assert_eq!(tokenizer.count_message_tokens(&messages[0]), 3);
+        assert_eq!(tokenizer.count_chat_tokens(&messages), 4);
+    }
+
+    #[test]
+    fn encodes_with_a_real_gpt2_tokenizer() {
+        let tokenizer = Tokenizer::from_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../sdk_v2/cpp/test/testdata/tiny-random-gpt2-fp32-1/tokenizer.json"
+        ))
+        .unwrap();
+        assert_eq!(tokenizer.vocab_size(), 50295);
+
+        let text = "Hello, world! The quick brown fox don't\n<|endoftext|>";
+        let ids = tokenizer.encode(text);
+        assert_eq!(
+            ids,
+            [15496, 11, 995, 0, 383, 2068, 7586, 21831, 836, 470, 198, 50256]
+        );
+        assert_eq!(tokenizer.decode(&ids), text);
+    }
+
+    #[test]
+    fn rejects_unsupported_models() {
+        let json = json!({ "model": { "type": "WordPiece", "vocab": {} } });
+        assert!(matches!(
+            Tokenizer::from_json(&json.to_string()),
+            Err(FoundryLocalError::Validation { .. })
+        ));
+    }
+}
@@ -0,0 +1,571 @@
+//! Splitting text into words before BPE, following the `normalizer` and
+//! `pre_tokenizer` sections of a Hugging Face `tokenizer.json`.
+
+use std::sync::OnceLock;
+
+use serde_json::Value;
+
+use crate::error::{FoundryLocalError, Result};
+
+/// Text rewrites applied before splitting.
+#[derive(Debug, Clone)]
+pub(super) enum Normalizer {
+    Prepend(String),
+    Replace {
+        from: String,
+        to: String,
+    },
+    Lowercase,
+    /// Unicode normalization forms; model vocabularies are already in NFC
+    /// for the text this is used on, so they leave the text unchanged.
+    Identity,
+    Sequence(Vec<Normalizer>),
+}
+
+impl Normalizer {
+    pub(super) fn from_json(value: &Value) -> Result<Option<Self>> {
+        if value.is_null() {
+            return Ok(None);
+        }
+        let normalizer = match component_type(value, "normalizer")? {
+            "Prepend" => Self::Prepend(string_field(value, "prepend")?),
+            "Replace" => Self::Replace {
+                from: literal_pattern(value, "normalizer")?,
+                to: string_field(value, "content")?,
+            },
+            "Lowercase" => Self::Lowercase,
+            "NFC" | "NFD" | "NFKC" | "NFKD" | "Strip" | "StripAccents" => Self::Identity,
+            "Sequence" => Self::Sequence(
+                array_field(value, "normalizers")?
+                    .iter()
+                    .filter_map(|n| Self::from_json(n).transpose())
+                    .collect::<Result<_>>()?,
+            ),
+            other => return Err(unsupported("normalizer", other)),
+        };
+        Ok(Some(normalizer))
+    }
+
+    pub(super) fn apply(&self, text: &str) -> String {
+        match self {
+            Self::Prepend(prefix) if !text.is_empty() => format!("{prefix}{text}"),
+            Self::Prepend(_) | Self::Identity => text.to_owned(),
+            Self::Replace { from, to } => text.replace(from.as_str(), to),
+            Self::Lowercase => text.to_lowercase(),
+            Self::Sequence(steps) => steps
+                .iter()
+                .fold(text.to_owned(), |text, step| step.apply(&text)),
+        }
+    }
+}
+
+/// Which built-in regular expression a `Split` or `ByteLevel`
+/// pre-tokenizer uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SplitStyle {
+    /// GPT-2: `'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+`
+    Gpt2,
+    /// cl100k and its descendants (Llama 3, Qwen 2, Phi 4), with numbers
+    /// split into groups of at most `max_digits`.
+    Cl100k { max_digits: usize },
+}
+
+impl SplitStyle {
+    /// The style that implements `pattern`, or `None` for a pattern that
+    /// is neither GPT-2 nor cl100k.
+    fn for_pattern(pattern: &str) -> Option<Self> {
+        if pattern.contains(r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+") {
+            return Some(Self::Gpt2);
+        }
+        if !pattern.contains(r"[^\r\n\p{L}\p{N}]?\p{L}+") {
+            return None;
+        }
+        if pattern.contains(r"|\p{N}{1,3}|") {
+            Some(Self::Cl100k { max_digits: 3 })
+        } else if pattern.contains(r"|\p{N}|") {
+            Some(Self::Cl100k { max_digits: 1 })
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PrependScheme {
+    Always,
+    First,
+    Never,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum PreTokenizer {
+    ByteLevel {
+        add_prefix_space: bool,
+        use_regex: bool,
+    },
+    Split {
+        style: SplitStyle,
+    },
+    SplitLiteral {
+        delimiter: String,
+        keep: bool,
+    },
+    Metaspace {
+        replacement: char,
+        prepend: PrependScheme,
+        split: bool,
+    },
+    Digits {
+        individual: bool,
+    },
+    Whitespace,
+    Sequence(Vec<PreTokenizer>),
+}
+
+impl PreTokenizer {
+    pub(super) fn from_json(value: &Value) -> Result<Option<Self>> {
+        if value.is_null() {
+            return Ok(None);
+        }
+        let flag =
+            |name: &str, default: bool| value.get(name).and_then(Value::as_bool).unwrap_or(default);
+        let pre = match component_type(value, "pre_tokenizer")? {
+            "ByteLevel" => Self::ByteLevel {
+                add_prefix_space: flag("add_prefix_space", false),
+                use_regex: flag("use_regex", true),
+            },
+            "Split" => match value.pointer("/pattern/Regex").and_then(Value::as_str) {
+                Some(pattern) => Self::Split {
+                    style: SplitStyle::for_pattern(pattern)
+                        .ok_or_else(|| unsupported("Split pattern", pattern))?,
+                },
+                None => Self::SplitLiteral {
+                    delimiter: literal_pattern(value, "pre_tokenizer")?,
+                    keep: value.get("behavior").and_then(Value::as_str) != Some("Removed"),
+                },
+            },
+            "Metaspace" => {
+                let replacement = value
+                    .get("replacement")
+                    .and_then(Value::as_str)
+                    .and_then(|s| s.chars().next())
+                    .unwrap_or('\u{2581}');
+                let prepend = match value.get("prepend_scheme").and_then(Value::as_str) {
+                    Some("first") => PrependScheme::First,
+                    Some("never") => PrependScheme::Never,
+                    Some(_) => PrependScheme::Always,
+                    // Older files only have `add_prefix_space`.
+                    None if flag("add_prefix_space", true) => PrependScheme::Always,
+                    None => PrependScheme::Never,
+                };
+                Self::Metaspace {
+                    replacement,
+                    prepend,
+                    split: flag("split", true),
+                }
+            }
+            "Digits" => Self::Digits {
+                individual: flag("individual_digits", false),
+            },
+            "Whitespace" | "WhitespaceSplit" => Self::Whitespace,
+            "Sequence" => Self::Sequence(
+                array_field(value, "pretokenizers")?
+                    .iter()
+                    .filter_map(|p| Self::from_json(p).transpose())
+                    .collect::<Result<_>>()?,
+            ),
+            other => return Err(unsupported("pre_tokenizer", other)),
+        };
+        Ok(Some(pre))
+    }
+
+    /// Whether words must be mapped to byte-level characters before BPE.
+    pub(super) fn is_byte_level(&self) -> bool {
+        match self {
+            Self::ByteLevel { .. } => true,
+            Self::Sequence(steps) => steps.iter().any(Self::is_byte_level),
+            _ => false,
+        }
+    }
+
+    /// Split each of `words` further.  `first` is false for text that
+    /// follows an added token.
+    pub(super) fn apply(&self, words: Vec<String>, first: bool) -> Vec<String> {
+        match self {
+            Self::ByteLevel {
+                add_prefix_space,
+                use_regex,
+            } => {
+                let mut words = words;
+                if *add_prefix_space {
+                    if let Some(first) = words.first_mut() {
+                        if !first.starts_with(' ') {
+                            first.insert(0, ' ');
+                        }
+                    }
+                }
+                let words = if *use_regex {
+                    flat_map(words, |w| split_words(w, SplitStyle::Gpt2))
+                } else {
+                    words
+                };
+                words.iter().map(|w| to_byte_level(w)).collect()
+            }
+            Self::Split { style } => flat_map(words, |w| split_words(w, *style)),
+            Self::SplitLiteral { delimiter, keep } => {
+                flat_map(words, |w| split_literal(w, delimiter, *keep))
+            }
+            Self::Metaspace {
+                replacement,
+                prepend,
+                split,
+            } => {
+                let mut out = Vec::new();
+                for (index, word) in words.iter().enumerate() {
+                    let mut word = word.replace(' ', &replacement.to_string());
+                    let prefix = match prepend {
+                        PrependScheme::Always => true,
+                        PrependScheme::First => first && index == 0,
+                        PrependScheme::Never => false,
+                    };
+                    if prefix && !word.starts_with(*replacement) {
+                        word.insert(0, *replacement);
+                    }
+                    if *split {
+                        out.extend(split_before(&word, *replacement));
+                    } else {
+                        out.push(word);
+                    }
+                }
+                out
+            }
+            Self::Digits { individual } => flat_map(words, |w| split_digits(w, *individual)),
+            Self::Whitespace => {
+                flat_map(words, |w| w.split_whitespace().map(str::to_owned).collect())
+            }
+            Self::Sequence(steps) => steps
+                .iter()
+                .fold(words, |words, step| step.apply(words, first)),
+        }
+    }
+}
+
+fn flat_map(words: Vec<String>, f: impl Fn(&str) -> Vec<String>) -> Vec<String> {
+    words.iter().flat_map(|w| f(w)).collect()
+}
+
+fn component_type<'a>(value: &'a Value, section: &str) -> Result<&'a str> {
+    value
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| FoundryLocalError::Validation {
+            reason: format!("tokenizer {section} has no type"),
+        })
+}
+
+fn string_field(value: &Value, name: &str) -> Result<String> {
+    value
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_owned)
+        .ok_or_else(|| FoundryLocalError::Validation {
+            reason: format!("tokenizer component is missing '{name}'"),
+        })
+}
+
+fn array_field<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>> {
+    value
+        .get(name)
+        .and_then(Value::as_array)
+        .ok_or_else(|| FoundryLocalError::Validation {
+            reason: format!("tokenizer component is missing '{name}'"),
+        })
+}
+
+fn literal_pattern(value: &Value, section: &str) -> Result<String> {
+    value
+        .pointer("/pattern/String")
+        .and_then(Value::as_str)
+        .map(str::to_owned)
+        .ok_or_else(|| FoundryLocalError::Validation {
+            reason: format!(
+                "regular expression patterns in the tokenizer {section} are not supported"
+            ),
+        })
+}
+
+fn unsupported(section: &str, kind: &str) -> FoundryLocalError {
+    FoundryLocalError::Validation {
+        reason: format!("unsupported tokenizer {section} '{kind}'"),
+    }
+}
+
+/// The GPT-2 mapping of bytes to printable characters.
+pub(super) fn byte_to_char() -> &'static [char; 256] {
+    static TABLE: OnceLock<[char; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = ['\0'; 256];
+        let mut shifted = 0u32;
+        for (byte, slot) in table.iter_mut().enumerate() {
+            let printable = matches!(byte, 0x21..=0x7E | 0xA1..=0xAC | 0xAE..=0xFF);
+            *slot = if printable {
+                char::from(byte as u8)
+            } else {
+                shifted += 1;
+                char::from_u32(255 + shifted).unwrap_or('\0')
+            };
+        }
+        table
+    })
+}
+
+fn to_byte_level(word: &str) -> String {
+    let table = byte_to_char();
+    word.bytes().map(|b| table[usize::from(b)]).collect()
+}
+
+fn split_literal(word: &str, delimiter: &str, keep: bool) -> Vec<String> {
+    if delimiter.is_empty() {
+        return vec![word.to_owned()];
+    }
+    let mut out = Vec::new();
+    let mut rest = word;
+    while let Some(at) = rest.find(delimiter) {
+        if at > 0 {
+            out.push(rest[..at].to_owned());
+        }
+        if keep {
+            out.push(delimiter.to_owned());
+        }
+        rest = &rest[at + delimiter.len()..];
+    }
+    if !rest.is_empty() {
+        out.push(rest.to_owned());
+    }
+    out
+}
+
+/// Split `word` so each piece but the first starts with `marker`.
+fn split_before(word: &str, marker: char) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for c in word.chars() {
+        match out.last_mut() {
+            Some(last) if c != marker => last.push(c),
+            _ => out.push(c.to_string()),
+        }
+    }
+    out
+}
+
+fn split_digits(word: &str, individual: bool) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut previous_digit = None;
+    for c in word.chars() {
+        let digit = c.is_ascii_digit();
+        match out.last_mut() {
+            Some(last) if previous_digit == Some(digit) && !(digit && individual) => last.push(c),
+            _ => out.push(c.to_string()),
+        }
+        previous_digit = Some(digit);
+    }
+    out
+}
+
+fn is_letter(c: char) -> bool {
+    c.is_alphabetic()
+}
+
+fn is_number(c: char) -> bool {
+    c.is_numeric()
+}
+
+fn is_newline(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+fn is_other(c: char) -> bool {
+    !c.is_whitespace() && !is_letter(c) && !is_number(c)
+}
+
+/// Split `text` the way the regular expression of `style` does.
+pub(super) fn split_words(text: &str, style: SplitStyle) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let end = match style {
+            SplitStyle::Gpt2 => gpt2_match(&chars, i),
+            SplitStyle::Cl100k { max_digits } => cl100k_match(&chars, i, max_digits),
+        };
+        out.push(chars[i..end].iter().collect());
+        i = end;
+    }
+    out
+}
+
+fn run(chars: &[char], start: usize, pred: impl Fn(char) -> bool) -> usize {
+    let mut end = start;
+    while end < chars.len() && pred(chars[end]) {
+        end += 1;
+    }
+    end
+}
+
+/// End of a contraction such as `'ll` at `i`.
+fn contraction(chars: &[char], i: usize, ignore_case: bool) -> Option<usize> {
+    if chars[i] != '\'' {
+        return None;
+    }
+    let lower = |k: usize| {
+        chars.get(k).map(|&c| {
+            if ignore_case {
+                c.to_ascii_lowercase()
+            } else {
+                c
+            }
+        })
+    };
+    match (lower(i + 1), lower(i + 2)) {
+        (Some('r'), Some('e')) | (Some('v'), Some('e')) | (Some('l'), Some('l')) => Some(i + 3),
+        (Some('s' | 't' | 'm' | 'd'), _) => Some(i + 2),
+        _ => None,
+    }
+}
+
+/// `\s+(?!\S)|\s+`: a whitespace run, leaving its last character for the
+/// next word when one follows.
+fn whitespace(chars: &[char], i: usize) -> usize {
+    let end = run(chars, i, char::is_whitespace);
+    if end < chars.len() && end - i > 1 {
+        end - 1
+    } else {
+        end
+    }
+}
+
+fn gpt2_match(chars: &[char], i: usize) -> usize {
+    if let Some(end) = contraction(chars, i, false) {
+        return end;
+    }
+    let start = if chars[i] == ' ' { i + 1 } else { i };
+    if let Some(&c) = chars.get(start) {
+        for class in [is_letter, is_number, is_other] {
+            if class(c) {
+                return run(chars, start, class);
+            }
+        }
+    }
+    whitespace(chars, i)
+}
+
+fn cl100k_match(chars: &[char], i: usize, max_digits: usize) -> usize {
+    let c = chars[i];
+    if let Some(end) = contraction(chars, i, true) {
+        return end;
+    }
+    // [^\r\n\p{L}\p{N}]?\p{L}+
+    if is_letter(c) {
+        return run(chars, i, is_letter);
+    }
+    if !is_newline(c) && !is_number(c) && chars.get(i + 1).is_some_and(|&n| is_letter(n)) {
+        return run(chars, i + 1, is_letter);
+    }
+    // \p{N}{1,max_digits}
+    if is_number(c) {
+        return run(chars, i, is_number).min(i + max_digits);
+    }
+    // ` ?[^\s\p{L}\p{N}]+[\r\n]*`
+    let start = if c == ' ' { i + 1 } else { i };
+    if chars.get(start).is_some_and(|&n| is_other(n)) {
+        let end = run(chars, start, is_other);
+        return run(chars, end, is_newline);
+    }
+    // \s*[\r\n]+
+    let end = run(chars, i, char::is_whitespace);
+    if let Some(last) = (i..end).rev().find(|&k| is_newline(chars[k])) {
+        return last + 1;
+    }
+    whitespace(chars, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str, style: SplitStyle) -> Vec<String> {
+        split_words(text, style)
+    }
+
+    #[test]
+    fn gpt2_pattern() {
+        assert_eq!(
+            split("Hello world's  42 fish!!\n\nok ", SplitStyle::Gpt2),
+            ["Hello", " world", "'s", " ", " 42", " fish", "!!", "\n", "\n", "ok", " "]
+        );
+    }
+
+    #[test]
+    fn cl100k_pattern() {
+        let style = SplitStyle::Cl100k { max_digits: 3 };
+        assert_eq!(
+            split("I'LL pay 12345 $5.\n\n  x", style),
+            ["I", "'LL", " pay", " ", "123", "45", " $", "5", ".\n\n", " ", " x"]
+        );
+        assert_eq!(
+            split("a\t\nb", SplitStyle::Cl100k { max_digits: 1 }),
+            ["a", "\t\n", "b"]
+        );
+    }
+
+    #[test]
+    fn recognises_split_patterns() {
+        let split = |pattern: &str| {
+            PreTokenizer::from_json(&serde_json::json!({
+                "type": "Split",
+                "pattern": { "Regex": pattern },
+                "behavior": "Isolated"
+            }))
+        };
+        let style = |pattern: &str| match split(pattern) {
+            Ok(Some(PreTokenizer::Split { style })) => Some(style),
+            _ => None,
+        };
+        let qwen2 = concat!(
+            r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}",
+            r"| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+"
+        );
+        assert_eq!(style(qwen2), Some(SplitStyle::Cl100k { max_digits: 1 }));
+        assert_eq!(
+            style(r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+"),
+            Some(SplitStyle::Gpt2)
+        );
+
+        let o200k = concat!(
+            r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+",
+            r"(?i:'s|'t|'re|'ve|'m|'ll|'d)?|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+",
+            r"|\s+(?!\S)|\s+"
+        );
+        let err = split(o200k).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unsupported tokenizer Split pattern"));
+    }
+
+    #[test]
+    fn byte_level_and_metaspace() {
+        let table = byte_to_char();
+        assert_eq!(table[usize::from(b' ')], '\u{120}');
+        assert_eq!(table[usize::from(b'\n')], '\u{10A}');
+        assert_eq!(to_byte_level(" é"), "\u{120}\u{C3}\u{A9}");
+
+        let metaspace = PreTokenizer::Metaspace {
+            replacement: '\u{2581}',
+            prepend: PrependScheme::First,
+            split: true,
+        };
+        assert_eq!(
+            metaspace.apply(vec!["Hi there".into()], true),
+            ["\u{2581}Hi", "\u{2581}there"]
+        );
+    }
+}
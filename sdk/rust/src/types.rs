@@ -24,6 +24,37 @@ pub struct PromptTemplate {
     pub prompt: Option<String>,
 }
 
+impl PromptTemplate {
+    /// Placeholder the templates replace with the text.
+    pub const CONTENT_PLACEHOLDER: &'static str = "{Content}";
+
+    /// Render one chat message with the template for `role`.
+    pub(crate) fn render_message(&self, role: &str, content: &str) -> String {
+        let template = match role {
+            "system" | "developer" => self.system.as_deref(),
+            "assistant" => self.assistant.as_deref(),
+            _ => self.user.as_deref(),
+        };
+        fill(template, content)
+    }
+
+    /// The text that starts the assistant's turn: the `assistant` template
+    /// up to its placeholder.
+    pub(crate) fn generation_prompt(&self) -> &str {
+        self.assistant
+            .as_deref()
+            .and_then(|t| t.split(Self::CONTENT_PLACEHOLDER).next())
+            .unwrap_or_default()
+    }
+}
+
+fn fill(template: Option<&str>, content: &str) -> String {
+    match template {
+        Some(template) => template.replace(PromptTemplate::CONTENT_PLACEHOLDER, content),
+        None => content.to_owned(),
+    }
+}
+
 /// Runtime information for a model (device type and execution provider).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -97,6 +128,40 @@ pub struct ModelInfo {
     pub capabilities: Option<String>,
 }
 
+/// How the ONNX Runtime memory arena grows when it runs out of space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaExtendStrategy {
+    /// Double the arena size on each extension (ONNX Runtime default).
+    NextPowerOfTwo,
+    /// Extend the arena by exactly the requested amount.
+    SameAsRequested,
+}
+
+impl ArenaExtendStrategy {
+    /// Returns the string value expected by the native core library.
+    pub(crate) fn as_core_str(&self) -> &'static str {
+        match self {
+            Self::NextPowerOfTwo => "kNextPowerOfTwo",
+            Self::SameAsRequested => "kSameAsRequested",
+        }
+    }
+}
+
+/// License terms presented to a download's license acceptance hook.
+///
+/// See [`DownloadBuilder::accept_license`](crate::DownloadBuilder::accept_license).
+#[derive(Debug, Clone)]
+pub struct ModelLicense {
+    /// Id of the variant being downloaded.
+    pub model_id: String,
+    /// Model name shared by all versions of the variant.
+    pub model_name: String,
+    /// License identifier (e.g. `"MIT"`).
+    pub license: String,
+    /// Full license text or summary, if the catalog provides one.
+    pub license_description: Option<String>,
+}
+
 /// Desired response format for chat completions.
 ///
 /// Extends the standard OpenAI formats with the Foundry-specific
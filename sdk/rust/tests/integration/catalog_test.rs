@@ -104,3 +104,21 @@ async fn should_throw_when_getting_model_variant_with_unknown_id() {
         .await;
     assert!(result.is_err(), "Expected error for unknown variant ID");
 }
+
+#[tokio::test]
+async fn should_report_test_model_as_cached() {
+    let cat = catalog();
+    let model = cat
+        .get_model(common::TEST_MODEL_ALIAS)
+        .await
+        .expect("get_model failed");
+    let report = cat.cache_report().await.expect("cache_report failed");
+
+    let entry = report
+        .entries
+        .iter()
+        .find(|e| e.model_id.as_deref() == Some(model.id()))
+        .expect("test model should appear in the cache report");
+    assert_eq!(entry.kind, foundry_local_sdk::CacheEntryKind::Cached);
+    assert!(entry.size_bytes > 0);
+}
@@ -1,9 +1,12 @@
 use super::common;
-use foundry_local_sdk::openai::ChatClient;
+use foundry_local_sdk::openai::{
+    user_message_with_images, ChatClient, ImageInput, ReasoningEvent, ReasoningMode, ToolRegistry,
+};
 use foundry_local_sdk::{
     ChatCompletionMessageToolCalls, ChatCompletionRequestMessage,
     ChatCompletionRequestSystemMessage, ChatCompletionRequestToolMessage,
-    ChatCompletionRequestUserMessage, ChatToolChoice,
+    ChatCompletionRequestUserMessage, ChatResponseFormat, ChatToolChoice, FoundryLocalError,
+    Grammar,
 };
 use serde_json::json;
 use std::sync::Arc;
@@ -332,3 +335,408 @@ async fn should_perform_tool_calling_chat_completion_streaming() {
 
     model.unload().await.expect("model.unload() failed");
 }
+
+#[tokio::test]
+async fn should_keep_history_in_chat_session() {
+    let (_client, model) = setup_chat_client().await;
+    let mut session = model
+        .create_chat_session()
+        .configure_client(|c| c.max_tokens(500).temperature(0.0))
+        .system_prompt("You are a helpful math assistant. Respond with just the answer.");
+
+    session
+        .send("What is 7*6?")
+        .await
+        .expect("first turn failed");
+
+    let mut second = String::new();
+    {
+        let mut stream = session
+            .send_streaming("Add 25 to the previous answer.")
+            .await
+            .expect("streaming turn setup failed");
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.expect("stream chunk error");
+            if let Some(content) = chunk
+                .choices
+                .first()
+                .and_then(|c| c.delta.content.as_deref())
+            {
+                second.push_str(content);
+            }
+        }
+    }
+
+    assert!(
+        second.contains("67"),
+        "Expected the second answer to build on the first, got: {second}"
+    );
+    // System prompt, two user turns and two assistant replies.
+    assert_eq!(session.messages().len(), 5);
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_run_registered_tools_until_answered() {
+    let (client, model) = setup_chat_client().await;
+    let client = client.tool_choice(ChatToolChoice::Required);
+
+    let multiply = common::get_multiply_tool();
+    let parameters = match &multiply {
+        foundry_local_sdk::ChatCompletionTools::Function(f) => f.function.parameters.clone(),
+        _ => None,
+    }
+    .expect("multiply tool has parameters");
+    let registry = ToolRegistry::new()
+        .register(
+            "multiply",
+            "Multiply two numbers together",
+            parameters,
+            |args| async move {
+                let a = args["a"].as_f64().ok_or("a must be a number")?;
+                let b = args["b"].as_f64().ok_or("b must be a number")?;
+                Ok::<_, &str>((a * b) as i64)
+            },
+        )
+        .max_iterations(3);
+
+    let messages = vec![
+        system_message("You are a math assistant. Use the multiply tool to answer."),
+        user_message("What is 6 times 7?"),
+    ];
+    let run = client
+        .run_with_tools(&messages, &registry)
+        .await
+        .expect("run_with_tools failed");
+
+    assert!(
+        run.iterations >= 1,
+        "Expected at least one round of tool calls"
+    );
+    let content = run.content().unwrap_or("");
+    assert!(
+        content.contains("42"),
+        "Final answer should contain '42', got: {content}"
+    );
+    assert!(run.messages.len() > messages.len() + 2);
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Product {
+    result: i64,
+}
+
+#[tokio::test]
+async fn should_complete_with_schema_into_a_rust_type() {
+    let (client, model) = setup_chat_client().await;
+
+    let schema = json!({
+        "type": "object",
+        "properties": { "result": { "type": "integer" } },
+        "required": ["result"]
+    });
+    let messages = vec![
+        system_message("You are a calculator. Respond in JSON."),
+        user_message("What is 7*6?"),
+    ];
+    let product: Product = client
+        .complete_with_schema(&messages, &schema)
+        .await
+        .expect("complete_with_schema failed")
+        .value;
+
+    assert_eq!(product.result, 42);
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_validate_json_against_the_response_schema() {
+    let (client, model) = setup_chat_client().await;
+    let schema = json!({
+        "type": "object",
+        "properties": { "result": { "type": "integer" } },
+        "required": ["result"]
+    });
+    let client = client
+        .response_format(ChatResponseFormat::JsonSchema(schema.to_string()))
+        .max_json_attempts(3);
+
+    let messages = vec![
+        system_message("You are a calculator. Respond in JSON."),
+        user_message("What is 7*6?"),
+    ];
+    let completion = client
+        .complete_json(&messages)
+        .await
+        .expect("complete_json failed");
+
+    assert_eq!(completion.value["result"], json!(42));
+    assert!((1..=3).contains(&completion.attempts));
+    assert_eq!(completion.rejected.len() as u32, completion.attempts - 1);
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_constrain_output_to_a_grammar() {
+    let (client, model) = setup_chat_client().await;
+    let client = client.response_format(
+        Grammar::one_of(&["yes", "no"])
+            .try_into()
+            .expect("grammar should be valid"),
+    );
+
+    let messages = vec![user_message("Is 7*6 equal to 42? Answer yes or no.")];
+    let response = client
+        .complete_chat(&messages, None)
+        .await
+        .expect("complete_chat failed");
+    let content = response.choices[0].message.content.as_deref().unwrap_or("");
+    assert!(
+        content == "yes" || content == "no",
+        "unexpected reply {content:?}"
+    );
+
+    let err =
+        Grammar::validate_lark("start: answer").expect_err("an undefined rule should be rejected");
+    assert!(matches!(err, FoundryLocalError::Validation { .. }), "{err}");
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_accumulate_streamed_tool_calls() {
+    let (client, model) = setup_chat_client().await;
+    let client = client.tool_choice(ChatToolChoice::Required);
+
+    let tools = vec![common::get_multiply_tool()];
+    let messages = vec![
+        system_message("You are a math assistant. Use the multiply tool to answer."),
+        user_message("What is 6 times 7?"),
+    ];
+
+    let mut stream = client
+        .complete_streaming_chat(&messages, Some(&tools))
+        .await
+        .expect("streaming tool call setup failed")
+        .accumulate();
+    let mut streamed = String::new();
+    while let Some(text) = stream.next().await {
+        streamed.push_str(&text.expect("stream chunk error"));
+    }
+
+    let accumulator = stream.accumulator();
+    assert_eq!(accumulator.content(), streamed);
+    let calls = accumulator.tool_calls().expect("tool call arguments");
+    assert_eq!(calls.len(), 1, "Expected one tool call, got {calls:?}");
+    assert_eq!(calls[0].name, "multiply");
+    assert_eq!(
+        calls[0].arguments["a"].as_f64().unwrap_or(0.0)
+            * calls[0].arguments["b"].as_f64().unwrap_or(0.0),
+        42.0
+    );
+
+    let response = accumulator.response();
+    let tool_calls = response.choices[0]
+        .message
+        .tool_calls
+        .as_deref()
+        .unwrap_or(&[]);
+    assert!(matches!(
+        &tool_calls[0],
+        ChatCompletionMessageToolCalls::Function(call) if call.id == calls[0].id
+    ));
+
+    let response = client
+        .tool_choice(ChatToolChoice::None)
+        .complete_streaming_chat(&messages, None)
+        .await
+        .expect("streaming chat setup failed")
+        .collect()
+        .await
+        .expect("collect failed");
+    assert!(response.choices[0].finish_reason.is_some());
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_override_settings_for_one_request() {
+    let (client, model) = setup_chat_client().await;
+    let client = client.max_tokens(1);
+
+    let messages = vec![
+        system_message("You are a helpful math assistant. Respond with just the answer."),
+        user_message("What is 7*6?"),
+    ];
+    let response = client
+        .request(&messages)
+        .max_tokens(500)
+        .temperature(0.0)
+        .stop(["\n\n"])
+        .send()
+        .await
+        .expect("request failed");
+    let content = response.choices[0].message.content.as_deref().unwrap_or("");
+    assert!(content.contains("42"), "Expected '42', got: {content}");
+
+    let stream = client
+        .request(&messages)
+        .max_tokens(500)
+        .include_stream_usage(true)
+        .stream()
+        .await
+        .expect("streaming request failed");
+    let streamed = stream.collect().await.expect("collect failed");
+    let content = streamed.choices[0].message.content.as_deref().unwrap_or("");
+    assert!(content.contains("42"), "Expected '42', got: {content}");
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_separate_reasoning_from_answer() {
+    let (client, model) = setup_chat_client().await;
+    let client = client.reasoning(ReasoningMode::Separate);
+    let messages = vec![
+        system_message("You are a helpful math assistant. Respond with just the answer."),
+        user_message("What is 7*6?"),
+    ];
+
+    let completion = client
+        .complete_with_reasoning(&messages, None)
+        .await
+        .expect("complete_with_reasoning failed");
+    let content = completion.content().unwrap_or("");
+    assert!(content.contains("42"), "Expected '42', got: {content}");
+    assert!(!content.contains("<think>"), "Thinking left in: {content}");
+
+    let mut stream = client
+        .stream_with_reasoning(&messages, None)
+        .await
+        .expect("stream_with_reasoning failed");
+    let mut answer = String::new();
+    while let Some(event) = stream.next().await {
+        match event.expect("stream event error") {
+            ReasoningEvent::Reasoning { text, .. } => println!("Thinking: {text}"),
+            ReasoningEvent::Chunk(chunk) => {
+                for choice in &chunk.choices {
+                    answer.push_str(choice.delta.content.as_deref().unwrap_or(""));
+                }
+            }
+        }
+    }
+    assert!(answer.contains("42"), "Expected '42', got: {answer}");
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_reject_images_for_text_only_models() {
+    let (client, model) = setup_chat_client().await;
+    let accepts_images = model
+        .input_modalities()
+        .map_or(true, |m| m.split(',').any(|m| m.trim() == "image"));
+    let image = ImageInput::from_url("https://example.com/cat.png");
+    let messages = vec![user_message_with_images("Describe this image.", [image])];
+
+    let result = client.complete_chat(&messages, None).await;
+    if !accepts_images {
+        assert!(
+            matches!(result, Err(FoundryLocalError::Validation { .. })),
+            "Expected a Validation error for a text-only model"
+        );
+    }
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_complete_batch_in_input_order() {
+    let (client, model) = setup_chat_client().await;
+    let system = system_message("You are a helpful math assistant. Respond with just the answer.");
+    let requests = vec![
+        vec![system.clone(), user_message("What is 7*6?")],
+        Vec::new(),
+        vec![system, user_message("What is 10+5?")],
+    ];
+
+    let mut progress = Vec::new();
+    let results = client
+        .complete_batch(requests)
+        .concurrency(2)
+        .progress(|p| progress.push(p))
+        .run()
+        .await;
+
+    assert_eq!(results.len(), 3);
+    let answer = |i: usize| {
+        results[i].as_ref().expect("request failed").choices[0]
+            .message
+            .content
+            .clone()
+            .unwrap_or_default()
+    };
+    assert!(
+        answer(0).contains("42"),
+        "Expected '42', got: {}",
+        answer(0)
+    );
+    assert!(matches!(
+        results[1],
+        Err(FoundryLocalError::Validation { .. })
+    ));
+    assert!(
+        answer(2).contains("15"),
+        "Expected '15', got: {}",
+        answer(2)
+    );
+
+    assert_eq!(progress.len(), 3);
+    let last = progress.last().unwrap();
+    assert_eq!((last.succeeded, last.failed, last.total), (2, 1, 3));
+
+    model.unload().await.expect("model.unload() failed");
+}
+
+#[tokio::test]
+async fn should_run_openai_batch_jsonl() {
+    let (client, model) = setup_chat_client().await;
+    let input = [
+        json!({
+            "custom_id": "multiply",
+            "method": "POST",
+            "url": "/v1/chat/completions",
+            "body": {
+                "model": "ignored",
+                "messages": [{ "role": "user", "content": "What is 7*6? Reply with the number." }]
+            }
+        }),
+        json!({ "custom_id": "embed", "method": "POST", "url": "/v1/embeddings", "body": {} }),
+    ]
+    .iter()
+    .map(|line| format!("{line}\n"))
+    .collect::<String>();
+
+    let output = client.batch_from_jsonl(&input).run_jsonl().await;
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("output line is not JSON"))
+        .collect();
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["custom_id"], "multiply");
+    assert_eq!(lines[0]["response"]["status_code"], 200);
+    let content = lines[0]["response"]["body"]["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap_or("");
+    assert!(content.contains("42"), "Expected '42', got: {content}");
+    assert_eq!(lines[1]["custom_id"], "embed");
+    assert_eq!(lines[1]["error"]["code"], "invalid_request");
+
+    model.unload().await.expect("model.unload() failed");
+}
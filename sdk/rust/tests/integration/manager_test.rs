@@ -1,5 +1,6 @@
 use super::common;
-use foundry_local_sdk::FoundryLocalManager;
+use foundry_local_sdk::{FoundryLocalManager, PrepareProgress};
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn should_initialize_successfully() {
@@ -19,3 +20,30 @@ fn should_return_catalog_with_non_empty_name() {
     let name = catalog.name();
     assert!(!name.is_empty(), "Catalog name should not be empty");
 }
+
+#[tokio::test]
+async fn should_prepare_cached_model_and_report_ready() {
+    let manager = common::get_test_manager();
+    let phases = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&phases);
+
+    let model = manager
+        .prepare(common::TEST_MODEL_ALIAS)
+        .skip_execution_providers()
+        .progress(move |p| sink.lock().unwrap().push(p))
+        .run()
+        .await
+        .expect("prepare failed");
+
+    assert!(
+        model.is_loaded().await.expect("is_loaded check failed"),
+        "Model should be loaded after prepare()"
+    );
+    {
+        let phases = phases.lock().unwrap();
+        assert_eq!(phases.first(), Some(&PrepareProgress::ResolvingModel));
+        assert_eq!(phases.last(), Some(&PrepareProgress::Ready));
+    }
+
+    model.unload().await.expect("model.unload() failed");
+}
@@ -1,4 +1,8 @@
 use super::common;
+use foundry_local_sdk::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    ChatCompletionRequestUserMessage,
+};
 use std::sync::Arc;
 
 // ── Cached model verification ────────────────────────────────────────────────
@@ -53,8 +57,128 @@ async fn should_load_and_unload_model() {
     );
 }
 
+#[tokio::test]
+async fn should_track_residency_across_load_and_unload() {
+    let manager = common::get_test_manager();
+    let model = manager
+        .catalog()
+        .get_model(common::TEST_MODEL_ALIAS)
+        .await
+        .expect("get_model failed");
+    let residency = manager.residency();
+
+    model.load().await.expect("model.load() failed");
+    let resident = residency.resident_models().expect("resident_models failed");
+    assert!(
+        resident.iter().any(|id| id == model.id()),
+        "Loaded model should be tracked as resident"
+    );
+
+    model.unload().await.expect("model.unload() failed");
+    let resident = residency.resident_models().expect("resident_models failed");
+    assert!(
+        !resident.iter().any(|id| id == model.id()),
+        "Unloaded model should no longer be tracked as resident"
+    );
+}
+
+#[tokio::test]
+async fn should_keep_acquired_model_loaded_until_last_guard_drops() {
+    let manager = common::get_test_manager();
+    let model = manager
+        .catalog()
+        .get_model(common::TEST_MODEL_ALIAS)
+        .await
+        .expect("get_model failed");
+
+    let guard = model.acquire().await.expect("acquire failed");
+    let shared = guard.clone();
+    let client = guard.create_chat_client();
+    drop(guard);
+
+    assert!(
+        model.unload().await.is_err(),
+        "unload() should fail while a guard is alive"
+    );
+    drop(shared);
+    drop(client);
+
+    // The unload runs on the runtime after the last guard drops.
+    let mut loaded = true;
+    for _ in 0..50 {
+        loaded = model.is_loaded().await.expect("is_loaded check failed");
+        if !loaded {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(
+        !loaded,
+        "Model should be unloaded after the last guard drops"
+    );
+}
+
+#[tokio::test]
+async fn should_warm_up_loaded_chat_model() {
+    let manager = common::get_test_manager();
+    let model = manager
+        .catalog()
+        .get_model(common::TEST_MODEL_ALIAS)
+        .await
+        .expect("get_model failed");
+
+    model.load().await.expect("model.load() failed");
+    let report = model.warm_up().await.expect("warm_up failed");
+    assert_eq!(report.kind, foundry_local_sdk::WarmUpKind::Chat);
+    assert_eq!(report.model_id, model.id());
+    assert!(report.first_token_latency <= report.total_latency);
+
+    model.unload().await.expect("model.unload() failed");
+}
+
 // ── Introspection ────────────────────────────────────────────────────────────
 
+#[tokio::test]
+async fn should_inspect_cached_model_files() {
+    let model = get_test_model().await;
+    let inspection = model.inspect().await.expect("inspect failed");
+
+    assert_eq!(inspection.model_id, model.id());
+    assert!(
+        !inspection.files.is_empty(),
+        "Model directory should hold files"
+    );
+    assert_eq!(
+        inspection.total_size_bytes,
+        inspection.files.iter().map(|f| f.size_bytes).sum::<u64>()
+    );
+    assert!(
+        inspection.context_length.is_some(),
+        "Chat model should have a genai_config.json with a context length"
+    );
+}
+
+#[tokio::test]
+async fn should_tokenize_without_loading() {
+    let model = get_test_model().await;
+    let tokenizer = model.tokenizer().await.expect("tokenizer failed");
+
+    let text = "The quick brown fox jumps over the lazy dog.";
+    let ids = tokenizer.encode(text);
+    assert!(!ids.is_empty(), "Text should encode to tokens");
+    assert!(ids.len() < text.len(), "Tokens should span several bytes");
+    assert_eq!(tokenizer.decode(&ids), text);
+
+    let messages: Vec<ChatCompletionRequestMessage> = vec![
+        ChatCompletionRequestSystemMessage::from("You are a helpful assistant.").into(),
+        ChatCompletionRequestUserMessage::from(text).into(),
+    ];
+    assert!(
+        tokenizer.count_chat_tokens(&messages) > ids.len(),
+        "Chat count should include the system prompt and template"
+    );
+}
+
 #[tokio::test]
 async fn should_expose_alias() {
     let manager = common::get_test_manager();
@@ -1,5 +1,7 @@
 use super::common;
+use foundry_local_sdk::openai::ResponseStreamEvent;
 use serde_json::json;
+use tokio_stream::StreamExt;
 
 /// Start the web service, make a non-streaming POST to v1/chat/completions,
 /// verify we get a valid response, then stop the service.
@@ -159,3 +161,55 @@ async fn should_expose_urls_after_start() {
         .await
         .expect("stop_web_service failed");
 }
+
+/// Create, stream, fetch and delete responses through the ResponsesClient.
+#[tokio::test]
+async fn should_create_responses_via_responses_client() {
+    let manager = common::get_test_manager();
+    let catalog = manager.catalog();
+    let model = catalog
+        .get_model(common::TEST_MODEL_ALIAS)
+        .await
+        .expect("get_model failed");
+    model.load().await.expect("model.load() failed");
+
+    manager
+        .start_web_service()
+        .await
+        .expect("start_web_service failed");
+    let client = manager
+        .create_responses_client(Some(model.id()))
+        .expect("create_responses_client failed")
+        .instructions("You are a helpful math assistant. Respond with just the answer.")
+        .max_output_tokens(500)
+        .temperature(0.0)
+        .store(true);
+
+    let response = client.create("What is 7*6?").await.expect("create failed");
+    let text = response.output_text();
+    println!("Responses API response: {text}");
+    assert!(text.contains("42"), "Expected '42', got: {text}");
+
+    let stored = client.get(&response.id).await.expect("get failed");
+    assert_eq!(stored.id, response.id);
+    let deleted = client.delete(&response.id).await.expect("delete failed");
+    assert!(deleted.deleted);
+
+    let mut stream = client
+        .create_streaming("What is 7*6?")
+        .await
+        .expect("create_streaming failed");
+    let mut streamed = String::new();
+    while let Some(event) = stream.next().await {
+        if let ResponseStreamEvent::OutputTextDelta { delta, .. } = event.expect("stream error") {
+            streamed.push_str(&delta);
+        }
+    }
+    assert!(streamed.contains("42"), "Expected '42', got: {streamed}");
+
+    manager
+        .stop_web_service()
+        .await
+        .expect("stop_web_service failed");
+    model.unload().await.expect("model.unload() failed");
+}